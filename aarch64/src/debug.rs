@@ -0,0 +1,395 @@
+//! Self-hosted debug at EL1
+//!
+//! The hardware breakpoints and watchpoints, and the software step. The
+//! debug exceptions are taken to EL1 once `Debug::enable` unlocks the OS
+//! Lock and sets `MDSCR_EL1.KDE`, and only while `PSTATE.D` is clear.
+//!
+//! A breakpoint or a watchpoint fires before the instruction completes,
+//! so resuming would hit it again. `Debug::handle` steps over the
+//! instruction with the slots of the kind disabled, and re-enables them
+//! on the step exception.
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering;
+
+use crate::regs::access::ReadWriteRegister;
+use crate::regs::access::ReadableRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::BreakpointControlEl1;
+use crate::regs::DataAbortIss;
+use crate::regs::DebugFeatures0El1;
+use crate::regs::ExceptionClass;
+use crate::regs::ExceptionFrame;
+use crate::regs::ExceptionSource;
+use crate::regs::ExceptionSyndromeEl1;
+use crate::regs::MonitorDebugSystemControlEl1;
+use crate::regs::OsLockAccessEl1;
+use crate::regs::SavedProgramStateEl1;
+use crate::regs::WatchpointControlEl1;
+
+/// The architecture allows 16 breakpoints and 16 watchpoints.
+pub const MAX_SLOTS: usize = 16;
+
+/// `PMC` and `PAC` matching at EL1 only, with `HMC` and `SSC` clear.
+const EL1: u64 = 0b01;
+/// All four bytes of an A64 instruction.
+const BAS_A64: u64 = 0b1111;
+
+/// The accesses a watchpoint fires on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u64)]
+pub enum WatchAccess {
+    Load = 0b01,
+    Store = 0b10,
+    Any = 0b11,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugError {
+    /// The implementation has fewer slots.
+    NoSlot { slot: usize, slots: usize },
+    /// Breakpoints take 4-byte aligned addresses.
+    Unaligned(u64),
+    /// Watchpoints cover up to 8 bytes within a doubleword, or a power of
+    /// two of bytes aligned to the size.
+    Length { address: u64, len: usize },
+}
+
+impl core::fmt::Display for DebugError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DebugError::NoSlot { slot, slots } => write!(f, "no slot {slot}, only {slots}"),
+            DebugError::Unaligned(address) => write!(f, "unaligned breakpoint {address:#x}"),
+            DebugError::Length { address, len } => {
+                write!(f, "cannot watch {len:#x} bytes at {address:#x}")
+            }
+        }
+    }
+}
+
+/// A debug exception taken from EL1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugEvent {
+    Breakpoint {
+        pc: u64,
+    },
+    Watchpoint {
+        pc: u64,
+        address: u64,
+        write: bool,
+    },
+    /// The instruction before `pc` has been stepped.
+    Step {
+        pc: u64,
+    },
+    /// The BRK instruction
+    Brk {
+        pc: u64,
+        comment: u16,
+    },
+}
+
+/// How to return from a debug exception
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    Continue,
+    /// Take a step exception after the next instruction.
+    Step,
+}
+
+/// The DBGBCR<n>_EL1, DBGBVR<n>_EL1, DBGWCR<n>_EL1 and DBGWVR<n>_EL1
+/// accessors, the register names must be known to the assembler, hence
+/// the match.
+macro_rules! indexed_access {
+    ($($index:literal => $bcr:ident $bvr:ident $wcr:ident $wvr:ident,)+) => {
+        fn load_breakpoint(index: usize) -> (BreakpointControlEl1, u64) {
+            match index {
+                $($index => (
+                    crate::load_sys_reg!($bcr).into(),
+                    crate::load_sys_reg!($bvr),
+                ),)+
+                _ => panic!("no breakpoint {index}"),
+            }
+        }
+
+        fn store_breakpoint(index: usize, control: BreakpointControlEl1, value: u64) {
+            let control: u64 = control.into();
+            match index {
+                $($index => {
+                    crate::store_sys_reg!($bvr, value);
+                    crate::store_sys_reg!($bcr, control);
+                })+
+                _ => panic!("no breakpoint {index}"),
+            }
+        }
+
+        fn load_watchpoint(index: usize) -> (WatchpointControlEl1, u64) {
+            match index {
+                $($index => (
+                    crate::load_sys_reg!($wcr).into(),
+                    crate::load_sys_reg!($wvr),
+                ),)+
+                _ => panic!("no watchpoint {index}"),
+            }
+        }
+
+        fn store_watchpoint(index: usize, control: WatchpointControlEl1, value: u64) {
+            let control: u64 = control.into();
+            match index {
+                $($index => {
+                    crate::store_sys_reg!($wvr, value);
+                    crate::store_sys_reg!($wcr, control);
+                })+
+                _ => panic!("no watchpoint {index}"),
+            }
+        }
+    };
+}
+
+indexed_access! {
+    0 => DBGBCR0_EL1 DBGBVR0_EL1 DBGWCR0_EL1 DBGWVR0_EL1,
+    1 => DBGBCR1_EL1 DBGBVR1_EL1 DBGWCR1_EL1 DBGWVR1_EL1,
+    2 => DBGBCR2_EL1 DBGBVR2_EL1 DBGWCR2_EL1 DBGWVR2_EL1,
+    3 => DBGBCR3_EL1 DBGBVR3_EL1 DBGWCR3_EL1 DBGWVR3_EL1,
+    4 => DBGBCR4_EL1 DBGBVR4_EL1 DBGWCR4_EL1 DBGWVR4_EL1,
+    5 => DBGBCR5_EL1 DBGBVR5_EL1 DBGWCR5_EL1 DBGWVR5_EL1,
+    6 => DBGBCR6_EL1 DBGBVR6_EL1 DBGWCR6_EL1 DBGWVR6_EL1,
+    7 => DBGBCR7_EL1 DBGBVR7_EL1 DBGWCR7_EL1 DBGWVR7_EL1,
+    8 => DBGBCR8_EL1 DBGBVR8_EL1 DBGWCR8_EL1 DBGWVR8_EL1,
+    9 => DBGBCR9_EL1 DBGBVR9_EL1 DBGWCR9_EL1 DBGWVR9_EL1,
+    10 => DBGBCR10_EL1 DBGBVR10_EL1 DBGWCR10_EL1 DBGWVR10_EL1,
+    11 => DBGBCR11_EL1 DBGBVR11_EL1 DBGWCR11_EL1 DBGWVR11_EL1,
+    12 => DBGBCR12_EL1 DBGBVR12_EL1 DBGWCR12_EL1 DBGWVR12_EL1,
+    13 => DBGBCR13_EL1 DBGBVR13_EL1 DBGWCR13_EL1 DBGWVR13_EL1,
+    14 => DBGBCR14_EL1 DBGBVR14_EL1 DBGWCR14_EL1 DBGWVR14_EL1,
+    15 => DBGBCR15_EL1 DBGBVR15_EL1 DBGWCR15_EL1 DBGWVR15_EL1,
+}
+
+/// The debug state of the current CPU, can live in a `static`.
+#[derive(Debug)]
+pub struct Debug {
+    /// The slots disabled for the step over, breakpoints in the bits
+    /// [15:0] and watchpoints in the bits [31:16].
+    step_over: AtomicU32,
+    /// A step was asked for with `Resume::Step`.
+    stepping: AtomicBool,
+}
+
+impl Default for Debug {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug {
+    pub const fn new() -> Self {
+        Self {
+            step_over: AtomicU32::new(0),
+            stepping: AtomicBool::new(false),
+        }
+    }
+
+    /// The number of the breakpoints, from `ID_AA64DFR0_EL1.BRPs`
+    pub fn breakpoints(&self) -> usize {
+        DebugFeatures0El1::read().brps() as usize + 1
+    }
+
+    /// The number of the watchpoints, from `ID_AA64DFR0_EL1.WRPs`
+    pub fn watchpoints(&self) -> usize {
+        DebugFeatures0El1::read().wrps() as usize + 1
+    }
+
+    /// Unlocks the OS Lock and enables the breakpoints, the watchpoints
+    /// and the software step at EL1. All slots are cleared.
+    pub fn enable(&self) {
+        OsLockAccessEl1::new().with_oslk(false).store();
+        for slot in 0..self.breakpoints() {
+            store_breakpoint(slot, BreakpointControlEl1::new(), 0);
+        }
+        for slot in 0..self.watchpoints() {
+            store_watchpoint(slot, WatchpointControlEl1::new(), 0);
+        }
+        MonitorDebugSystemControlEl1::modify(|mdscr| {
+            mdscr.with_mde(true).with_kde(true).with_ss(false)
+        });
+        self.step_over.store(0, Ordering::Relaxed);
+        self.stepping.store(false, Ordering::Relaxed);
+    }
+
+    fn check_slot(slot: usize, slots: usize) -> Result<(), DebugError> {
+        if slot < slots {
+            Ok(())
+        } else {
+            Err(DebugError::NoSlot { slot, slots })
+        }
+    }
+
+    /// Fires on the instruction at `address` executed at EL1.
+    pub fn set_breakpoint(&self, slot: usize, address: u64) -> Result<(), DebugError> {
+        Self::check_slot(slot, self.breakpoints())?;
+        if address & 3 != 0 {
+            return Err(DebugError::Unaligned(address));
+        }
+        let control = BreakpointControlEl1::new()
+            .with_e(true)
+            .with_pmc(EL1)
+            .with_bas(BAS_A64);
+        store_breakpoint(slot, control, address);
+        Ok(())
+    }
+
+    pub fn clear_breakpoint(&self, slot: usize) -> Result<(), DebugError> {
+        Self::check_slot(slot, self.breakpoints())?;
+        store_breakpoint(slot, BreakpointControlEl1::new(), 0);
+        Ok(())
+    }
+
+    /// Fires on the accesses at EL1 to the `len` bytes at `address`.
+    pub fn set_watchpoint(
+        &self,
+        slot: usize,
+        address: u64,
+        len: usize,
+        access: WatchAccess,
+    ) -> Result<(), DebugError> {
+        Self::check_slot(slot, self.watchpoints())?;
+        let offset = (address & 7) as usize;
+        let (value, bas, mask) = if len > 0 && offset + len <= 8 {
+            (address & !7, ((1 << len) - 1) << offset, 0)
+        } else if len > 8 && len.is_power_of_two() && address & (len as u64 - 1) == 0 {
+            // MASK is 3 to 31, covering the doublewords of the region.
+            (address, 0xff, len.trailing_zeros() as u64)
+        } else {
+            return Err(DebugError::Length { address, len });
+        };
+        if mask > 31 {
+            return Err(DebugError::Length { address, len });
+        }
+        let control = WatchpointControlEl1::new()
+            .with_e(true)
+            .with_pac(EL1)
+            .with_lsc(access as u64)
+            .with_bas(bas)
+            .with_mask(mask);
+        store_watchpoint(slot, control, value);
+        Ok(())
+    }
+
+    pub fn clear_watchpoint(&self, slot: usize) -> Result<(), DebugError> {
+        Self::check_slot(slot, self.watchpoints())?;
+        store_watchpoint(slot, WatchpointControlEl1::new(), 0);
+        Ok(())
+    }
+
+    /// Makes the exception return step one instruction.
+    pub fn step(&self, frame: &mut ExceptionFrame) {
+        MonitorDebugSystemControlEl1::modify(|mdscr| mdscr.with_ss(true));
+        let spsr = SavedProgramStateEl1::from(frame.spsr as u64).with_ss(true);
+        frame.spsr = u64::from(spsr) as usize;
+    }
+
+    fn stop_stepping(&self, frame: &mut ExceptionFrame) {
+        MonitorDebugSystemControlEl1::modify(|mdscr| mdscr.with_ss(false));
+        let spsr = SavedProgramStateEl1::from(frame.spsr as u64).with_ss(false);
+        frame.spsr = u64::from(spsr) as usize;
+    }
+
+    /// Disables the enabled slots of the kind for a step, returns the
+    /// mask of them.
+    fn disable_breakpoints(&self) -> u32 {
+        let mut disabled = 0;
+        for slot in 0..self.breakpoints() {
+            let (control, value) = load_breakpoint(slot);
+            if control.e() {
+                store_breakpoint(slot, control.with_e(false), value);
+                disabled |= 1 << slot;
+            }
+        }
+        disabled
+    }
+
+    fn disable_watchpoints(&self) -> u32 {
+        let mut disabled = 0;
+        for slot in 0..self.watchpoints() {
+            let (control, value) = load_watchpoint(slot);
+            if control.e() {
+                store_watchpoint(slot, control.with_e(false), value);
+                disabled |= 1 << slot;
+            }
+        }
+        disabled
+    }
+
+    fn reenable(&self, step_over: u32) {
+        for slot in 0..MAX_SLOTS {
+            if step_over & (1 << slot) != 0 {
+                let (control, value) = load_breakpoint(slot);
+                store_breakpoint(slot, control.with_e(true), value);
+            }
+            if step_over & (1 << (slot + MAX_SLOTS)) != 0 {
+                let (control, value) = load_watchpoint(slot);
+                store_watchpoint(slot, control.with_e(true), value);
+            }
+        }
+    }
+
+    /// Handles a debug exception taken from EL1: calls `f` with the event
+    /// and sets up the return. Returns `false` for the other exceptions,
+    /// the asynchronous ones included as `esr` is stale for them. The step
+    /// over a breakpoint or a watchpoint is not reported.
+    pub fn handle(
+        &self,
+        frame: &mut ExceptionFrame,
+        esr: ExceptionSyndromeEl1,
+        far: u64,
+        f: impl FnOnce(DebugEvent) -> Resume,
+    ) -> bool {
+        if { frame.source } != ExceptionSource::Synchronous {
+            return false;
+        }
+        let pc = frame.elr as u64;
+        let event = match esr.ec() {
+            ExceptionClass::BreakpointSameEl => DebugEvent::Breakpoint { pc },
+            ExceptionClass::WatchpointSameEl => DebugEvent::Watchpoint {
+                pc,
+                address: far,
+                write: DataAbortIss::from(esr.iss()).wnr(),
+            },
+            ExceptionClass::StepSameEl => {
+                self.reenable(self.step_over.swap(0, Ordering::Relaxed));
+                if !self.stepping.load(Ordering::Relaxed) {
+                    self.stop_stepping(frame);
+                    return true;
+                }
+                DebugEvent::Step { pc }
+            }
+            ExceptionClass::Brk64bit => DebugEvent::Brk {
+                pc,
+                comment: esr.iss() as u16,
+            },
+            _ => return false,
+        };
+
+        let resume = f(event);
+        self.stepping
+            .store(resume == Resume::Step, Ordering::Relaxed);
+        let step_over = match event {
+            DebugEvent::Breakpoint { .. } => self.disable_breakpoints(),
+            DebugEvent::Watchpoint { .. } => self.disable_watchpoints() << MAX_SLOTS,
+            DebugEvent::Brk { .. } => {
+                frame.elr += 4;
+                0
+            }
+            DebugEvent::Step { .. } => 0,
+        };
+        self.step_over.store(step_over, Ordering::Relaxed);
+
+        if resume == Resume::Step || step_over != 0 {
+            self.step(frame);
+        } else {
+            self.stop_stepping(frame);
+        }
+        true
+    }
+}
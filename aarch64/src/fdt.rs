@@ -0,0 +1,382 @@
+//! A minimal reader of the Flattened Device Tree blob (DTB) the firmware
+//! or the loader passes in `x0`.
+//!
+//! Only walks the structure block, enough to find the memory nodes, the
+//! reserved memory and the `reg` properties of the devices.
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_HEADER_SIZE: usize = 40;
+const FDT_LAST_COMP_VERSION: u32 = 16;
+
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+const FDT_END: u32 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdtError {
+    BadMagic(u32),
+    UnsupportedVersion(u32),
+    /// The blob or one of its blocks ends prematurely.
+    Truncated,
+    /// An unknown token at the offset in the structure block.
+    BadToken {
+        offset: usize,
+        token: u32,
+    },
+    /// An end of node at the offset in the structure block with no node
+    /// to end.
+    UnbalancedEndNode(usize),
+}
+
+impl core::fmt::Display for FdtError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FdtError::BadMagic(magic) => write!(f, "bad DTB magic {magic:#x}"),
+            FdtError::UnsupportedVersion(version) => {
+                write!(f, "unsupported DTB version {version}")
+            }
+            FdtError::Truncated => write!(f, "truncated DTB"),
+            FdtError::BadToken { offset, token } => {
+                write!(f, "bad DTB token {token:#x} at offset {offset:#x}")
+            }
+            FdtError::UnbalancedEndNode(offset) => {
+                write!(f, "DTB node end without a node at offset {offset:#x}")
+            }
+        }
+    }
+}
+
+fn be32(bytes: &[u8], offset: usize) -> Result<u32, FdtError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(FdtError::Truncated)
+}
+
+fn be64(bytes: &[u8], offset: usize) -> Result<u64, FdtError> {
+    Ok(((be32(bytes, offset)? as u64) << 32) | be32(bytes, offset + 4)? as u64)
+}
+
+/// A NUL-terminated string at the offset, without the NUL.
+fn c_str(bytes: &[u8], offset: usize) -> Result<&[u8], FdtError> {
+    let tail = bytes.get(offset..).ok_or(FdtError::Truncated)?;
+    let len = tail
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(FdtError::Truncated)?;
+    Ok(&tail[..len])
+}
+
+const fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fdt<'a> {
+    blob: &'a [u8],
+    structs: &'a [u8],
+    strings: &'a [u8],
+    mem_rsvmap: usize,
+}
+
+impl<'a> Fdt<'a> {
+    pub fn new(blob: &'a [u8]) -> Result<Self, FdtError> {
+        let magic = be32(blob, 0)?;
+        if magic != FDT_MAGIC {
+            return Err(FdtError::BadMagic(magic));
+        }
+        if blob.len() < FDT_HEADER_SIZE {
+            return Err(FdtError::Truncated);
+        }
+        let total_size = be32(blob, 4)? as usize;
+        let off_dt_struct = be32(blob, 8)? as usize;
+        let off_dt_strings = be32(blob, 12)? as usize;
+        let off_mem_rsvmap = be32(blob, 16)? as usize;
+        let last_comp_version = be32(blob, 24)?;
+        let size_dt_strings = be32(blob, 32)? as usize;
+        let size_dt_struct = be32(blob, 36)? as usize;
+
+        if last_comp_version > FDT_LAST_COMP_VERSION {
+            return Err(FdtError::UnsupportedVersion(last_comp_version));
+        }
+        let blob = blob.get(..total_size).ok_or(FdtError::Truncated)?;
+
+        Ok(Self {
+            blob,
+            structs: blob
+                .get(off_dt_struct..off_dt_struct + size_dt_struct)
+                .ok_or(FdtError::Truncated)?,
+            strings: blob
+                .get(off_dt_strings..off_dt_strings + size_dt_strings)
+                .ok_or(FdtError::Truncated)?,
+            mem_rsvmap: off_mem_rsvmap,
+        })
+    }
+
+    /// # Safety
+    ///
+    /// The pointer must point to a DTB that stays valid and unchanged
+    /// for the lifetime `'a`.
+    pub unsafe fn from_ptr(ptr: *const u8) -> Result<Self, FdtError> {
+        // SAFETY: the header is there as promised by the caller.
+        let header = unsafe { core::slice::from_raw_parts(ptr, FDT_HEADER_SIZE) };
+        let magic = be32(header, 0)?;
+        if magic != FDT_MAGIC {
+            return Err(FdtError::BadMagic(magic));
+        }
+        let total_size = be32(header, 4)? as usize;
+        Self::new(unsafe { core::slice::from_raw_parts(ptr, total_size) })
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.blob.len()
+    }
+
+    /// The `(address, size)` entries of the memory reservation block.
+    pub fn memory_reservations(&self) -> MemoryReservations<'a> {
+        MemoryReservations {
+            blob: self.blob,
+            offset: self.mem_rsvmap,
+        }
+    }
+
+    /// The depth-first walk of the structure block.
+    pub fn tokens(&self) -> Tokens<'a> {
+        Tokens {
+            structs: self.structs,
+            strings: self.strings,
+            offset: 0,
+            depth: 0,
+            done: false,
+        }
+    }
+
+    /// The first node with `compatible` among its compatible strings.
+    pub fn find_compatible(&self, compatible: &[u8]) -> Result<Option<Device<'a>>, FdtError> {
+        // The cells of the node at each depth for its children, the root
+        // is at depth 1. A node without them has 2 and 1.
+        let mut address_cells = [2; MAX_DEPTH];
+        let mut size_cells = [1; MAX_DEPTH];
+        let mut depth = 0;
+        // The properties come before the subnodes, the node in progress
+        // is complete at its first subnode or at its end.
+        let mut device: Option<(usize, Device<'a>)> = None;
+        let mut is_compatible = false;
+
+        for token in self.tokens() {
+            let token = token?;
+            if is_compatible && matches!(token, Token::BeginNode(_) | Token::EndNode) {
+                return Ok(device.map(|(_, device)| device));
+            }
+            match token {
+                Token::BeginNode(name) => {
+                    depth += 1;
+                    if depth < MAX_DEPTH {
+                        address_cells[depth] = 2;
+                        size_cells[depth] = 1;
+                    }
+                    device = Some((
+                        depth,
+                        Device {
+                            name,
+                            reg: &[],
+                            interrupts: &[],
+                            address_cells: address_cells[(depth - 1).min(MAX_DEPTH - 1)],
+                            size_cells: size_cells[(depth - 1).min(MAX_DEPTH - 1)],
+                        },
+                    ));
+                }
+                Token::EndNode => {
+                    depth -= 1;
+                    device = None;
+                }
+                Token::Property { name, value } => {
+                    if depth < MAX_DEPTH && name == b"#address-cells" {
+                        address_cells[depth] = cells(value).unwrap_or(2);
+                    } else if depth < MAX_DEPTH && name == b"#size-cells" {
+                        size_cells[depth] = cells(value).unwrap_or(1);
+                    }
+                    let Some((_, device)) = device.as_mut() else {
+                        continue;
+                    };
+                    match name {
+                        b"compatible" => {
+                            is_compatible = value.split(|&b| b == 0).any(|c| c == compatible)
+                        }
+                        b"reg" => device.reg = value,
+                        b"interrupts" => device.interrupts = value,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The deepest nodes whose cells are tracked
+const MAX_DEPTH: usize = 16;
+
+/// A device node, found by `Fdt::find_compatible`
+#[derive(Debug, Clone, Copy)]
+pub struct Device<'a> {
+    /// The node name with the unit address
+    pub name: &'a [u8],
+    /// The `reg` property value, empty if none
+    pub reg: &'a [u8],
+    /// The `interrupts` property value, empty if none
+    pub interrupts: &'a [u8],
+    /// The `#address-cells` of the parent
+    pub address_cells: usize,
+    /// The `#size-cells` of the parent
+    pub size_cells: usize,
+}
+
+impl<'a> Device<'a> {
+    /// The `(address, size)` entries of `reg`.
+    pub fn reg(&self) -> impl Iterator<Item = (u64, u64)> + 'a {
+        reg_entries(self.reg, self.address_cells, self.size_cells)
+    }
+
+    /// The cells of `interrupts`, their meaning is up to the interrupt
+    /// controller.
+    pub fn interrupts(&self) -> impl Iterator<Item = u32> + 'a {
+        self.interrupts
+            .as_chunks::<4>()
+            .0
+            .iter()
+            .map(|&c| u32::from_be_bytes(c))
+    }
+}
+
+pub struct MemoryReservations<'a> {
+    blob: &'a [u8],
+    offset: usize,
+}
+
+impl Iterator for MemoryReservations<'_> {
+    type Item = Result<(u64, u64), FdtError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = be64(self.blob, self.offset)
+            .and_then(|address| Ok((address, be64(self.blob, self.offset + 8)?)));
+        match entry {
+            Ok((0, 0)) => None,
+            Ok(entry) => {
+                self.offset += 16;
+                Some(Ok(entry))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// The node name with the unit address, empty for the root node.
+    BeginNode(&'a [u8]),
+    EndNode,
+    Property {
+        name: &'a [u8],
+        value: &'a [u8],
+    },
+}
+
+pub struct Tokens<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+    offset: usize,
+    /// The nodes begun and not ended yet.
+    depth: usize,
+    done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, FdtError> {
+        loop {
+            let token = be32(self.structs, self.offset)?;
+            let token_offset = self.offset;
+            self.offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = c_str(self.structs, self.offset)?;
+                    self.offset = align4(self.offset + name.len() + 1);
+                    self.depth += 1;
+                    return Ok(Some(Token::BeginNode(name)));
+                }
+                FDT_END_NODE => {
+                    self.depth = self
+                        .depth
+                        .checked_sub(1)
+                        .ok_or(FdtError::UnbalancedEndNode(token_offset))?;
+                    return Ok(Some(Token::EndNode));
+                }
+                FDT_PROP => {
+                    let len = be32(self.structs, self.offset)? as usize;
+                    let name_offset = be32(self.structs, self.offset + 4)? as usize;
+                    let value_offset = self.offset + 8;
+                    let value = self
+                        .structs
+                        .get(value_offset..value_offset + len)
+                        .ok_or(FdtError::Truncated)?;
+                    self.offset = align4(value_offset + len);
+                    return Ok(Some(Token::Property {
+                        name: c_str(self.strings, name_offset)?,
+                        value,
+                    }));
+                }
+                FDT_NOP => continue,
+                FDT_END => return Ok(None),
+                _ => {
+                    return Err(FdtError::BadToken {
+                        offset: token_offset,
+                        token,
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, FdtError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = self.next_token().transpose();
+        self.done = !matches!(token, Some(Ok(_)));
+        token
+    }
+}
+
+/// Reads a `#address-cells` or `#size-cells` property value.
+pub fn cells(value: &[u8]) -> Option<usize> {
+    be32(value, 0).ok().map(|c| c as usize)
+}
+
+/// Splits a `reg` property value into `(address, size)` pairs given
+/// the `#address-cells` and `#size-cells` of the parent node.
+pub fn reg_entries(
+    value: &[u8],
+    address_cells: usize,
+    size_cells: usize,
+) -> impl Iterator<Item = (u64, u64)> + '_ {
+    // The chunks have all the cells, reading them cannot fail.
+    let read_cells = |chunk: &[u8], cells: usize| {
+        (0..cells).fold(0u64, |acc, i| {
+            (acc << 32) | be32(chunk, i * 4).unwrap_or_default() as u64
+        })
+    };
+    value
+        .chunks_exact((address_cells + size_cells).max(1) * 4)
+        .map(move |chunk| {
+            (
+                read_cells(chunk, address_cells),
+                read_cells(&chunk[address_cells * 4..], size_cells),
+            )
+        })
+}
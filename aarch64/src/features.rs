@@ -0,0 +1,353 @@
+//! CPU feature summary
+//!
+//! Collects the ID registers once and answers whether a named feature
+//! is implemented, so the callers do not compare the raw fields.
+
+use crate::regs::access::ReadableRegister;
+use crate::regs::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Fp,
+    AdvSimd,
+    Fp16,
+    Ras,
+    Sve,
+    Sve2,
+    Sme,
+    Sme2,
+    Sel2,
+    Amu,
+    Dit,
+    Csv2,
+    Csv3,
+    Bti,
+    Ssbs,
+    Mte,
+    Mte2,
+    Mte3,
+    Nmi,
+    Gcs,
+    Aes,
+    Pmull,
+    Sha1,
+    Sha256,
+    Sha512,
+    Sha3,
+    Sm3,
+    Sm4,
+    Crc32,
+    Lse,
+    Lse128,
+    Rdm,
+    DotProd,
+    Fhm,
+    FlagM,
+    FlagM2,
+    TlbiOs,
+    TlbiRange,
+    Rng,
+    Dpb,
+    Dpb2,
+    PAuth,
+    PAuth2,
+    Fpac,
+    PAuthGeneric,
+    Jscvt,
+    Fcma,
+    Lrcpc,
+    Lrcpc2,
+    Lrcpc3,
+    FrintTs,
+    Sb,
+    SpecRes,
+    Bf16,
+    I8mm,
+    Xs,
+    Ls64,
+    Wfxt,
+    Mops,
+    Hbc,
+    Cssc,
+    Lpa,
+    Lpa2,
+    Ecv,
+    Fgt,
+    HaFlag,
+    HdFlag,
+    Vhe,
+    Pan,
+    Lor,
+    Cnp,
+    Uao,
+    Lva,
+    Tcr2,
+    S1Pie,
+    S1Poe,
+    D128,
+    Pmu,
+    Spe,
+    Trbe,
+    Brbe,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 81] = [
+        Feature::Fp,
+        Feature::AdvSimd,
+        Feature::Fp16,
+        Feature::Ras,
+        Feature::Sve,
+        Feature::Sve2,
+        Feature::Sme,
+        Feature::Sme2,
+        Feature::Sel2,
+        Feature::Amu,
+        Feature::Dit,
+        Feature::Csv2,
+        Feature::Csv3,
+        Feature::Bti,
+        Feature::Ssbs,
+        Feature::Mte,
+        Feature::Mte2,
+        Feature::Mte3,
+        Feature::Nmi,
+        Feature::Gcs,
+        Feature::Aes,
+        Feature::Pmull,
+        Feature::Sha1,
+        Feature::Sha256,
+        Feature::Sha512,
+        Feature::Sha3,
+        Feature::Sm3,
+        Feature::Sm4,
+        Feature::Crc32,
+        Feature::Lse,
+        Feature::Lse128,
+        Feature::Rdm,
+        Feature::DotProd,
+        Feature::Fhm,
+        Feature::FlagM,
+        Feature::FlagM2,
+        Feature::TlbiOs,
+        Feature::TlbiRange,
+        Feature::Rng,
+        Feature::Dpb,
+        Feature::Dpb2,
+        Feature::PAuth,
+        Feature::PAuth2,
+        Feature::Fpac,
+        Feature::PAuthGeneric,
+        Feature::Jscvt,
+        Feature::Fcma,
+        Feature::Lrcpc,
+        Feature::Lrcpc2,
+        Feature::Lrcpc3,
+        Feature::FrintTs,
+        Feature::Sb,
+        Feature::SpecRes,
+        Feature::Bf16,
+        Feature::I8mm,
+        Feature::Xs,
+        Feature::Ls64,
+        Feature::Wfxt,
+        Feature::Mops,
+        Feature::Hbc,
+        Feature::Cssc,
+        Feature::Lpa,
+        Feature::Lpa2,
+        Feature::Ecv,
+        Feature::Fgt,
+        Feature::HaFlag,
+        Feature::HdFlag,
+        Feature::Vhe,
+        Feature::Pan,
+        Feature::Lor,
+        Feature::Cnp,
+        Feature::Uao,
+        Feature::Lva,
+        Feature::Tcr2,
+        Feature::S1Pie,
+        Feature::S1Poe,
+        Feature::D128,
+        Feature::Pmu,
+        Feature::Spe,
+        Feature::Trbe,
+        Feature::Brbe,
+    ];
+}
+
+/// The field value is at least `min`, the usual way the ID register
+/// fields tell that a feature is implemented.
+fn at_least(field: impl Into<u64>, min: u64) -> bool {
+    field.into() >= min
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CpuFeatures {
+    pub pfr0: ProcessorFeatures0El1,
+    pub pfr1: ProcessorFeatures1El1,
+    pub mmfr0: MmFeatures0El1,
+    pub mmfr1: MmFeatures1El1,
+    pub mmfr2: MmFeatures2El1,
+    pub mmfr3: MmFeatures3El1,
+    pub isar0: InstructionSetFeatures0El1,
+    pub isar1: InstructionSetFeatures1El1,
+    pub isar2: InstructionSetFeatures2El1,
+    pub dfr0: DebugFeatures0El1,
+    pub zfr0: SveFeatures0El1,
+    pub smfr0: SmeFeatures0El1,
+}
+
+impl Default for CpuFeatures {
+    /// Nothing beyond the base architecture.
+    fn default() -> Self {
+        Self {
+            pfr0: ProcessorFeatures0El1::new(),
+            pfr1: ProcessorFeatures1El1::new(),
+            mmfr0: MmFeatures0El1::new(),
+            mmfr1: MmFeatures1El1::new(),
+            mmfr2: MmFeatures2El1::new(),
+            mmfr3: MmFeatures3El1::new(),
+            isar0: InstructionSetFeatures0El1::new(),
+            isar1: InstructionSetFeatures1El1::new(),
+            isar2: InstructionSetFeatures2El1::new(),
+            dfr0: DebugFeatures0El1::new(),
+            zfr0: SveFeatures0El1::new(),
+            smfr0: SmeFeatures0El1::new(),
+        }
+    }
+}
+
+impl CpuFeatures {
+    /// Reads the ID registers of the current CPU.
+    pub fn detect() -> Self {
+        let mut features = Self {
+            pfr0: ProcessorFeatures0El1::read(),
+            pfr1: ProcessorFeatures1El1::read(),
+            mmfr0: MmFeatures0El1::read(),
+            mmfr1: MmFeatures1El1::read(),
+            mmfr2: MmFeatures2El1::read(),
+            mmfr3: MmFeatures3El1::read(),
+            isar0: InstructionSetFeatures0El1::read(),
+            isar1: InstructionSetFeatures1El1::read(),
+            isar2: InstructionSetFeatures2El1::read(),
+            dfr0: DebugFeatures0El1::read(),
+            ..Self::default()
+        };
+        // These read as zero without SVE and SME, but the encodings might
+        // not be known to the CPUs that predate them.
+        if features.has(Feature::Sve) {
+            features.zfr0 = SveFeatures0El1::read();
+        }
+        if features.has(Feature::Sme) {
+            features.smfr0 = SmeFeatures0El1::read();
+        }
+
+        features
+    }
+
+    pub fn has(&self, feature: Feature) -> bool {
+        let (pfr0, pfr1) = (&self.pfr0, &self.pfr1);
+        let (mmfr0, mmfr1, mmfr2, mmfr3) = (&self.mmfr0, &self.mmfr1, &self.mmfr2, &self.mmfr3);
+        let (isar0, isar1, isar2) = (&self.isar0, &self.isar1, &self.isar2);
+        let pauth = |min| {
+            at_least(isar1.apa(), min) || at_least(isar1.api(), min) || at_least(isar2.apa3(), min)
+        };
+
+        match feature {
+            // 0b1111 means not implemented for these two.
+            Feature::Fp => pfr0.fp() != 0b1111,
+            Feature::AdvSimd => pfr0.adv_simd() != 0b1111,
+            Feature::Fp16 => pfr0.fp() == 0b0001,
+            Feature::Ras => pfr0.ras() >= 1,
+            Feature::Sve => pfr0.sve() >= 1,
+            Feature::Sve2 => pfr0.sve() >= 1 && at_least(self.zfr0.sve_ver(), 1),
+            Feature::Sme => pfr1.sme() >= 1,
+            Feature::Sme2 => pfr1.sme() >= 2,
+            Feature::Sel2 => pfr0.sel2() >= 1,
+            Feature::Amu => pfr0.amu() >= 1,
+            Feature::Dit => pfr0.dit() >= 1,
+            Feature::Csv2 => pfr0.csv2() >= 1,
+            Feature::Csv3 => pfr0.csv3() >= 1,
+            Feature::Bti => pfr1.bt() >= 1,
+            Feature::Ssbs => pfr1.ssbs() >= 1,
+            Feature::Mte => pfr1.mte() >= 1,
+            Feature::Mte2 => pfr1.mte() >= 2,
+            Feature::Mte3 => pfr1.mte() >= 3,
+            Feature::Nmi => pfr1.nmi() >= 1,
+            Feature::Gcs => pfr1.gcs() >= 1,
+            Feature::Aes => at_least(isar0.aes(), 1),
+            Feature::Pmull => at_least(isar0.aes(), 2),
+            Feature::Sha1 => at_least(isar0.sha1(), 1),
+            Feature::Sha256 => at_least(isar0.sha2(), 1),
+            Feature::Sha512 => at_least(isar0.sha2(), 2),
+            Feature::Sha3 => at_least(isar0.sha3(), 1),
+            Feature::Sm3 => at_least(isar0.sm3(), 1),
+            Feature::Sm4 => at_least(isar0.sm4(), 1),
+            Feature::Crc32 => at_least(isar0.crc32(), 1),
+            Feature::Lse => at_least(isar0.atomic(), 2),
+            Feature::Lse128 => at_least(isar0.atomic(), 3),
+            Feature::Rdm => at_least(isar0.rdm(), 1),
+            Feature::DotProd => at_least(isar0.dp(), 1),
+            Feature::Fhm => at_least(isar0.fhm(), 1),
+            Feature::FlagM => at_least(isar0.ts(), 1),
+            Feature::FlagM2 => at_least(isar0.ts(), 2),
+            Feature::TlbiOs => at_least(isar0.tlb(), 1),
+            Feature::TlbiRange => at_least(isar0.tlb(), 2),
+            Feature::Rng => at_least(isar0.rndr(), 1),
+            Feature::Dpb => at_least(isar1.dpb(), 1),
+            Feature::Dpb2 => at_least(isar1.dpb(), 2),
+            Feature::PAuth => pauth(1),
+            Feature::PAuth2 => pauth(3),
+            Feature::Fpac => pauth(4),
+            Feature::PAuthGeneric => {
+                at_least(isar1.gpa(), 1) || at_least(isar1.gpi(), 1) || at_least(isar2.gpa3(), 1)
+            }
+            Feature::Jscvt => at_least(isar1.jscvt(), 1),
+            Feature::Fcma => at_least(isar1.fcma(), 1),
+            Feature::Lrcpc => at_least(isar1.lrcpc(), 1),
+            Feature::Lrcpc2 => at_least(isar1.lrcpc(), 2),
+            Feature::Lrcpc3 => at_least(isar1.lrcpc(), 3),
+            Feature::FrintTs => at_least(isar1.frintts(), 1),
+            Feature::Sb => at_least(isar1.sb(), 1),
+            Feature::SpecRes => at_least(isar1.specres(), 1),
+            Feature::Bf16 => at_least(isar1.bf16(), 1),
+            Feature::I8mm => at_least(isar1.i8mm(), 1),
+            Feature::Xs => at_least(isar1.xs(), 1),
+            Feature::Ls64 => at_least(isar1.ls64(), 1),
+            Feature::Wfxt => at_least(isar2.wfxt(), 2),
+            Feature::Mops => at_least(isar2.mops(), 1),
+            Feature::Hbc => at_least(isar2.bc(), 1),
+            Feature::Cssc => at_least(isar2.cssc(), 1),
+            Feature::Lpa => matches!(
+                mmfr0.pa_range(),
+                MmfPaRange::_52_bits_4PB | MmfPaRange::_56_bits_64PB
+            ),
+            Feature::Lpa2 => matches!(mmfr0.t_gran4(), MmfTGran4KB::Yes_52bit),
+            Feature::Ecv => mmfr0.ecv() >= 1,
+            Feature::Fgt => mmfr0.fgt() >= 1,
+            Feature::HaFlag => mmfr1.hafdbs() >= 1,
+            Feature::HdFlag => mmfr1.hafdbs() >= 2,
+            Feature::Vhe => mmfr1.vh() >= 1,
+            Feature::Pan => mmfr1.pan() >= 1,
+            Feature::Lor => mmfr1.lo() >= 1,
+            Feature::Cnp => mmfr2.cn_p() >= 1,
+            Feature::Uao => mmfr2.uao() >= 1,
+            Feature::Lva => mmfr2.va_range() >= 1,
+            Feature::Tcr2 => mmfr3.tcrx() >= 1,
+            Feature::S1Pie => mmfr3.s1pie() >= 1,
+            Feature::S1Poe => mmfr3.s1poe() >= 1,
+            Feature::D128 => mmfr3.d128() >= 1,
+            Feature::Pmu => !matches!(self.dfr0.pmu_ver(), DbgPmuVer::No | DbgPmuVer::ImpDef),
+            Feature::Spe => at_least(self.dfr0.pms_ver(), 1),
+            Feature::Trbe => at_least(self.dfr0.trace_buffer(), 1),
+            Feature::Brbe => at_least(self.dfr0.brbe(), 1),
+        }
+    }
+
+    /// The features the CPU has, in the order of `Feature::ALL`.
+    pub fn iter(&self) -> impl Iterator<Item = Feature> + '_ {
+        Feature::ALL.into_iter().filter(|&f| self.has(f))
+    }
+}
@@ -33,6 +33,12 @@ use crate::dev_registrer::DeviceRegister;
 use crate::dev_registrer::DeviceRegisterArray;
 use crate::dev_registrer::DeviceRegisterArraySpec;
 use crate::dev_registrer::DeviceRegisterSpec;
+use crate::regs::access::impl_register_access;
+use crate::regs::access::impl_register_access_ro;
+use crate::regs::access::impl_register_access_wo;
+use crate::regs::access::ReadableRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::MultiprocessorAffinityEl1;
 use bitfield_struct::bitfield;
 
 pub const GICD_SIZE: usize = 0x10000;
@@ -270,13 +276,13 @@ pub const GICD_INMIR_E_OFFSET: usize = 0x3B00; // [u32; 32]
 ///
 /// Configures interrupt routing for affinity-based systems.
 /// Reset value: 0x00000000
-pub const GICD_IROUTER_OFFSET: usize = 0x6100; // [u32; 1984]
+pub const GICD_IROUTER_OFFSET: usize = 0x6100; // [u64; 988]
 
 /// 0x8000-0x9FFC - Interrupt Routing Registers for extended SPI range (GICD_IROUTER<n>E)
 ///
 /// Configures interrupt routing for extended SPI interrupts in affinity-based systems.
 /// Reset value: 0x00000000
-pub const GICD_IROUTER_E_OFFSET: usize = 0x8000; // [u32; 2048]
+pub const GICD_IROUTER_E_OFFSET: usize = 0x8000; // [u64; 1024]
 
 /// 0xFFE8 - Distributor Peripheral ID2 Register (GICD_PIDR2)
 ///
@@ -451,20 +457,108 @@ impl DeviceRegisterArraySpec for GicdIgrpmodr {
     const COUNT: usize = 32;
 }
 
-/// Interrupt Routing Registers
-#[bitfield(u32)]
+/// Interrupt Routing Registers, for the SPIs 32 to 1019
+#[bitfield(u64)]
 pub struct GicdIrouter {
-    pub iroute: u32,
+    pub aff0: u8,
+    pub aff1: u8,
+    pub aff2: u8,
+    #[bits(7)]
+    _res0: u64,
+    /// Route to any participating PE rather than to the affinity
+    pub interrupt_routing_mode: bool,
+    pub aff3: u8,
+    #[bits(24)]
+    _res1: u64,
 }
 
 impl DeviceRegisterSpec for GicdIrouter {
-    type Raw = u32;
+    type Raw = u64;
     type Value = Self;
     const OFFSET: usize = GICD_IROUTER_OFFSET;
 }
 
 impl DeviceRegisterArraySpec for GicdIrouter {
-    const COUNT: usize = 1984;
+    const COUNT: usize = 988;
+}
+
+impl GicdIrouter {
+    /// Routes to the PE with the affinity.
+    pub fn to_pe(affinity: Affinity) -> Self {
+        Self::new()
+            .with_aff0(affinity.aff0)
+            .with_aff1(affinity.aff1)
+            .with_aff2(affinity.aff2)
+            .with_aff3(affinity.aff3)
+    }
+}
+
+/// Set enabled interrupts
+#[bitfield(u32)]
+pub struct GicdIsenabler {
+    pub isenable: u32,
+}
+
+impl DeviceRegisterSpec for GicdIsenabler {
+    type Raw = u32;
+    type Value = Self;
+    const OFFSET: usize = GICD_ISENABLER_OFFSET;
+}
+
+impl DeviceRegisterArraySpec for GicdIsenabler {
+    const COUNT: usize = 32;
+}
+
+/// Set pending interrupts
+#[bitfield(u32)]
+pub struct GicdIspendr {
+    pub ispend: u32,
+}
+
+impl DeviceRegisterSpec for GicdIspendr {
+    type Raw = u32;
+    type Value = Self;
+    const OFFSET: usize = GICD_ISPENDR_OFFSET;
+}
+
+impl DeviceRegisterArraySpec for GicdIspendr {
+    const COUNT: usize = 32;
+}
+
+/// Interrupt priorities, a byte per interrupt
+#[bitfield(u32)]
+pub struct GicdIpriorityr {
+    pub p0: u8,
+    pub p1: u8,
+    pub p2: u8,
+    pub p3: u8,
+}
+
+impl DeviceRegisterSpec for GicdIpriorityr {
+    type Raw = u32;
+    type Value = Self;
+    const OFFSET: usize = GICD_IPRIORITYR_OFFSET;
+}
+
+impl DeviceRegisterArraySpec for GicdIpriorityr {
+    const COUNT: usize = 255;
+}
+
+/// Interrupt configuration, two bits per interrupt with the upper one
+/// set for the edge-triggered ones
+#[bitfield(u32)]
+pub struct GicdIcfgr {
+    pub icfg: u32,
+}
+
+impl DeviceRegisterSpec for GicdIcfgr {
+    type Raw = u32;
+    type Value = Self;
+    const OFFSET: usize = GICD_ICFGR_OFFSET;
+}
+
+impl DeviceRegisterArraySpec for GicdIcfgr {
+    const COUNT: usize = 64;
 }
 // GICR registers, "12.11 The GIC Redistributor register descriptions"
 
@@ -813,6 +907,172 @@ impl DeviceRegisterSpec for GicrIgroupr {
     const OFFSET: usize = GICR_IGROUPR0_OFFSET;
 }
 
+// The CPU interface, "12.2 AArch64 System register descriptions of the
+// GIC CPU interface"
+
+#[bitfield(u64)]
+pub struct IccSre {
+    /// Use the system register interface
+    pub sre: bool,
+    /// Disable the FIQ bypass
+    pub dfb: bool,
+    /// Disable the IRQ bypass
+    pub dib: bool,
+    #[bits(61)]
+    _mbz0: u64,
+}
+
+#[bitfield(u64)]
+pub struct IccCtlr {
+    /// Common binary point register for both groups
+    pub cbpr: bool,
+    /// Writing EOIR only drops the priority, DIR deactivates
+    pub eoi_mode: bool,
+    #[bits(4)]
+    _mbz0: u64,
+    /// Priority mask hint enable
+    pub pmhe: bool,
+    _mbz1: bool,
+    /// The number of priority bits minus 1
+    #[bits(3)]
+    pub pri_bits: u64,
+    /// 0 for 16-bit INTIDs, 1 for 24-bit INTIDs
+    #[bits(3)]
+    pub id_bits: u64,
+    pub seis: bool,
+    /// Non-zero Affinity 3 supported in SGIs
+    pub a3v: bool,
+    #[bits(2)]
+    _mbz2: u64,
+    /// Range Selector supported in SGIs
+    pub rss: bool,
+    pub ext_range: bool,
+    #[bits(44)]
+    _mbz3: u64,
+}
+
+/// Interrupt priority mask
+#[bitfield(u64)]
+pub struct IccPmr {
+    pub priority: u8,
+    #[bits(56)]
+    _mbz0: u64,
+}
+
+/// Interrupt group 1 enable
+#[bitfield(u64)]
+pub struct IccIgrpen1 {
+    pub enable: bool,
+    #[bits(63)]
+    _mbz0: u64,
+}
+
+/// The INTID returned by the interrupt acknowledge registers when
+/// nothing is pending.
+pub const SPURIOUS_INTID: u32 = 1023;
+
+/// Group 0 interrupt acknowledge, the INTID of the highest priority
+/// pending interrupt, made active
+#[bitfield(u64)]
+pub struct IccIar0 {
+    #[bits(24)]
+    pub intid: u32,
+    #[bits(40)]
+    _mbz0: u64,
+}
+
+/// Group 1 interrupt acknowledge
+#[bitfield(u64)]
+pub struct IccIar1 {
+    #[bits(24)]
+    pub intid: u32,
+    #[bits(40)]
+    _mbz0: u64,
+}
+
+/// Group 0 end of interrupt, drops the running priority and, with
+/// `ICC_CTLR_EL1.EOImode` clear, deactivates
+#[bitfield(u64)]
+pub struct IccEoir0 {
+    #[bits(24)]
+    pub intid: u32,
+    #[bits(40)]
+    _mbz0: u64,
+}
+
+/// Group 1 end of interrupt
+#[bitfield(u64)]
+pub struct IccEoir1 {
+    #[bits(24)]
+    pub intid: u32,
+    #[bits(40)]
+    _mbz0: u64,
+}
+
+/// Deactivate interrupt, for `ICC_CTLR_EL1.EOImode` set
+#[bitfield(u64)]
+pub struct IccDir {
+    #[bits(24)]
+    pub intid: u32,
+    #[bits(40)]
+    _mbz0: u64,
+}
+
+/// The value of `ICC_SGI0R_EL1`, `ICC_SGI1R_EL1` and `ICC_ASGI1R_EL1`
+#[bitfield(u64)]
+pub struct IccSgir {
+    /// The PEs with Aff0 of `16 * rs` plus the bit number
+    pub target_list: u16,
+    pub aff1: u8,
+    #[bits(4)]
+    pub intid: u8,
+    #[bits(4)]
+    _mbz0: u64,
+    pub aff2: u8,
+    /// Interrupt Routing Mode, to all the PEs but the current one
+    pub irm: bool,
+    #[bits(3)]
+    _mbz1: u64,
+    /// Range selector, needs `ICC_CTLR_EL1.RSS`
+    #[bits(4)]
+    pub rs: u8,
+    pub aff3: u8,
+    _mbz2: u8,
+}
+
+/// Which of the SGI generation registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgiGroup {
+    /// `ICC_SGI0R_EL1`
+    Group0,
+    /// `ICC_SGI1R_EL1`, Group 1 of the current Security state
+    Group1,
+    /// `ICC_ASGI1R_EL1`, Group 1 of the other Security state
+    AlternateGroup1,
+}
+
+impl IccSgir {
+    /// Generates the SGI.
+    pub fn send(self, group: SgiGroup) {
+        let value = u64::from(self);
+        match group {
+            SgiGroup::Group0 => crate::store_sys_reg!(ICC_SGI0R_EL1, value),
+            SgiGroup::Group1 => crate::store_sys_reg!(ICC_SGI1R_EL1, value),
+            SgiGroup::AlternateGroup1 => crate::store_sys_reg!(ICC_ASGI1R_EL1, value),
+        }
+    }
+}
+
+impl_register_access!(IccSre, ICC_SRE_EL1);
+impl_register_access!(IccCtlr, ICC_CTLR_EL1);
+impl_register_access!(IccPmr, ICC_PMR_EL1);
+impl_register_access!(IccIgrpen1, ICC_IGRPEN1_EL1);
+impl_register_access_ro!(IccIar0, ICC_IAR0_EL1);
+impl_register_access_ro!(IccIar1, ICC_IAR1_EL1);
+impl_register_access_wo!(IccEoir0, ICC_EOIR0_EL1);
+impl_register_access_wo!(IccEoir1, ICC_EOIR1_EL1);
+impl_register_access_wo!(IccDir, ICC_DIR_EL1);
+
 /// GIC version
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GicVersion {
@@ -822,7 +1082,81 @@ pub enum GicVersion {
     GicV4,
 }
 
+/// How an interrupt is triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Pending while the line is asserted
+    Level,
+    /// Pending on the rising edge
+    Edge,
+}
+
+/// The INTID of a DTB `interrupts` specifier of the `arm,gic-v3` binding:
+/// the type, 0 for an SPI and 1 for a PPI, the number within the type and
+/// the trigger flags. `None` for the other types, e.g. the extended ranges.
+pub fn dt_intid(specifier: impl IntoIterator<Item = u32>) -> Option<u64> {
+    let mut cells = specifier.into_iter();
+    match (cells.next()?, cells.next()?) {
+        (0, number) if number < 988 => Some(32 + number as u64),
+        (1, number) if number < 16 => Some(16 + number as u64),
+        _ => None,
+    }
+}
+
+/// The group of an interrupt, with `GICD_CTLR.DS` clear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptGroup {
+    /// Signaled as FIQ, `ICC_IAR0_EL1` acknowledges
+    Group0,
+    SecureGroup1,
+    /// Signaled as IRQ at the Non-secure EL1, `ICC_IAR1_EL1` acknowledges
+    NonSecureGroup1,
+}
+
+/// The affinity of a PE, the levels of its `MPIDR_EL1`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Affinity {
+    pub aff0: u8,
+    pub aff1: u8,
+    pub aff2: u8,
+    pub aff3: u8,
+}
+
+impl Affinity {
+    /// The affinity of the current PE
+    pub fn current() -> Self {
+        MultiprocessorAffinityEl1::read().into()
+    }
+}
+
+impl From<MultiprocessorAffinityEl1> for Affinity {
+    fn from(mpidr: MultiprocessorAffinityEl1) -> Self {
+        Self {
+            aff0: mpidr.aff0() as u8,
+            aff1: mpidr.aff1() as u8,
+            aff2: mpidr.aff2() as u8,
+            aff3: mpidr.aff3() as u8,
+        }
+    }
+}
+
+impl core::fmt::Display for Affinity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.aff3, self.aff2, self.aff1, self.aff0)
+    }
+}
+
+/// Where an SPI goes, `GICD_IROUTER<n>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiRoute {
+    /// The PE with the affinity
+    Pe(Affinity),
+    /// Any participating PE, the GIC chooses
+    Any,
+}
+
 /// GIC intrerface
+#[derive(Clone)]
 pub struct Gic {
     gicd_base: usize,
     gicr_base: usize,
@@ -898,7 +1232,10 @@ impl Gic {
         // Reset
         gicd_ctrl.store(GicdCtrl::new().with_disable_secure(0));
         while gicd_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
 
         // Mask and clear all SPIs
@@ -913,7 +1250,10 @@ impl Gic {
         DeviceRegisterArray::<GicdIgrpmodr>::new(self.gicd_base)
             .fill(1..max_spi / 32, GicdIgrpmodr::from(!0));
         while gicd_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
 
         gicd_ctrl.store(
@@ -923,19 +1263,31 @@ impl Gic {
                 .with_are_ns(1),
         );
         while gicd_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
 
-        unsafe { core::arch::asm!("isb sy", options(nostack)) };
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            core::arch::asm!("isb sy", options(nostack))
+        };
 
-        // CPU 0, affinity 0.0.0.0
+        // CPU 0, affinity 0.0.0.0. The array starts at the SPI 32.
         DeviceRegisterArray::<GicdIrouter>::new(self.gicd_base)
-            .fill(32..max_spi, GicdIrouter::from(0));
+            .fill(0..max_spi.saturating_sub(32), GicdIrouter::new());
         while gicd_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
 
-        unsafe { core::arch::asm!("isb sy", options(nostack)) };
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            core::arch::asm!("isb sy", options(nostack))
+        };
     }
 
     /// Wake up the CPU and initialize its redistributor.
@@ -947,7 +1299,10 @@ impl Gic {
         let mut waker = DeviceRegister::<GicrWaker>::new(gicr_base);
         waker.store(waker.load().with_processor_sleep(0));
         while waker.load().children_asleep() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
 
         // Configure interrupts
@@ -980,31 +1335,38 @@ impl Gic {
 
         let gicr_ctrl = DeviceRegister::<GicrCtlr>::new(gicr_base);
         while gicr_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
 
-        unsafe { core::arch::asm!("isb sy", options(nostack)) };
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            core::arch::asm!("isb sy", options(nostack))
+        };
     }
 
     /// Enables a local (SGI or PPI interrupt).
-    fn enable_interrupt(&mut self, irq_num: u64, enable: bool, cpu: usize) {
+    fn enable_interrupt(&self, irq_num: u64, enable: bool, cpu: usize) {
         let gicr_base = self.gicr_base + cpu * self.redist_size;
 
-        let mut icenable = DeviceRegister::<GicrIcenabler>::new(gicr_base);
-        let mut isenable = DeviceRegister::<GicrIsenabler>::new(gicr_base);
         let mask = 1 << irq_num;
 
+        // Writing zeroes has no effect, and reading back the enabled ones
+        // for an RMW would change them.
         if enable {
-            icenable.fetch_and(GicrIcenabler::from(!mask));
-            isenable.fetch_or(GicrIsenabler::from(mask));
+            DeviceRegister::<GicrIsenabler>::new(gicr_base).store(GicrIsenabler::from(mask));
         } else {
-            isenable.fetch_and(GicrIsenabler::from(!mask));
-            icenable.fetch_or(GicrIcenabler::from(mask));
+            DeviceRegister::<GicrIcenabler>::new(gicr_base).store(GicrIcenabler::from(mask));
         }
 
         let gicr_ctrl = DeviceRegister::<GicrCtlr>::new(gicr_base);
         while gicr_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
     }
 
@@ -1012,21 +1374,20 @@ impl Gic {
     fn pend_interrupt(&mut self, irq_num: u64, pend: bool, cpu: usize) {
         let gicr_base = self.gicr_base + cpu * self.redist_size;
 
-        let mut icpend = DeviceRegister::<GicrIcpendr>::new(gicr_base);
-        let mut ispend = DeviceRegister::<GicrIspendr>::new(gicr_base);
         let mask = 1 << irq_num;
 
         if pend {
-            icpend.fetch_and(GicrIcpendr::from(!mask));
-            ispend.fetch_or(GicrIspendr::from(mask));
+            DeviceRegister::<GicrIspendr>::new(gicr_base).store(GicrIspendr::from(mask));
         } else {
-            ispend.fetch_and(GicrIspendr::from(!mask));
-            icpend.fetch_or(GicrIcpendr::from(mask));
+            DeviceRegister::<GicrIcpendr>::new(gicr_base).store(GicrIcpendr::from(mask));
         }
 
         let gicr_ctrl = DeviceRegister::<GicrCtlr>::new(gicr_base);
         while gicr_ctrl.load().reg_write_pending() != 0 {
-            unsafe { core::arch::asm!("yield", options(nostack)) }
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
         }
     }
 
@@ -1074,57 +1435,286 @@ impl Gic {
         true
     }
 
+    fn is_spi(&self, irq_num: u64) -> bool {
+        (32..self.max_spi as u64).contains(&irq_num)
+    }
+
+    fn wait_for_gicd_write(&self) {
+        let gicd_ctrl = DeviceRegister::<GicdCtrl>::new(self.gicd_base);
+        while gicd_ctrl.load().reg_write_pending() != 0 {
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("yield", options(nostack))
+            }
+        }
+    }
+
+    /// Enables an SPI.
+    #[must_use]
+    pub fn enable_spi(&mut self, irq_num: u64, enable: bool) -> bool {
+        if !self.is_spi(irq_num) {
+            return false;
+        }
+
+        let (index, mask) = (irq_num as usize / 32, 1 << (irq_num % 32));
+        if enable {
+            DeviceRegisterArray::<GicdIsenabler>::new(self.gicd_base)
+                .index(index)
+                .store(GicdIsenabler::from(mask));
+        } else {
+            DeviceRegisterArray::<GicdIcenabler>::new(self.gicd_base)
+                .index(index)
+                .store(GicdIcenabler::from(mask));
+            self.wait_for_gicd_write();
+        }
+        true
+    }
+
+    /// Disables an SGI or a PPI of the CPU, or an SPI. Takes `&self`, for
+    /// the interrupt handlers.
+    #[must_use]
+    pub fn disable_interrupt(&self, irq_num: u64, cpu: usize) -> bool {
+        if irq_num < 32 {
+            self.enable_interrupt(irq_num, false, cpu);
+        } else if self.is_spi(irq_num) {
+            DeviceRegisterArray::<GicdIcenabler>::new(self.gicd_base)
+                .index(irq_num as usize / 32)
+                .store(GicdIcenabler::from(1 << (irq_num % 32)));
+            self.wait_for_gicd_write();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Pends an SPI.
+    #[must_use]
+    pub fn pend_spi(&mut self, irq_num: u64, pend: bool) -> bool {
+        if !self.is_spi(irq_num) {
+            return false;
+        }
+
+        let (index, mask) = (irq_num as usize / 32, 1 << (irq_num % 32));
+        if pend {
+            DeviceRegisterArray::<GicdIspendr>::new(self.gicd_base)
+                .index(index)
+                .store(GicdIspendr::from(mask));
+        } else {
+            DeviceRegisterArray::<GicdIcpendr>::new(self.gicd_base)
+                .index(index)
+                .store(GicdIcpendr::from(mask));
+        }
+        true
+    }
+
+    /// Sets the priority of an SPI, lower values are more urgent. The GIC
+    /// might implement only the upper bits.
+    #[must_use]
+    pub fn set_priority(&mut self, irq_num: u64, priority: u8) -> bool {
+        if !self.is_spi(irq_num) {
+            return false;
+        }
+
+        let mut ipriorityr =
+            DeviceRegisterArray::<GicdIpriorityr>::new(self.gicd_base).index(irq_num as usize / 4);
+        let value = ipriorityr.load();
+        ipriorityr.store(match irq_num % 4 {
+            0 => value.with_p0(priority),
+            1 => value.with_p1(priority),
+            2 => value.with_p2(priority),
+            _ => value.with_p3(priority),
+        });
+        true
+    }
+
+    /// Configures an SPI as level-sensitive or edge-triggered. Best done
+    /// while the SPI is disabled.
+    #[must_use]
+    pub fn set_trigger(&mut self, irq_num: u64, trigger: Trigger) -> bool {
+        if !self.is_spi(irq_num) {
+            return false;
+        }
+
+        let mut icfgr =
+            DeviceRegisterArray::<GicdIcfgr>::new(self.gicd_base).index(irq_num as usize / 16);
+        let edge = 0b10 << (2 * (irq_num % 16));
+        let icfg = icfgr.load().icfg();
+        icfgr.store(GicdIcfgr::from(match trigger {
+            Trigger::Level => icfg & !edge,
+            Trigger::Edge => icfg | edge,
+        }));
+        true
+    }
+
+    /// Puts an SPI into a group.
+    #[must_use]
+    pub fn set_group(&mut self, irq_num: u64, group: InterruptGroup) -> bool {
+        if !self.is_spi(irq_num) {
+            return false;
+        }
+
+        let (index, mask) = (irq_num as usize / 32, 1 << (irq_num % 32));
+        let (igroup, igrpmod) = match group {
+            InterruptGroup::Group0 => (false, false),
+            InterruptGroup::SecureGroup1 => (false, true),
+            InterruptGroup::NonSecureGroup1 => (true, false),
+        };
+        let mut igroupr = DeviceRegisterArray::<GicdIgroupr>::new(self.gicd_base).index(index);
+        let value = igroupr.load().igroup() & !mask;
+        igroupr.store(GicdIgroupr::from(value | if igroup { mask } else { 0 }));
+        let mut igrpmodr = DeviceRegisterArray::<GicdIgrpmodr>::new(self.gicd_base).index(index);
+        let value = igrpmodr.load().igrpmod() & !mask;
+        igrpmodr.store(GicdIgrpmodr::from(value | if igrpmod { mask } else { 0 }));
+        self.wait_for_gicd_write();
+        true
+    }
+
+    /// Routes an SPI to a PE, or to any of the participating ones.
+    #[must_use]
+    pub fn route_spi(&mut self, irq_num: u64, route: SpiRoute) -> bool {
+        if !self.is_spi(irq_num) {
+            return false;
+        }
+
+        let irouter = match route {
+            SpiRoute::Pe(affinity) => GicdIrouter::to_pe(affinity),
+            SpiRoute::Any => GicdIrouter::new().with_interrupt_routing_mode(true),
+        };
+        DeviceRegisterArray::<GicdIrouter>::new(self.gicd_base)
+            .index(irq_num as usize - 32)
+            .store(irouter);
+        self.wait_for_gicd_write();
+        true
+    }
+
+    /// Where an SPI is routed to.
+    pub fn spi_route(&self, irq_num: u64) -> Option<SpiRoute> {
+        if !self.is_spi(irq_num) {
+            return None;
+        }
+
+        let irouter = DeviceRegisterArray::<GicdIrouter>::new(self.gicd_base)
+            .index(irq_num as usize - 32)
+            .load();
+        Some(if irouter.interrupt_routing_mode() {
+            SpiRoute::Any
+        } else {
+            SpiRoute::Pe(Affinity {
+                aff0: irouter.aff0(),
+                aff1: irouter.aff1(),
+                aff2: irouter.aff2(),
+                aff3: irouter.aff3(),
+            })
+        })
+    }
+
     /// Initialize the control interface to the CPU
     /// through the ICC_* system registers.
     ///
     /// TODO: not hardcode the mask and the target.
     pub fn init_icc(&mut self) {
-        // TODO: definitions for the registers
-        let enable = 1u64;
-        let disable = 0u64;
-        let mask: u64 = 0xffu64;
-
-        // SAFETY: not accesiing the memory.
-        unsafe {
-            core::arch::asm!(
-                // Enable access to the system regster interface
-                "msr ICC_SRE_EL1, {enable}",
-                // EOI will deactivate the interrupt so don't need
-                // to flip the bits in GICR separately
-                "msr ICC_CTLR_EL1, {disable}",
-                // Interrupt priority filter mask. Only interrupts with a higher priority than the value in this
-                // register are signaled
-                "msr ICC_PMR_EL1, {mask}",
-                // Enable group 1 (we're in the non-secure world)
-                "msr ICC_IGRPEN1_EL1, {enable}",
-                enable = in(reg) enable, disable = in(reg) disable, mask = in(reg) mask,
-                options(nostack, nomem))
-        };
+        // Enable access to the system regster interface
+        IccSre::new().with_sre(true).store();
+        // EOI will deactivate the interrupt so don't need
+        // to flip the bits in GICR separately
+        IccCtlr::new().store();
+        // Interrupt priority filter mask. Only interrupts with a higher priority than the value in this
+        // register are signaled
+        IccPmr::new().with_priority(0xff).store();
+        // Enable group 1 (we're in the non-secure world)
+        IccIgrpen1::new().with_enable(true).store();
     }
 
+    /// Sends a Group 1 SGI to all the PEs but the current one, see
+    /// `send_sgi` for the individual ones.
     #[must_use]
     pub fn generate_sgi(&self, int_id: u64) -> bool {
         if !(0..16).contains(&int_id) {
             return false;
         }
 
-        // Send to all (IRM aka Interrupt Routing Mode set to 1).
-        // TODO: define a struct and provide an ability to send to
-        // an individual CPU/PE.
-        let route_sgi = (1u64 << 40) | (int_id << 24);
+        IccSgir::new()
+            .with_irm(true)
+            .with_intid(int_id as u8)
+            .send(SgiGroup::Group1);
+
+        true
+    }
+
+    /// Tells if `send_sgi` takes the SGI and the targets: Aff0 above 15
+    /// needs the range selector, and non-zero Aff3 its support in the CPU
+    /// interface.
+    pub fn can_send_sgi(&self, int_id: u64, targets: &[Affinity]) -> bool {
+        if !(0..16).contains(&int_id) {
+            return false;
+        }
+        let ctlr = IccCtlr::read();
+        !targets
+            .iter()
+            .any(|target| (target.aff0 >= 16 && !ctlr.rss()) || (target.aff3 != 0 && !ctlr.a3v()))
+    }
 
-        // SAFETY: not accesiing the memory.
+    /// Sends an SGI to the PEs with the affinities, with a write for each
+    /// run of the targets sharing Aff3, Aff2, Aff1 and the range of 16
+    /// Aff0 values. `false` without a write unless `can_send_sgi`.
+    #[must_use]
+    pub fn send_sgi(&self, int_id: u64, targets: &[Affinity], group: SgiGroup) -> bool {
+        if !self.can_send_sgi(int_id, targets) {
+            return false;
+        }
+
+        let mut pending: Option<IccSgir> = None;
+        for target in targets {
+            let sgir = IccSgir::new()
+                .with_intid(int_id as u8)
+                .with_aff1(target.aff1)
+                .with_aff2(target.aff2)
+                .with_aff3(target.aff3)
+                .with_rs(target.aff0 / 16);
+            let bit = 1 << (target.aff0 % 16);
+            pending = match pending {
+                Some(sgir_pending)
+                    if u64::from(sgir_pending.with_target_list(0)) == u64::from(sgir) =>
+                {
+                    Some(sgir.with_target_list(sgir_pending.target_list() | bit))
+                }
+                _ => {
+                    if let Some(sgir_pending) = pending {
+                        sgir_pending.send(group);
+                    }
+                    Some(sgir.with_target_list(bit))
+                }
+            };
+        }
+        if let Some(sgir_pending) = pending {
+            sgir_pending.send(group);
+        }
+
+        #[cfg(target_arch = "aarch64")]
         unsafe {
-            core::arch::asm!(
-                // Generates a software interrupt
-                "msr ICC_SGI1R_EL1, {route_sgi}",
-                route_sgi = in(reg) route_sgi,
-                options(nostack, nomem))
+            core::arch::asm!("isb sy", options(nostack))
         };
-
         true
     }
 
+    /// The affinity of a CPU, from its redistributor.
+    pub fn affinity(&self, cpu: usize) -> Affinity {
+        assert!(cpu < self.num_cpus, "no CPU {cpu}");
+        let typer =
+            DeviceRegister::<GicrTyper>::new(self.gicr_base + cpu * self.redist_size).load();
+        Affinity {
+            aff0: typer.aff0() as u8,
+            aff1: typer.aff1() as u8,
+            aff2: typer.aff2() as u8,
+            aff3: typer.aff3() as u8,
+        }
+    }
+
+    /// The CPU with the redistributor of the affinity
+    pub fn cpu_of(&self, affinity: Affinity) -> Option<usize> {
+        (0..self.num_cpus).find(|&cpu| self.affinity(cpu) == affinity)
+    }
+
     /// Get the GIC version.
     pub fn version(&self) -> GicVersion {
         self.version
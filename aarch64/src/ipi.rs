@@ -0,0 +1,173 @@
+//! Calling a function on another CPU
+//!
+//! Each CPU has a slot in the `Mailbox`. The caller posts the function and
+//! its argument to the slot of the target CPU and sends that CPU the SGI
+//! of the mailbox. The SGI handler there runs the function through
+//! `Mailbox::handle` and posts the result back, the caller spinning until
+//! it does. A slot holds one call at a time.
+//!
+//! A call to the current CPU completes only once the SGI is taken, so not
+//! with the IRQs masked, nor from the SGI handler itself.
+
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+use crate::gic::Gic;
+use crate::gic::SgiGroup;
+
+/// Runs on the target CPU with the argument, returns the result.
+pub type Function = fn(argument: usize) -> usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiError {
+    /// Beyond the mailbox or the GIC
+    Cpu(usize),
+    /// Another call to the CPU is in flight
+    Busy(usize),
+    /// The SGI can't reach the CPU
+    Sgi(usize),
+    /// Nothing posted to the CPU to wait for
+    NotPosted(usize),
+}
+
+impl core::fmt::Display for IpiError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IpiError::Cpu(cpu) => write!(f, "no CPU {cpu}"),
+            IpiError::Busy(cpu) => write!(f, "CPU {cpu} has a call in flight"),
+            IpiError::Sgi(cpu) => write!(f, "can't send an SGI to CPU {cpu}"),
+            IpiError::NotPosted(cpu) => write!(f, "no call posted to CPU {cpu}"),
+        }
+    }
+}
+
+/// Nothing posted
+const EMPTY: u8 = 0;
+/// Taken by a caller, the call being written
+const CLAIMED: u8 = 1;
+/// Waiting for the target
+const POSTED: u8 = 2;
+/// Run, the result waiting for the caller
+const DONE: u8 = 3;
+
+struct Slot {
+    state: AtomicU8,
+    function: AtomicPtr<()>,
+    argument: AtomicUsize,
+    result: AtomicUsize,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            function: AtomicPtr::new(core::ptr::null_mut()),
+            argument: AtomicUsize::new(0),
+            result: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// The slots of `CPUS` CPUs, meant for a `static` the SGI handler calls
+/// `handle` on
+pub struct Mailbox<const CPUS: usize> {
+    sgi: u64,
+    slots: [Slot; CPUS],
+}
+
+impl<const CPUS: usize> Mailbox<CPUS> {
+    /// Uses the Group 1 SGI `sgi`, registering its handler is up to the
+    /// caller.
+    pub const fn new(sgi: u64) -> Self {
+        Self {
+            sgi,
+            slots: [const { Slot::new() }; CPUS],
+        }
+    }
+
+    pub fn sgi(&self) -> u64 {
+        self.sgi
+    }
+
+    fn slot(&self, cpu: usize) -> Result<&Slot, IpiError> {
+        self.slots.get(cpu).ok_or(IpiError::Cpu(cpu))
+    }
+
+    /// Posts the call to `cpu` and sends it the SGI, `wait` collects the
+    /// result. The slot is left alone if the SGI can't reach the CPU.
+    pub fn post(
+        &self,
+        gic: &Gic,
+        cpu: usize,
+        function: Function,
+        argument: usize,
+    ) -> Result<(), IpiError> {
+        if cpu >= gic.num_cpus() {
+            return Err(IpiError::Cpu(cpu));
+        }
+        let slot = self.slot(cpu)?;
+        let targets = [gic.affinity(cpu)];
+        if !gic.can_send_sgi(self.sgi, &targets) {
+            return Err(IpiError::Sgi(cpu));
+        }
+        slot.state
+            .compare_exchange(EMPTY, CLAIMED, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| IpiError::Busy(cpu))?;
+        slot.function.store(function as *mut (), Ordering::Relaxed);
+        slot.argument.store(argument, Ordering::Relaxed);
+        slot.state.store(POSTED, Ordering::Release);
+
+        let sent = gic.send_sgi(self.sgi, &targets, SgiGroup::Group1);
+        debug_assert!(sent, "checked by `can_send_sgi`");
+        Ok(())
+    }
+
+    /// Spins until the call posted to `cpu` is done, returns its result and
+    /// frees the slot.
+    pub fn wait(&self, cpu: usize) -> Result<usize, IpiError> {
+        let slot = self.slot(cpu)?;
+        loop {
+            match slot.state.load(Ordering::Acquire) {
+                DONE => break,
+                EMPTY => return Err(IpiError::NotPosted(cpu)),
+                _ => core::hint::spin_loop(),
+            }
+        }
+        let result = slot.result.load(Ordering::Relaxed);
+        slot.state.store(EMPTY, Ordering::Release);
+        Ok(result)
+    }
+
+    /// Runs `function(argument)` on `cpu`, returns the result.
+    pub fn call(
+        &self,
+        gic: &Gic,
+        cpu: usize,
+        function: Function,
+        argument: usize,
+    ) -> Result<usize, IpiError> {
+        self.post(gic, cpu, function, argument)?;
+        self.wait(cpu)
+    }
+
+    /// Runs the call posted to `cpu`, from the SGI handler on that CPU.
+    /// `false` if nothing is posted.
+    pub fn handle(&self, cpu: usize) -> bool {
+        let Ok(slot) = self.slot(cpu) else {
+            return false;
+        };
+        if slot.state.load(Ordering::Acquire) != POSTED {
+            return false;
+        }
+        // SAFETY: only `post` stores the pointer, from a `Function`.
+        let function = unsafe {
+            core::mem::transmute::<*mut (), Function>(slot.function.load(Ordering::Relaxed))
+        };
+        let result = function(slot.argument.load(Ordering::Relaxed));
+        slot.result.store(result, Ordering::Relaxed);
+        slot.state.store(DONE, Ordering::Release);
+        true
+    }
+}
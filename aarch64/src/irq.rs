@@ -0,0 +1,244 @@
+//! Interrupt dispatch through the GICv3 CPU interface
+//!
+//! An IRQ or FIQ exception is taken while the CPU interface signals a
+//! pending interrupt of the group. Reading `ICC_IAR<n>_EL1` acknowledges
+//! the highest priority one, making it active, and returns its INTID or
+//! the spurious 1023 when nothing is pending any longer. Writing the INTID
+//! to `ICC_EOIR<n>_EL1` drops the running priority and deactivates it,
+//! unless `ICC_CTLR_EL1.EOImode` splits the deactivation into a write to
+//! `ICC_DIR_EL1`.
+//!
+//! The non-secure EL1 sees the Group 1 interrupts as IRQs, and the Group 0
+//! ones as FIQs where `GICD_CTLR.DS` makes those accessible.
+//!
+//! An interrupt without a handler is disabled in the GIC before it is
+//! ended, else a level-sensitive one is acknowledged over and over.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::Ordering;
+
+use crate::gic::Affinity;
+use crate::gic::Gic;
+use crate::gic::IccCtlr;
+use crate::gic::IccDir;
+use crate::gic::IccEoir0;
+use crate::gic::IccEoir1;
+use crate::gic::IccIar0;
+use crate::gic::IccIar1;
+use crate::regs::access::ReadableRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::ExceptionSource;
+
+/// The SGIs, the PPIs and the SPIs, the INTIDs from 1020 up are special.
+pub const MAX_INTID: usize = 1020;
+
+/// Called with the INTID, the interrupt is active meanwhile.
+pub type Handler = fn(intid: u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqError {
+    /// Not an SGI, PPI or SPI
+    Intid(u32),
+    /// Another handler is registered
+    Busy(u32),
+}
+
+impl core::fmt::Display for IrqError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IrqError::Intid(intid) => write!(f, "INTID {intid} has no handler slot"),
+            IrqError::Busy(intid) => write!(f, "INTID {intid} already has a handler"),
+        }
+    }
+}
+
+/// The interrupt group acknowledged for an exception
+fn group(source: ExceptionSource) -> Option<u8> {
+    match source {
+        ExceptionSource::Irq => Some(1),
+        ExceptionSource::Fiq => Some(0),
+        _ => None,
+    }
+}
+
+/// No GIC attached
+const DETACHED: u8 = 0;
+/// The GIC being written
+const ATTACHING: u8 = 1;
+/// The GIC written, only read from now on
+const ATTACHED: u8 = 2;
+
+/// The handlers by INTID, meant for a `static` the exception handler
+/// dispatches through
+pub struct Dispatcher {
+    handlers: [AtomicPtr<()>; MAX_INTID],
+    /// The acknowledged interrupts without a handler
+    unhandled: AtomicU64,
+    /// The exceptions with nothing to acknowledge
+    spurious: AtomicU64,
+    /// Disables the interrupts without a handler, see `attach`
+    gic: UnsafeCell<Option<Gic>>,
+    gic_state: AtomicU8,
+}
+
+// SAFETY: `gic` is written once, by the `attach` call moving `gic_state`
+// to ATTACHING, and read only after the Release store of ATTACHED.
+unsafe impl Sync for Dispatcher {}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dispatcher {
+    pub const fn new() -> Self {
+        Self {
+            handlers: [const { AtomicPtr::new(core::ptr::null_mut()) }; MAX_INTID],
+            unhandled: AtomicU64::new(0),
+            spurious: AtomicU64::new(0),
+            gic: UnsafeCell::new(None),
+            gic_state: AtomicU8::new(DETACHED),
+        }
+    }
+
+    /// Keeps a copy of the GIC to disable the interrupts without a handler
+    /// with, on the CPU taking them. `false` if one is attached already.
+    pub fn attach(&self, gic: &Gic) -> bool {
+        if self
+            .gic_state
+            .compare_exchange(DETACHED, ATTACHING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        // SAFETY: the state keeps the other `attach` calls and the readers
+        // out.
+        unsafe { *self.gic.get() = Some(gic.clone()) };
+        self.gic_state.store(ATTACHED, Ordering::Release);
+        true
+    }
+
+    fn gic(&self) -> Option<&Gic> {
+        if self.gic_state.load(Ordering::Acquire) != ATTACHED {
+            return None;
+        }
+        // SAFETY: not written any longer, see `attach`.
+        unsafe { (*self.gic.get()).as_ref() }
+    }
+
+    /// Disables the interrupt in the GIC attached, if any.
+    fn disable(&self, intid: u32) {
+        let Some(gic) = self.gic() else {
+            return;
+        };
+        if let Some(cpu) = gic.cpu_of(Affinity::current()) {
+            let _ = gic.disable_interrupt(intid as u64, cpu);
+        }
+    }
+
+    fn slot(&self, intid: u32) -> Result<&AtomicPtr<()>, IrqError> {
+        self.handlers
+            .get(intid as usize)
+            .ok_or(IrqError::Intid(intid))
+    }
+
+    /// Registers the handler of an INTID, enabling the interrupt is up to
+    /// the caller.
+    pub fn register(&self, intid: u32, handler: Handler) -> Result<(), IrqError> {
+        self.slot(intid)?
+            .compare_exchange(
+                core::ptr::null_mut(),
+                handler as *mut (),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .map(|_| ())
+            .map_err(|_| IrqError::Busy(intid))
+    }
+
+    /// Removes the handler of an INTID, returning it.
+    pub fn unregister(&self, intid: u32) -> Result<Option<Handler>, IrqError> {
+        let handler = self
+            .slot(intid)?
+            .swap(core::ptr::null_mut(), Ordering::AcqRel);
+        // SAFETY: only `register` stores the non-null pointers, from a
+        // `Handler`.
+        Ok((!handler.is_null())
+            .then(|| unsafe { core::mem::transmute::<*mut (), Handler>(handler) }))
+    }
+
+    fn handler(&self, intid: u32) -> Option<Handler> {
+        let handler = self.slot(intid).ok()?.load(Ordering::Acquire);
+        // SAFETY: see `unregister`.
+        (!handler.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), Handler>(handler) })
+    }
+
+    pub fn unhandled(&self) -> u64 {
+        self.unhandled.load(Ordering::Relaxed)
+    }
+
+    pub fn spurious(&self) -> u64 {
+        self.spurious.load(Ordering::Relaxed)
+    }
+
+    /// Acknowledges and handles the pending interrupts of the group the
+    /// exception stands for, until none is left. Returns the number of
+    /// the acknowledged ones, `None` for the synchronous and SError
+    /// exceptions.
+    ///
+    /// An interrupt without a handler is disabled through the GIC
+    /// attached before it is ended. Without one, a level-sensitive
+    /// interrupt stays pending: acknowledged again at once, it is ended
+    /// and left to the next exception.
+    pub fn dispatch(&self, source: ExceptionSource) -> Option<usize> {
+        let group = group(source)?;
+        let split_deactivation = IccCtlr::read().eoi_mode();
+        let mut handled = 0;
+        // The INTID without a handler acknowledged last, if nothing since.
+        let mut unhandled = None;
+        loop {
+            let intid = if group == 0 {
+                IccIar0::read().intid()
+            } else {
+                IccIar1::read().intid()
+            };
+            if intid as usize >= MAX_INTID {
+                // Nothing made active, nothing to end.
+                if handled == 0 {
+                    self.spurious.fetch_add(1, Ordering::Relaxed);
+                }
+                return Some(handled);
+            }
+
+            let repeated = unhandled == Some(intid);
+            match self.handler(intid) {
+                Some(handler) => {
+                    handler(intid);
+                    unhandled = None;
+                }
+                None if repeated => {}
+                None => {
+                    self.unhandled.fetch_add(1, Ordering::Relaxed);
+                    self.disable(intid);
+                    unhandled = Some(intid);
+                }
+            }
+            if group == 0 {
+                IccEoir0::new().with_intid(intid).store();
+            } else {
+                IccEoir1::new().with_intid(intid).store();
+            }
+            if split_deactivation {
+                IccDir::new().with_intid(intid).store();
+            }
+            handled += 1;
+            if repeated {
+                return Some(handled);
+            }
+        }
+    }
+}
@@ -1,10 +1,24 @@
 #![cfg_attr(not(test), no_std)]
 
+pub mod debug;
 pub mod dev_registrer;
+pub mod fdt;
+pub mod features;
 pub mod gic;
+pub mod ipi;
+pub mod irq;
+pub mod memmap;
 pub mod mmu;
+pub mod mte;
+pub mod pauth;
 pub mod pl011;
+pub mod pmu;
 pub mod regs;
+pub mod rng;
+#[cfg(target_arch = "aarch64")]
 pub mod semihosting;
+pub mod survey;
+pub mod sve;
+pub mod timer;
 
 mod tests;
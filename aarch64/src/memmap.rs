@@ -0,0 +1,414 @@
+//! Physical memory map
+//!
+//! Describes the RAM, the parts of it that are taken, and the MMIO windows.
+//! The RAM and the firmware reservations come from the DTB, the rest
+//! is carved out of the RAM as the image, the page tables and the
+//! allocations claim it.
+
+use crate::fdt;
+use crate::fdt::Fdt;
+use crate::fdt::FdtError;
+use crate::fdt::Token;
+
+/// What a reserved part of the RAM holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reservation {
+    Image,
+    Stack,
+    PageTables,
+    Dtb,
+    /// The memory reservation block and `/reserved-memory` of the DTB
+    Firmware,
+    Payload,
+    Allocated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Free RAM
+    Ram,
+    Reserved(Reservation),
+    Mmio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub base: u64,
+    pub size: u64,
+    pub kind: RegionKind,
+}
+
+impl Region {
+    pub fn end(&self) -> u64 {
+        self.base + self.size
+    }
+
+    pub fn contains(&self, addr: u64) -> bool {
+        addr >= self.base && addr < self.end()
+    }
+
+    /// RAM, free or reserved.
+    pub fn is_ram(&self) -> bool {
+        self.kind != RegionKind::Mmio
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryMapError {
+    /// No space left for more regions.
+    TooManyRegions,
+    EmptyRegion,
+    /// The region wraps around the end of the address space.
+    InvalidRegion {
+        base: u64,
+        size: u64,
+    },
+    /// The region to add overlaps the region at `existing_base`.
+    Overlap {
+        base: u64,
+        size: u64,
+        existing_base: u64,
+    },
+    /// The region to carve out is not fully backed by RAM.
+    NotRam {
+        base: u64,
+        size: u64,
+    },
+    /// No free RAM for `size` bytes aligned to `align`.
+    OutOfMemory {
+        size: u64,
+        align: u64,
+    },
+    Fdt(FdtError),
+}
+
+impl core::fmt::Display for MemoryMapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MemoryMapError::TooManyRegions => write!(f, "too many memory regions"),
+            MemoryMapError::EmptyRegion => write!(f, "empty memory region"),
+            MemoryMapError::InvalidRegion { base, size } => {
+                write!(f, "invalid memory region at {base:#x} of size {size:#x}")
+            }
+            MemoryMapError::Overlap {
+                base,
+                size,
+                existing_base,
+            } => write!(
+                f,
+                "memory region at {base:#x} of size {size:#x} overlaps the one at {existing_base:#x}"
+            ),
+            MemoryMapError::NotRam { base, size } => {
+                write!(f, "memory at {base:#x} of size {size:#x} is not RAM")
+            }
+            MemoryMapError::OutOfMemory { size, align } => {
+                write!(f, "no free RAM for {size:#x} bytes aligned to {align:#x}")
+            }
+            MemoryMapError::Fdt(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<FdtError> for MemoryMapError {
+    fn from(value: FdtError) -> Self {
+        MemoryMapError::Fdt(value)
+    }
+}
+
+pub const MAX_REGIONS: usize = 64;
+
+/// The regions do not overlap and are sorted by their base address.
+#[derive(Debug, Clone)]
+pub struct MemoryMap {
+    regions: [Region; MAX_REGIONS],
+    count: usize,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMap {
+    pub const fn new() -> Self {
+        Self {
+            regions: [Region {
+                base: 0,
+                size: 0,
+                kind: RegionKind::Ram,
+            }; MAX_REGIONS],
+            count: 0,
+        }
+    }
+
+    /// The RAM from the memory nodes, and the reservations from the
+    /// memory reservation block and from `/reserved-memory`. The
+    /// reservations may repeat or overlap, the parts of them outside of
+    /// the RAM are left out.
+    pub fn from_fdt(fdt: &Fdt) -> Result<Self, MemoryMapError> {
+        let mut map = Self::new();
+        let mut reserved = [(0, 0); MAX_REGIONS];
+        let mut reserved_count = 0;
+        let mut reserve = |base, size| {
+            let entry = reserved
+                .get_mut(reserved_count)
+                .ok_or(MemoryMapError::TooManyRegions)?;
+            *entry = (base, size);
+            reserved_count += 1;
+            Ok::<(), MemoryMapError>(())
+        };
+
+        // The cells of the node at each depth for its children, the root
+        // is at depth 1. A node without them has 2 and 1.
+        let mut address_cells = [2; 8];
+        let mut size_cells = [1; 8];
+        let mut depth = 0;
+        // The depth of the `/reserved-memory` node if inside it.
+        let mut reserved_memory_depth = None;
+        // A memory node at depth 2 is in progress, its `reg` and
+        // whether `device_type` is "memory".
+        let mut memory_node: Option<(&[u8], bool)> = None;
+
+        for token in fdt.tokens() {
+            match token? {
+                Token::BeginNode(name) => {
+                    depth += 1;
+                    if depth >= address_cells.len() {
+                        continue;
+                    }
+                    address_cells[depth] = 2;
+                    size_cells[depth] = 1;
+                    if depth == 2 {
+                        if name == b"reserved-memory" {
+                            reserved_memory_depth = Some(depth);
+                        } else if name == b"memory" || name.starts_with(b"memory@") {
+                            memory_node = Some((&[], true));
+                        } else {
+                            memory_node = Some((&[], false));
+                        }
+                    }
+                }
+                Token::EndNode => {
+                    if depth == 2 {
+                        if let Some((reg, true)) = memory_node.take() {
+                            for (base, size) in
+                                fdt::reg_entries(reg, address_cells[1], size_cells[1])
+                            {
+                                if size != 0 {
+                                    map.add(base, size, RegionKind::Ram)?;
+                                }
+                            }
+                        }
+                    }
+                    if reserved_memory_depth == Some(depth) {
+                        reserved_memory_depth = None;
+                    }
+                    // `Tokens` fails on the end of a node never begun.
+                    depth -= 1;
+                }
+                Token::Property { name, value } => {
+                    if depth == 0 || depth >= address_cells.len() {
+                        continue;
+                    }
+                    // The cells apply to the children of the node.
+                    if name == b"#address-cells" {
+                        address_cells[depth] = fdt::cells(value).unwrap_or(2);
+                    } else if name == b"#size-cells" {
+                        size_cells[depth] = fdt::cells(value).unwrap_or(1);
+                    } else if depth == 2 && name == b"device_type" {
+                        if let Some((_, is_memory)) = memory_node.as_mut() {
+                            *is_memory |= value == b"memory\0";
+                        }
+                    } else if depth == 2 && name == b"reg" {
+                        if let Some((reg, _)) = memory_node.as_mut() {
+                            *reg = value;
+                        }
+                    } else if depth == 3 && reserved_memory_depth == Some(2) && name == b"reg" {
+                        for (base, size) in fdt::reg_entries(value, address_cells[2], size_cells[2])
+                        {
+                            if size != 0 {
+                                reserve(base, size)?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for entry in fdt.memory_reservations() {
+            let (base, size) = entry?;
+            if size != 0 {
+                reserve(base, size)?;
+            }
+        }
+        for &(base, size) in &reserved[..reserved_count] {
+            map.carve_out_ram(base, size, Reservation::Firmware)?;
+        }
+
+        Ok(map)
+    }
+
+    pub fn regions(&self) -> core::slice::Iter<'_, Region> {
+        self.regions[..self.count].iter()
+    }
+
+    /// The free RAM regions.
+    pub fn free_ram(&self) -> impl Iterator<Item = &Region> {
+        self.regions().filter(|r| r.kind == RegionKind::Ram)
+    }
+
+    pub fn find(&self, addr: u64) -> Option<&Region> {
+        self.regions().find(|r| r.contains(addr))
+    }
+
+    fn check_range(base: u64, size: u64) -> Result<u64, MemoryMapError> {
+        if size == 0 {
+            return Err(MemoryMapError::EmptyRegion);
+        }
+        base.checked_add(size)
+            .ok_or(MemoryMapError::InvalidRegion { base, size })
+    }
+
+    fn insert(&mut self, index: usize, region: Region) {
+        debug_assert!(self.count < MAX_REGIONS);
+        self.regions.copy_within(index..self.count, index + 1);
+        self.regions[index] = region;
+        self.count += 1;
+    }
+
+    /// Adds RAM or an MMIO window, or a reservation outside of the RAM.
+    pub fn add(&mut self, base: u64, size: u64, kind: RegionKind) -> Result<(), MemoryMapError> {
+        let end = Self::check_range(base, size)?;
+        if let Some(r) = self.regions().find(|r| r.base < end && base < r.end()) {
+            return Err(MemoryMapError::Overlap {
+                base,
+                size,
+                existing_base: r.base,
+            });
+        }
+        if self.count == MAX_REGIONS {
+            return Err(MemoryMapError::TooManyRegions);
+        }
+
+        let index = self.regions().take_while(|r| r.base < base).count();
+        self.insert(index, Region { base, size, kind });
+        Ok(())
+    }
+
+    /// Reserves the free RAM in the range. The range must be covered by
+    /// RAM, the parts of it reserved already keep their reservation.
+    pub fn carve_out(
+        &mut self,
+        base: u64,
+        size: u64,
+        reservation: Reservation,
+    ) -> Result<(), MemoryMapError> {
+        let end = Self::check_range(base, size)?;
+
+        // Check the coverage and count the regions the split adds.
+        let mut covered = base;
+        let mut new_regions = 0;
+        for r in self.regions().filter(|r| r.base < end && base < r.end()) {
+            if r.base > covered || !r.is_ram() {
+                return Err(MemoryMapError::NotRam { base, size });
+            }
+            covered = r.end();
+            if r.kind == RegionKind::Ram {
+                new_regions += (r.base < base) as usize + (end < r.end()) as usize;
+            }
+        }
+        if covered < end {
+            return Err(MemoryMapError::NotRam { base, size });
+        }
+        if self.count + new_regions > MAX_REGIONS {
+            return Err(MemoryMapError::TooManyRegions);
+        }
+
+        let mut index = 0;
+        while index < self.count {
+            let r = self.regions[index];
+            if r.kind != RegionKind::Ram || r.end() <= base || end <= r.base {
+                index += 1;
+                continue;
+            }
+
+            let lo = r.base.max(base);
+            let hi = r.end().min(end);
+            self.regions[index] = Region {
+                base: lo,
+                size: hi - lo,
+                kind: RegionKind::Reserved(reservation),
+            };
+            if r.base < lo {
+                self.insert(
+                    index,
+                    Region {
+                        base: r.base,
+                        size: lo - r.base,
+                        kind: RegionKind::Ram,
+                    },
+                );
+                index += 1;
+            }
+            if hi < r.end() {
+                self.insert(
+                    index + 1,
+                    Region {
+                        base: hi,
+                        size: r.end() - hi,
+                        kind: RegionKind::Ram,
+                    },
+                );
+                index += 1;
+            }
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Reserves the free RAM in the range, skipping the parts of the range
+    /// outside of the RAM.
+    pub fn carve_out_ram(
+        &mut self,
+        base: u64,
+        size: u64,
+        reservation: Reservation,
+    ) -> Result<(), MemoryMapError> {
+        let end = Self::check_range(base, size)?;
+        let mut covered = base;
+        while let Some((lo, hi)) = self
+            .regions()
+            .find(|r| r.is_ram() && covered < r.end() && r.base < end)
+            .map(|r| (r.base.max(covered), r.end().min(end)))
+        {
+            self.carve_out(lo, hi - lo, reservation)?;
+            covered = hi;
+        }
+        Ok(())
+    }
+
+    /// Reserves the first free RAM that fits, `align` must be a power of 2.
+    pub fn allocate(
+        &mut self,
+        size: u64,
+        align: u64,
+        reservation: Reservation,
+    ) -> Result<u64, MemoryMapError> {
+        assert!(align.is_power_of_two());
+        if size == 0 {
+            return Err(MemoryMapError::EmptyRegion);
+        }
+
+        let base = self
+            .free_ram()
+            .find_map(|r| {
+                let base = r.base.checked_add(align - 1)? & !(align - 1);
+                (base.checked_add(size)? <= r.end()).then_some(base)
+            })
+            .ok_or(MemoryMapError::OutOfMemory { size, align })?;
+        self.carve_out(base, size, reservation)?;
+        Ok(base)
+    }
+}
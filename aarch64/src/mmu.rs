@@ -1,5 +1,20 @@
 use bitfield_struct::bitfield;
 
+use crate::regs::access::ReadWriteRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::IntermPhysAddrSize;
+use crate::regs::MairIndex;
+use crate::regs::PermissionIndirectionE0El1;
+use crate::regs::PermissionIndirectionEl1;
+use crate::regs::PermissionOverlayEl1;
+use crate::regs::SystemControlEl1;
+use crate::regs::TranslationBase0El1;
+use crate::regs::TranslationBase1El1;
+use crate::regs::TranslationControl2El1;
+use crate::regs::TranslationControlEl1;
+use crate::regs::TranslationGranule0;
+use crate::regs::TranslationGranule1;
+
 #[bitfield(u64)]
 pub struct PageTableEntry {
     pub valid: bool,
@@ -44,12 +59,80 @@ pub struct PageBlockEntry {
     pub not_global: bool,
     #[bits(35)]
     pub address_pfn: u64,
-    #[bits(4)]
+    #[bits(3)]
     _mbz1: u64,
+    /// Guarded Page, the indirect branches must land on BTI instructions
+    pub guarded: bool,
     pub dirty: bool,
     pub contig: bool,
     pub priv_x_never: bool,
     pub user_x_never: bool,
+    #[bits(5)]
+    _mbz2: u64,
+    /// The index into POR_EL1 when the Permission Overlay is enabled
+    #[bits(3)]
+    pub po_index: u64,
+    #[bits(1)]
+    _mbz3: u64,
+}
+
+impl PageBlockEntry {
+    /// The index into PIR_EL1 and PIRE0_EL1 when the Permission Indirection
+    /// is enabled, made up of the bits {UXN, PXN, DBM, AP[1]}.
+    pub fn pi_index(&self) -> usize {
+        (self.user_x_never() as usize) << 3
+            | (self.priv_x_never() as usize) << 2
+            | (self.dirty() as usize) << 1
+            | (self.access_perm() & 1) as usize
+    }
+
+    pub fn with_pi_index(self, pi_index: usize) -> Self {
+        assert!(pi_index < 16);
+        self.with_user_x_never(pi_index & 0b1000 != 0)
+            .with_priv_x_never(pi_index & 0b0100 != 0)
+            .with_dirty(pi_index & 0b0010 != 0)
+            .with_access_perm(self.access_perm() & !1 | (pi_index & 0b0001) as u64)
+    }
+}
+
+/// VMSAv9-128 (FEAT_D128) table descriptor
+#[bitfield(u128)]
+pub struct PageTableEntryD128 {
+    pub valid: bool,
+    pub table: bool, // Use PageBlockEntryD128 if `false`
+    #[bits(10)]
+    _mbz0: u64,
+    #[bits(44)]
+    pub next_table_pfn: u64,
+    #[bits(53)]
+    _mbz1: u64,
+    #[bits(2)]
+    pub skip_level: u64,
+    #[bits(17)]
+    _mbz2: u64,
+}
+
+/// VMSAv9-128 (FEAT_D128) block and page descriptor. The access
+/// permissions always come from the Permission Indirection.
+#[bitfield(u128)]
+pub struct PageBlockEntryD128 {
+    pub valid: bool,
+    pub page: bool,
+    #[bits(4)]
+    pub mair_idx: usize,
+    #[bits(1)]
+    _mbz0: u64,
+    pub not_dirty: bool,
+    #[bits(2)]
+    pub share_perm: u64,
+    pub accessed: bool,
+    pub not_global: bool,
+    #[bits(44)]
+    pub address_pfn: u64,
+    #[bits(59)]
+    _mbz1: u64,
+    #[bits(4)]
+    pub pi_index: usize,
     #[bits(9)]
     _mbz2: u64,
 }
@@ -96,17 +179,6 @@ const PAGE_SIZE_2M: u64 = 1 << PAGE_SHIFT_2M;
 const PAGE_SIZE_1G: u64 = 1 << PAGE_SHIFT_1G;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PageMapError {
-    OutOfMemory,
-    NonCanonicalVirtAddress,
-    MisalignedVirtAddress,
-    MisalignedPhysAddress,
-    InvalidMappingSize,
-    EmptyMapping,
-    AlreadyMapped,
-}
-
-#[derive(Debug, Clone, Copy)]
 #[repr(u64)]
 pub enum PageSize {
     Small = PAGE_SIZE_4K,
@@ -114,6 +186,81 @@ pub enum PageSize {
     Huge = PAGE_SIZE_1G,
 }
 
+impl core::fmt::Display for PageSize {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PageSize::Small => write!(f, "4KiB"),
+            PageSize::Large => write!(f, "2MiB"),
+            PageSize::Huge => write!(f, "1GiB"),
+        }
+    }
+}
+
+/// How the leaf entries grant the access permissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionModel {
+    /// The AP and XN bits, read-write at EL1 and EL0. The D128 entries
+    /// get the PIIndex of such an entry instead.
+    AccessBits,
+    /// The Stage 1 Permission Indirection (FEAT_S1PIE), the leaf entries
+    /// carry the given PIIndex. AP[2] is the dirty state then, and is left
+    /// clear so that writes are allowed.
+    Indirect(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageMapError {
+    /// No space left for a page table at `level` while mapping `virt_addr`.
+    OutOfMemory {
+        virt_addr: u64,
+        level: usize,
+    },
+    NonCanonicalVirtAddress(u64),
+    MisalignedVirtAddress(u64),
+    MisalignedPhysAddress(u64),
+    InvalidMappingSize(u64),
+    EmptyMapping,
+    /// The entry at `level` that `virt_addr` walks through is already
+    /// a valid leaf (`entry` is its raw value), so a page of `page_size`
+    /// cannot be placed there.
+    AlreadyMapped {
+        virt_addr: u64,
+        level: usize,
+        entry: u128,
+        page_size: PageSize,
+    },
+}
+
+impl core::fmt::Display for PageMapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PageMapError::OutOfMemory { virt_addr, level } => write!(
+                f,
+                "out of page table memory at level {level} mapping VA {virt_addr:#x}"
+            ),
+            PageMapError::NonCanonicalVirtAddress(va) => {
+                write!(f, "non-canonical VA {va:#x}")
+            }
+            PageMapError::MisalignedVirtAddress(va) => write!(f, "misaligned VA {va:#x}"),
+            PageMapError::MisalignedPhysAddress(pa) => write!(f, "misaligned PA {pa:#x}"),
+            PageMapError::InvalidMappingSize(size) => {
+                write!(f, "mapping size {size:#x} is not a multiple of 4KiB")
+            }
+            PageMapError::EmptyMapping => write!(f, "empty mapping"),
+            PageMapError::AlreadyMapped {
+                virt_addr,
+                level,
+                entry,
+                page_size,
+            } => write!(
+                f,
+                "VA {virt_addr:#x} is already mapped at level {level} by entry {entry:#018x}, \
+                 cannot map a {page_size} page"
+            ),
+        }
+    }
+}
+
 impl From<PageSize> for u64 {
     fn from(value: PageSize) -> Self {
         value as u64
@@ -141,6 +288,85 @@ const fn aligned(x: u64, page_size: PageSize) -> bool {
     (x & ones_enough) == 0
 }
 
+/// The translation table descriptor format. The tables take
+/// a 4KiB page in both formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorFormat {
+    /// VMSAv8-64, 512 64-bit entries per table, 48-bit VA (`T0SZ` 16).
+    D64,
+    /// VMSAv9-128, 256 128-bit entries per table, 44-bit VA (`T0SZ` 20),
+    /// so the blocks are 1MiB at level 2 and 256MiB at level 1.
+    D128,
+}
+
+impl DescriptorFormat {
+    pub const fn entry_size(self) -> usize {
+        match self {
+            DescriptorFormat::D64 => 8,
+            DescriptorFormat::D128 => 16,
+        }
+    }
+
+    const fn index_bits(self) -> u64 {
+        match self {
+            DescriptorFormat::D64 => 9,
+            DescriptorFormat::D128 => 8,
+        }
+    }
+
+    /// The VA bits translated starting from level 0.
+    pub const fn va_bits(self) -> u64 {
+        PAGE_SHIFT_4K + 4 * self.index_bits()
+    }
+
+    /// The shift of the region size an entry at `level` maps.
+    pub const fn level_shift(self, level: usize) -> u64 {
+        PAGE_SHIFT_4K + (3 - level as u64) * self.index_bits()
+    }
+
+    fn table_index(self, virt_addr: VirtualAddress, level: usize) -> usize {
+        match self {
+            DescriptorFormat::D64 => virt_addr.lvl_index(level),
+            DescriptorFormat::D128 => {
+                ((virt_addr.0 >> self.level_shift(level)) & ((1 << self.index_bits()) - 1)) as usize
+            }
+        }
+    }
+
+    fn is_canonical(self, virt_addr: VirtualAddress) -> bool {
+        let unused_bits = 64 - self.va_bits();
+        ((virt_addr.0 as i64) << unused_bits >> unused_bits) == virt_addr.0 as i64
+    }
+
+    /// The level of the entries mapping a page, and how many of them
+    /// it takes: the pages larger than 4KiB take several blocks in D128.
+    fn leaf_level_and_count(self, page_size: PageSize) -> (usize, usize) {
+        let level = match page_size {
+            PageSize::Small => 3,
+            PageSize::Large => 2,
+            PageSize::Huge => 1,
+        };
+        (
+            level,
+            (page_size as u64 >> self.level_shift(level)) as usize,
+        )
+    }
+}
+
+/// The result of walking the page tables for a VA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Translation {
+    pub phys_addr: u64,
+    /// The level of the leaf entry.
+    pub level: usize,
+    pub mair_idx: usize,
+    /// The index into PIR_EL1 and PIRE0_EL1 the leaf entry has.
+    pub pi_index: usize,
+}
+
+// The PIIndex the legacy read-write entries have, see `PermissionModel`.
+const ACCESS_BITS_PI_INDEX: usize = 1;
+
 #[derive(Debug)]
 pub struct PageTableSpace<'a> {
     /// Physical address at which the page table area starts.
@@ -155,15 +381,34 @@ pub struct PageTableSpace<'a> {
     /// Statistics of page tables allocaions for each level.
     /// `lvl_stats[0]` is going to be always `1`.
     lvl_stats: [usize; 4],
+    /// How the permissions are encoded in the leaf entries.
+    permission_model: PermissionModel,
+    /// The leaf entries are Guarded Pages.
+    guarded: bool,
+    format: DescriptorFormat,
 }
 
 impl<'a> PageTableSpace<'a> {
     pub fn new(phys_start: usize, space: &'a mut [u8]) -> Result<Self, PageMapError> {
+        Self::with_format(phys_start, space, DescriptorFormat::D64)
+    }
+
+    /// The tables with 128-bit descriptors, need FEAT_D128 and
+    /// `TCR2_EL1.D128` set.
+    pub fn new_d128(phys_start: usize, space: &'a mut [u8]) -> Result<Self, PageMapError> {
+        Self::with_format(phys_start, space, DescriptorFormat::D128)
+    }
+
+    fn with_format(
+        phys_start: usize,
+        space: &'a mut [u8],
+        format: DescriptorFormat,
+    ) -> Result<Self, PageMapError> {
         if !aligned(phys_start as u64, PageSize::Small) {
-            return Err(PageMapError::MisalignedPhysAddress);
+            return Err(PageMapError::MisalignedPhysAddress(phys_start as u64));
         }
         if !aligned(space.len() as u64, PageSize::Small) {
-            return Err(PageMapError::InvalidMappingSize);
+            return Err(PageMapError::InvalidMappingSize(space.len() as u64));
         }
         if space.is_empty() {
             return Err(PageMapError::EmptyMapping);
@@ -179,12 +424,44 @@ impl<'a> PageTableSpace<'a> {
             space,
             brk: phys_start + PAGE_SIZE_4K as usize,
             lvl_stats: [1, 0, 0, 0],
+            permission_model: PermissionModel::AccessBits,
+            guarded: false,
+            format,
         })
     }
 
-    fn allocate_page_table(&mut self, level: usize) -> Result<u64, PageMapError> {
+    pub fn format(&self) -> DescriptorFormat {
+        self.format
+    }
+
+    /// Applies to the entries mapped afterwards.
+    pub fn set_permission_model(&mut self, permission_model: PermissionModel) {
+        if let PermissionModel::Indirect(pi_index) = permission_model {
+            assert!(pi_index < 16, "invalid PIIndex {pi_index}");
+        }
+        self.permission_model = permission_model;
+    }
+
+    pub fn permission_model(&self) -> PermissionModel {
+        self.permission_model
+    }
+
+    /// Applies to the D64 entries mapped afterwards, the branch target
+    /// checks of FEAT_BTI happen only on the Guarded Pages.
+    pub fn set_guarded(&mut self, guarded: bool) {
+        self.guarded = guarded;
+    }
+
+    fn allocate_page_table(
+        &mut self,
+        level: usize,
+        virt_addr: VirtualAddress,
+    ) -> Result<u64, PageMapError> {
         if self.brk >= self.phys_page_table_root + self.space.len() {
-            return Err(PageMapError::OutOfMemory);
+            return Err(PageMapError::OutOfMemory {
+                virt_addr: virt_addr.0,
+                level,
+            });
         }
         let page_table_phys_addr = self.brk;
         self.brk += PAGE_SIZE_4K as usize;
@@ -201,39 +478,143 @@ impl<'a> PageTableSpace<'a> {
         self.lvl_stats
     }
 
-    fn read_entry(&self, phys_table_start: u64, index: usize) -> u64 {
+    fn entry_pos(&self, phys_table_start: u64, index: usize) -> usize {
         debug_assert!(
             (phys_table_start as usize) < self.phys_page_table_root + self.space.len()
                 && (phys_table_start as usize) >= self.phys_page_table_root
         );
         debug_assert!(aligned(phys_table_start, PageSize::Small));
-        debug_assert!(index < PAGE_SIZE_4K as usize / core::mem::size_of::<PageTableEntry>());
-
-        let pos = phys_table_start as usize - self.phys_page_table_root
-            + index * core::mem::size_of::<PageTableEntry>();
-        u64::from_le_bytes([
-            self.space[pos],
-            self.space[pos + 1],
-            self.space[pos + 2],
-            self.space[pos + 3],
-            self.space[pos + 4],
-            self.space[pos + 5],
-            self.space[pos + 6],
-            self.space[pos + 7],
-        ])
-    }
-
-    fn write_entry(&mut self, phys_table_start: u64, index: usize, entry: u64) {
-        debug_assert!(
-            (phys_table_start as usize) < self.phys_page_table_root + self.space.len()
-                && (phys_table_start as usize) >= self.phys_page_table_root
-        );
-        debug_assert!(aligned(phys_table_start, PageSize::Small));
-        debug_assert!(index < PAGE_SIZE_4K as usize / core::mem::size_of::<PageTableEntry>());
+        debug_assert!(index < PAGE_SIZE_4K as usize / self.format.entry_size());
+
+        phys_table_start as usize - self.phys_page_table_root + index * self.format.entry_size()
+    }
+
+    /// The entry is zero-extended for `DescriptorFormat::D64`.
+    fn read_entry(&self, phys_table_start: u64, index: usize) -> u128 {
+        let pos = self.entry_pos(phys_table_start, index);
+        let mut bytes = [0; 16];
+        bytes[..self.format.entry_size()]
+            .copy_from_slice(&self.space[pos..pos + self.format.entry_size()]);
+        u128::from_le_bytes(bytes)
+    }
+
+    fn write_entry(&mut self, phys_table_start: u64, index: usize, entry: u128) {
+        let pos = self.entry_pos(phys_table_start, index);
+        let size = self.format.entry_size();
+        debug_assert!(size == 16 || entry >> 64 == 0);
+        self.space[pos..pos + size].copy_from_slice(&entry.to_le_bytes()[..size]);
+    }
+
+    /// Returns the physical address of the next table if the entry is
+    /// a valid table descriptor, and if it is valid.
+    fn decode_entry(&self, entry: u128) -> (bool, Option<u64>) {
+        let (valid, table, next_table_pfn) = match self.format {
+            DescriptorFormat::D64 => {
+                let entry = PageTableEntry::from(entry as u64);
+                (entry.valid(), entry.table(), entry.next_table_pfn())
+            }
+            DescriptorFormat::D128 => {
+                let entry = PageTableEntryD128::from(entry);
+                (entry.valid(), entry.table(), entry.next_table_pfn())
+            }
+        };
+        (
+            valid,
+            (valid && table).then_some(next_table_pfn << PAGE_SHIFT_4K),
+        )
+    }
+
+    fn table_entry(&self, next_table_phys_addr: u64) -> u128 {
+        match self.format {
+            DescriptorFormat::D64 => u64::from(
+                PageTableEntry::new()
+                    .with_valid(true)
+                    .with_table(true)
+                    .with_access_perm(1)
+                    .with_next_table_pfn(next_table_phys_addr >> PAGE_SHIFT_4K),
+            ) as u128,
+            DescriptorFormat::D128 => PageTableEntryD128::new()
+                .with_valid(true)
+                .with_table(true)
+                .with_next_table_pfn(next_table_phys_addr >> PAGE_SHIFT_4K)
+                .into(),
+        }
+    }
+
+    fn leaf_entry(&self, phys_addr: u64, level: usize, memory_attribute_index: MairIndex) -> u128 {
+        // Without setting the `accessed` flag, qemu fails translation
+        // if the HA flag is not enabled in the TCR register. Support for
+        // HA in indicated in the MMU features register #1.
+
+        match self.format {
+            DescriptorFormat::D64 => {
+                let page_entry = PageBlockEntry::new()
+                    .with_valid(true)
+                    .with_page(level == 3)
+                    .with_accessed(true)
+                    .with_share_perm(3)
+                    .with_mair_idx(memory_attribute_index.index())
+                    .with_address_pfn(phys_addr >> PAGE_SHIFT_4K)
+                    .with_guarded(self.guarded);
+                u64::from(match self.permission_model {
+                    PermissionModel::AccessBits => page_entry.with_access_perm(1),
+                    PermissionModel::Indirect(pi_index) => page_entry.with_pi_index(pi_index),
+                }) as u128
+            }
+            DescriptorFormat::D128 => PageBlockEntryD128::new()
+                .with_valid(true)
+                .with_page(level == 3)
+                .with_accessed(true)
+                .with_share_perm(3)
+                .with_mair_idx(memory_attribute_index.index())
+                .with_address_pfn(phys_addr >> PAGE_SHIFT_4K)
+                .with_pi_index(match self.permission_model {
+                    PermissionModel::AccessBits => ACCESS_BITS_PI_INDEX,
+                    PermissionModel::Indirect(pi_index) => pi_index,
+                })
+                .into(),
+        }
+    }
+
+    /// Walks the page tables, `None` if the VA is not mapped.
+    pub fn translate(&self, virt_addr: VirtualAddress) -> Option<Translation> {
+        if !self.format.is_canonical(virt_addr) {
+            return None;
+        }
+
+        let mut table_phys_addr = self.phys_page_table_root as u64;
+        for level in 0..4 {
+            let entry = self.read_entry(table_phys_addr, self.format.table_index(virt_addr, level));
+            match self.decode_entry(entry) {
+                (false, _) => return None,
+                (true, Some(next_table_phys_addr)) if level < 3 => {
+                    table_phys_addr = next_table_phys_addr;
+                    continue;
+                }
+                (true, _) => {}
+            }
 
-        let pos = phys_table_start as usize - self.phys_page_table_root
-            + index * core::mem::size_of::<PageTableEntry>();
-        self.space[pos..pos + 8].copy_from_slice(&entry.to_le_bytes());
+            let (address_pfn, mair_idx, pi_index) = match self.format {
+                DescriptorFormat::D64 => {
+                    let entry = PageBlockEntry::from(entry as u64);
+                    (entry.address_pfn(), entry.mair_idx(), entry.pi_index())
+                }
+                DescriptorFormat::D128 => {
+                    let entry = PageBlockEntryD128::from(entry);
+                    (entry.address_pfn(), entry.mair_idx(), entry.pi_index())
+                }
+            };
+            let offset_mask = (1 << self.format.level_shift(level)) - 1;
+            return Some(Translation {
+                phys_addr: (address_pfn << PAGE_SHIFT_4K) & !offset_mask
+                    | virt_addr.0 & offset_mask,
+                level,
+                mair_idx,
+                pi_index,
+            });
+        }
+
+        None
     }
 
     fn check_addresses_and_map_size(
@@ -243,17 +624,17 @@ impl<'a> PageTableSpace<'a> {
         page_size: PageSize,
     ) -> Result<(), PageMapError> {
         if virt_addr.offset() != 0 {
-            return Err(PageMapError::MisalignedVirtAddress);
+            return Err(PageMapError::MisalignedVirtAddress(virt_addr.0));
         }
-        if !virt_addr.is_canonical() {
-            return Err(PageMapError::NonCanonicalVirtAddress);
+        if !self.format.is_canonical(virt_addr) {
+            return Err(PageMapError::NonCanonicalVirtAddress(virt_addr.0));
         }
 
         if !aligned(phys_addr, page_size) {
-            return Err(PageMapError::MisalignedPhysAddress);
+            return Err(PageMapError::MisalignedPhysAddress(phys_addr));
         }
         if !aligned(virt_addr.0, page_size) {
-            return Err(PageMapError::MisalignedVirtAddress);
+            return Err(PageMapError::MisalignedVirtAddress(virt_addr.0));
         }
 
         Ok(())
@@ -263,68 +644,61 @@ impl<'a> PageTableSpace<'a> {
         &mut self,
         phys_addr: u64,
         virt_addr: VirtualAddress,
-        memory_attribute_index: usize,
+        memory_attribute_index: MairIndex,
         page_size: PageSize,
     ) -> Result<(), PageMapError> {
         let mut table_phys_addr = self.phys_page_table_root as u64;
         let mut level = 0;
-        let leaf_level = match page_size {
-            PageSize::Small => 3,
-            PageSize::Large => 2,
-            PageSize::Huge => 1,
-        };
+        let (leaf_level, leaf_count) = self.format.leaf_level_and_count(page_size);
         while level < leaf_level {
-            let mut table_entry =
-                PageTableEntry::from(self.read_entry(table_phys_addr, virt_addr.lvl_index(level)));
-
-            if table_entry.valid() && !table_entry.table() {
-                return Err(PageMapError::AlreadyMapped);
-            }
-
-            if !table_entry.valid() {
-                let next_table_phys_addr = self.allocate_page_table(level + 1)?;
-
-                table_entry = PageTableEntry::new()
-                    .with_valid(true)
-                    .with_table(true)
-                    .with_access_perm(1)
-                    .with_next_table_pfn(next_table_phys_addr >> PAGE_SHIFT_4K);
-
-                self.write_entry(
-                    table_phys_addr,
-                    virt_addr.lvl_index(level),
-                    table_entry.into(),
-                );
-            }
-            table_phys_addr = table_entry.next_table_pfn() << PAGE_SHIFT_4K;
+            let index = self.format.table_index(virt_addr, level);
+            let entry = self.read_entry(table_phys_addr, index);
+
+            table_phys_addr = match self.decode_entry(entry) {
+                (true, Some(next_table_phys_addr)) => next_table_phys_addr,
+                (true, None) => {
+                    return Err(PageMapError::AlreadyMapped {
+                        virt_addr: virt_addr.0,
+                        level,
+                        entry,
+                        page_size,
+                    });
+                }
+                (false, _) => {
+                    let next_table_phys_addr = self.allocate_page_table(level + 1, virt_addr)?;
+                    self.write_entry(
+                        table_phys_addr,
+                        index,
+                        self.table_entry(next_table_phys_addr),
+                    );
+                    next_table_phys_addr
+                }
+            };
 
             level += 1;
         }
 
-        let mut page_entry =
-            PageBlockEntry::from(self.read_entry(table_phys_addr, virt_addr.lvl_index(level)));
-        if page_entry.valid() {
-            return Err(PageMapError::AlreadyMapped);
+        let first_index = self.format.table_index(virt_addr, level);
+        for index in first_index..first_index + leaf_count {
+            let entry = self.read_entry(table_phys_addr, index);
+            if self.decode_entry(entry).0 {
+                return Err(PageMapError::AlreadyMapped {
+                    virt_addr: virt_addr.0,
+                    level,
+                    entry,
+                    page_size,
+                });
+            }
         }
 
-        // Without setting the `accessed` flag, qemu fails translation
-        // if the HA flag is not enabled in the TCR register. Support for
-        // HA in indicated in the MMU features register #1.
-
-        page_entry = PageBlockEntry::new()
-            .with_valid(true)
-            .with_page(leaf_level == 3)
-            .with_accessed(true)
-            .with_access_perm(1)
-            .with_share_perm(3)
-            .with_mair_idx(memory_attribute_index)
-            .with_address_pfn(phys_addr >> PAGE_SHIFT_4K);
-
-        self.write_entry(
-            table_phys_addr,
-            virt_addr.lvl_index(level),
-            page_entry.into(),
-        );
+        for i in 0..leaf_count {
+            let entry = self.leaf_entry(
+                phys_addr + ((i as u64) << self.format.level_shift(level)),
+                level,
+                memory_attribute_index,
+            );
+            self.write_entry(table_phys_addr, first_index + i, entry);
+        }
 
         Ok(())
     }
@@ -335,7 +709,7 @@ impl<'a> PageTableSpace<'a> {
         virt_addr: VirtualAddress,
         page_count: usize,
         page_size: PageSize,
-        memory_attribute_index: usize,
+        memory_attribute_index: MairIndex,
     ) -> Result<(), PageMapError> {
         self.check_addresses_and_map_size(phys_addr, virt_addr, page_size)?;
 
@@ -414,22 +788,22 @@ impl<'a> PageTableSpace<'a> {
         phys_addr: u64,
         virt_addr: VirtualAddress,
         size: u64,
-        memory_attribute_index: usize,
+        memory_attribute_index: MairIndex,
     ) -> Result<(), PageMapError> {
         if !aligned(phys_addr, PageSize::Small) {
-            return Err(PageMapError::MisalignedPhysAddress);
+            return Err(PageMapError::MisalignedPhysAddress(phys_addr));
         }
         if !aligned(size, PageSize::Small) {
-            return Err(PageMapError::InvalidMappingSize);
+            return Err(PageMapError::InvalidMappingSize(size));
         }
         if size == 0 {
             return Err(PageMapError::EmptyMapping);
         }
         if virt_addr.offset() != 0 {
-            return Err(PageMapError::MisalignedVirtAddress);
+            return Err(PageMapError::MisalignedVirtAddress(virt_addr.0));
         }
-        if !virt_addr.is_canonical() {
-            return Err(PageMapError::NonCanonicalVirtAddress);
+        if !self.format.is_canonical(virt_addr) {
+            return Err(PageMapError::NonCanonicalVirtAddress(virt_addr.0));
         }
 
         let mut non_mapped = size;
@@ -460,3 +834,67 @@ impl<'a> PageTableSpace<'a> {
         Ok(())
     }
 }
+
+/// The options of the EL1&0 translation regime `enable` sets up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TranslationConfig {
+    /// Ignore the top byte of the addresses, needed for the tagged ones.
+    pub tbi: bool,
+    /// Set `TCR2_EL1.PIE`, see `PermissionModel::Indirect`.
+    pub pie: bool,
+    /// Set `TCR2_EL1.POE`, takes `pie`.
+    pub poe: bool,
+}
+
+/// Points TTBR0_EL1 to the root table, sets up TCR_EL1 and TCR2_EL1 for
+/// the descriptor format, and then turns on the MMU and the caches.
+/// MAIR_EL1 must be set up before. Returns the TCR2_EL1 written, if any.
+pub fn enable(
+    page_tables: &PageTableSpace,
+    config: TranslationConfig,
+) -> Option<TranslationControl2El1> {
+    let d128 = page_tables.format() == DescriptorFormat::D128;
+    // BADDR is the bits [47:1] of the table address.
+    let mut ttbr0_el1 = TranslationBase0El1::new()
+        .with_asid(0)
+        .with_baddr(page_tables.phys_page_table_root as u64 >> 1);
+    if d128 {
+        ttbr0_el1.store128(0);
+    } else {
+        ttbr0_el1.store();
+    }
+    TranslationBase1El1::new().store();
+    TranslationControlEl1::new()
+        .with_t0sz(64 - page_tables.format().va_bits())
+        .with_irgn0(1)
+        .with_orgn0(1)
+        .with_sh0(3)
+        .with_tg0(TranslationGranule0::_4KB)
+        .with_epd1(1)
+        .with_tg1(TranslationGranule1::_4KB)
+        .with_ips(IntermPhysAddrSize::_48_bits_256TB)
+        .with_tbi0(config.tbi as u64)
+        // .with_ha(1) // Should checked against the MMU feature reg #1
+        // .with_hd(1) // Should checked against the MMU feature reg #1
+        .store();
+
+    let tcr2_el1 = (config.pie || d128).then(|| {
+        if config.pie {
+            PermissionIndirectionEl1::default().store();
+            PermissionIndirectionE0El1::default().store();
+            if config.poe {
+                PermissionOverlayEl1::default().store();
+            }
+        }
+        let tcr2_el1 = TranslationControl2El1::new()
+            .with_pie(config.pie as u64)
+            .with_poe((config.pie && config.poe) as u64)
+            .with_d128(d128 as u64);
+        tcr2_el1.store();
+        tcr2_el1
+    });
+
+    SystemControlEl1::modify(|r| r.with_m(1).with_a(1).with_c(1).with_i(1));
+
+    tcr2_el1
+}
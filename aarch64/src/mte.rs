@@ -0,0 +1,123 @@
+//! Memory Tagging Extension helpers
+//!
+//! The tag of a pointer lives in the bits [59:56] of the address, and
+//! the Allocation Tags are stored per 16 bytes granule of the memory
+//! mapped with `MemoryAttributeEl1::NormalTagged`. To have the tags
+//! checked, `TCR_EL1.TBI0` must be set as well as `SCTLR_EL1.ATA`
+//! and `SCTLR_EL1.TCF`.
+//!
+//! The tag instructions are available only when building for AArch64.
+
+/// The size of memory covered by one Allocation Tag
+pub const TAG_GRANULE: usize = 16;
+
+const TAG_SHIFT: u32 = 56;
+const TAG_MASK: u64 = 0xf << TAG_SHIFT;
+
+/// Extracts the Logical Address Tag of the pointer
+pub fn tag_of<T>(ptr: *const T) -> u8 {
+    ((ptr as u64 & TAG_MASK) >> TAG_SHIFT) as u8
+}
+
+/// Replaces the Logical Address Tag of the pointer
+pub fn with_tag<T>(ptr: *mut T, tag: u8) -> *mut T {
+    ((ptr as u64 & !TAG_MASK) | (((tag & 0xf) as u64) << TAG_SHIFT)) as *mut T
+}
+
+/// Inserts a random Logical Address Tag into the pointer. The tags set in the
+/// `exclude` mask and in `GCR_EL1.Exclude` are not generated.
+#[cfg(target_arch = "aarch64")]
+pub fn irg<T>(ptr: *mut T, exclude: u16) -> *mut T {
+    let mut tagged = ptr as u64;
+    // SAFETY: only computes an address.
+    unsafe {
+        core::arch::asm!(
+            ".arch_extension memtag",
+            "irg {0}, {0}, {1}",
+            inout(reg) tagged,
+            in(reg) exclude as u64,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    tagged as *mut T
+}
+
+/// Sets the Allocation Tag of the granule to the Logical Address Tag
+/// of the pointer.
+///
+/// # Safety
+///
+/// The pointer must be aligned to `TAG_GRANULE` and point into the tagged memory.
+#[cfg(target_arch = "aarch64")]
+pub unsafe fn stg<T>(ptr: *mut T) {
+    unsafe {
+        core::arch::asm!(
+            ".arch_extension memtag",
+            "stg {0}, [{0}]",
+            in(reg) ptr,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+/// Sets the Allocation Tags of two consecutive granules to the Logical
+/// Address Tag of the pointer.
+///
+/// # Safety
+///
+/// The pointer must be aligned to `TAG_GRANULE` and point into the tagged memory
+/// that spans at least two granules.
+#[cfg(target_arch = "aarch64")]
+pub unsafe fn st2g<T>(ptr: *mut T) {
+    unsafe {
+        core::arch::asm!(
+            ".arch_extension memtag",
+            "st2g {0}, [{0}]",
+            in(reg) ptr,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+/// Returns the pointer with the Logical Address Tag replaced by
+/// the Allocation Tag of the granule it points to.
+///
+/// # Safety
+///
+/// The pointer must point into the tagged memory.
+#[cfg(target_arch = "aarch64")]
+pub unsafe fn ldg<T>(ptr: *const T) -> *mut T {
+    let mut tagged = ptr as u64;
+    unsafe {
+        core::arch::asm!(
+            ".arch_extension memtag",
+            "ldg {0}, [{1}]",
+            inout(reg) tagged,
+            in(reg) ptr,
+            options(readonly, nostack, preserves_flags)
+        );
+    }
+    tagged as *mut T
+}
+
+/// Sets the Allocation Tags of the `size` bytes at the pointer to its
+/// Logical Address Tag.
+///
+/// # Safety
+///
+/// The pointer and the size must be aligned to `TAG_GRANULE`, and
+/// the whole range must be in the tagged memory.
+#[cfg(target_arch = "aarch64")]
+pub unsafe fn tag_range<T>(ptr: *mut T, size: usize) {
+    assert!((ptr as usize | size) & (TAG_GRANULE - 1) == 0);
+
+    let ptr = ptr as *mut u8;
+    let mut offset = 0;
+    while offset + 2 * TAG_GRANULE <= size {
+        unsafe { st2g(ptr.wrapping_add(offset)) };
+        offset += 2 * TAG_GRANULE;
+    }
+    if offset < size {
+        unsafe { stg(ptr.wrapping_add(offset)) };
+    }
+}
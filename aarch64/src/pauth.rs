@@ -0,0 +1,120 @@
+//! Pointer authentication and branch target identification at EL1
+//!
+//! The code built with `-Z branch-protection=pac-ret,bti` signs the return
+//! addresses with the IA key and starts the functions with the landing
+//! pads. Both are hint instructions, no-ops until `enable` sets the
+//! `SCTLR_EL1.EnIA` and friends, and until the code is mapped as Guarded
+//! Pages (`PageTableSpace::set_guarded`) respectively.
+//!
+//! Once enabled, a return address signed before fails to authenticate.
+//! Hence `enable` is inlined into its caller, and the caller must never
+//! return, as the callers up the stack have not signed their return
+//! addresses.
+
+use crate::regs::access::ReadableRegister;
+use crate::regs::SystemControlEl1;
+
+/// A 128-bit key, the `Lo` and `Hi` registers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Key {
+    pub lo: u64,
+    pub hi: u64,
+}
+
+impl Key {
+    pub fn generate(random: &mut impl FnMut() -> u64) -> Self {
+        Self {
+            lo: random(),
+            hi: random(),
+        }
+    }
+}
+
+/// The instruction, data and generic keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Keys {
+    pub ia: Key,
+    pub ib: Key,
+    pub da: Key,
+    pub db: Key,
+    pub ga: Key,
+}
+
+impl Keys {
+    /// The keys from a random source, e.g. `Rng::next_u64_reseeded`.
+    pub fn generate(mut random: impl FnMut() -> u64) -> Self {
+        Self {
+            ia: Key::generate(&mut random),
+            ib: Key::generate(&mut random),
+            da: Key::generate(&mut random),
+            db: Key::generate(&mut random),
+            ga: Key::generate(&mut random),
+        }
+    }
+
+    /// Writes the key registers, the return addresses signed with
+    /// the previous keys fail to authenticate afterwards.
+    pub fn install(&self) {
+        crate::store_sys_reg!(APIAKeyLo_EL1, "pauth", self.ia.lo);
+        crate::store_sys_reg!(APIAKeyHi_EL1, "pauth", self.ia.hi);
+        crate::store_sys_reg!(APIBKeyLo_EL1, "pauth", self.ib.lo);
+        crate::store_sys_reg!(APIBKeyHi_EL1, "pauth", self.ib.hi);
+        crate::store_sys_reg!(APDAKeyLo_EL1, "pauth", self.da.lo);
+        crate::store_sys_reg!(APDAKeyHi_EL1, "pauth", self.da.hi);
+        crate::store_sys_reg!(APDBKeyLo_EL1, "pauth", self.db.lo);
+        crate::store_sys_reg!(APDBKeyHi_EL1, "pauth", self.db.hi);
+        crate::store_sys_reg!(APGAKeyLo_EL1, "pauth", self.ga.lo);
+        crate::store_sys_reg!(APGAKeyHi_EL1, "pauth", self.ga.hi);
+    }
+}
+
+/// What `enable` turns on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protection {
+    /// The IA and IB keys, `pac-ret` uses these.
+    pub instruction_keys: bool,
+    /// The DA and DB keys
+    pub data_keys: bool,
+    /// `PACIASP` and `PACIBSP` are landing pads only for `BLR` and for
+    /// `BR` with X16 or X17, as `BTI c` is (`SCTLR_EL1.BT1`).
+    pub strict_bti: bool,
+}
+
+impl Protection {
+    pub const ALL: Self = Self {
+        instruction_keys: true,
+        data_keys: true,
+        strict_bti: true,
+    };
+}
+
+/// Enables the pointer authentication and tightens BTI at EL1, see the
+/// module docs for why the caller must not return.
+#[inline(always)]
+pub fn enable(protection: Protection) {
+    let instruction_keys = protection.instruction_keys as u64;
+    let data_keys = protection.data_keys as u64;
+    let sctlr = SystemControlEl1::read()
+        .with_en_ia(instruction_keys)
+        .with_en_ib(instruction_keys)
+        .with_en_da(data_keys)
+        .with_en_db(data_keys)
+        .with_bt1(protection.strict_bti as u64);
+
+    // No call to return from in between.
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    // SAFETY: the return addresses are signed only after this.
+    unsafe {
+        core::arch::asm!(
+            "msr sctlr_el1, {}",
+            "isb",
+            in(reg) u64::from(sctlr),
+            options(nostack, preserves_flags)
+        );
+    }
+    #[cfg(not(all(target_arch = "aarch64", not(test))))]
+    {
+        use crate::regs::access::WritableRegister;
+        sctlr.store();
+    }
+}
@@ -0,0 +1,356 @@
+//! Performance Monitors
+//!
+//! The cycle counter and the event counters of PMUv3. The event counters
+//! are numbered from 0, `Pmu::start` programs them in the order of the
+//! events. The overflow interrupt is the PPI in the `interrupts` of the
+//! `arm,armv8-pmuv3` DTB node, see `gic::dt_intid`, to be enabled in the
+//! GIC along with `Pmu::enable_overflow_interrupt`. The cycle counter is
+//! 64-bit, `Pmu::overflow_after` presets it to overflow all the same.
+
+use crate::regs::access::ReadWriteRegister;
+use crate::regs::access::ReadableRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::DbgPmuVer;
+use crate::regs::DebugFeatures0El1;
+use crate::regs::PerfMonControlEl0;
+use crate::regs::PerfMonCountEnableClearEl0;
+use crate::regs::PerfMonCountEnableSetEl0;
+use crate::regs::PerfMonCycleCountEl0;
+use crate::regs::PerfMonCycleCountFilterEl0;
+use crate::regs::PerfMonEventTypeEl0;
+use crate::regs::PerfMonInterruptEnableClearEl1;
+use crate::regs::PerfMonInterruptEnableSetEl1;
+use crate::regs::PerfMonOverflowClearEl0;
+use crate::regs::PerfMonOverflowSetEl0;
+
+/// The architecture allows 31 event counters, the implementations have
+/// fewer, see `Pmu::counters`.
+pub const MAX_COUNTERS: usize = 31;
+
+macro_rules! events {
+    ($($(#[$attr:meta])* $variant:ident = $number:literal $name:literal,)+) => {
+        /// The common events, numbered as in the Arm ARM
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u16)]
+        pub enum Event {
+            $($(#[$attr])* $variant = $number,)+
+        }
+
+        impl Event {
+            pub const ALL: &'static [Event] = &[$(Event::$variant),+];
+
+            /// The name in the Arm ARM, e.g. `L1D_TLB_REFILL`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Event::$variant => $name,)+
+                }
+            }
+        }
+    };
+}
+
+events! {
+    L1iCacheRefill = 0x01 "L1I_CACHE_REFILL",
+    L1iTlbRefill = 0x02 "L1I_TLB_REFILL",
+    L1dCacheRefill = 0x03 "L1D_CACHE_REFILL",
+    L1dCache = 0x04 "L1D_CACHE",
+    L1dTlbRefill = 0x05 "L1D_TLB_REFILL",
+    InstRetired = 0x08 "INST_RETIRED",
+    ExcTaken = 0x09 "EXC_TAKEN",
+    BrMisPred = 0x10 "BR_MIS_PRED",
+    CpuCycles = 0x11 "CPU_CYCLES",
+    L2dTlbRefill = 0x2d "L2D_TLB_REFILL",
+    /// A data access walked the translation tables.
+    DtlbWalk = 0x34 "DTLB_WALK",
+    /// An instruction fetch walked the translation tables.
+    ItlbWalk = 0x35 "ITLB_WALK",
+}
+
+impl core::fmt::Display for Event {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Selects the counter for the overflow interrupts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    Cycles,
+    Event(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PmuError {
+    /// More events than the counters.
+    TooManyEvents { events: usize, counters: usize },
+}
+
+impl core::fmt::Display for PmuError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PmuError::TooManyEvents { events, counters } => {
+                write!(f, "{events} events, only {counters} counters")
+            }
+        }
+    }
+}
+
+/// The PMEVTYPER<n>_EL0 and PMEVCNTR<n>_EL0 accessors, the register
+/// names must be known to the assembler, hence the match.
+macro_rules! indexed_access {
+    ($($index:literal => $type_reg:ident $count_reg:ident,)+) => {
+        fn load_event_type(index: usize) -> PerfMonEventTypeEl0 {
+            match index {
+                $($index => crate::load_sys_reg!($type_reg).into(),)+
+                _ => panic!("no event counter {index}"),
+            }
+        }
+
+        fn store_event_type(index: usize, event_type: PerfMonEventTypeEl0) {
+            let value: u64 = event_type.into();
+            match index {
+                $($index => crate::store_sys_reg!($type_reg, value),)+
+                _ => panic!("no event counter {index}"),
+            }
+        }
+
+        fn load_event_count(index: usize) -> u64 {
+            match index {
+                $($index => crate::load_sys_reg!($count_reg),)+
+                _ => panic!("no event counter {index}"),
+            }
+        }
+    };
+}
+
+indexed_access! {
+    0 => PMEVTYPER0_EL0 PMEVCNTR0_EL0,
+    1 => PMEVTYPER1_EL0 PMEVCNTR1_EL0,
+    2 => PMEVTYPER2_EL0 PMEVCNTR2_EL0,
+    3 => PMEVTYPER3_EL0 PMEVCNTR3_EL0,
+    4 => PMEVTYPER4_EL0 PMEVCNTR4_EL0,
+    5 => PMEVTYPER5_EL0 PMEVCNTR5_EL0,
+    6 => PMEVTYPER6_EL0 PMEVCNTR6_EL0,
+    7 => PMEVTYPER7_EL0 PMEVCNTR7_EL0,
+    8 => PMEVTYPER8_EL0 PMEVCNTR8_EL0,
+    9 => PMEVTYPER9_EL0 PMEVCNTR9_EL0,
+    10 => PMEVTYPER10_EL0 PMEVCNTR10_EL0,
+    11 => PMEVTYPER11_EL0 PMEVCNTR11_EL0,
+    12 => PMEVTYPER12_EL0 PMEVCNTR12_EL0,
+    13 => PMEVTYPER13_EL0 PMEVCNTR13_EL0,
+    14 => PMEVTYPER14_EL0 PMEVCNTR14_EL0,
+    15 => PMEVTYPER15_EL0 PMEVCNTR15_EL0,
+    16 => PMEVTYPER16_EL0 PMEVCNTR16_EL0,
+    17 => PMEVTYPER17_EL0 PMEVCNTR17_EL0,
+    18 => PMEVTYPER18_EL0 PMEVCNTR18_EL0,
+    19 => PMEVTYPER19_EL0 PMEVCNTR19_EL0,
+    20 => PMEVTYPER20_EL0 PMEVCNTR20_EL0,
+    21 => PMEVTYPER21_EL0 PMEVCNTR21_EL0,
+    22 => PMEVTYPER22_EL0 PMEVCNTR22_EL0,
+    23 => PMEVTYPER23_EL0 PMEVCNTR23_EL0,
+    24 => PMEVTYPER24_EL0 PMEVCNTR24_EL0,
+    25 => PMEVTYPER25_EL0 PMEVCNTR25_EL0,
+    26 => PMEVTYPER26_EL0 PMEVCNTR26_EL0,
+    27 => PMEVTYPER27_EL0 PMEVCNTR27_EL0,
+    28 => PMEVTYPER28_EL0 PMEVCNTR28_EL0,
+    29 => PMEVTYPER29_EL0 PMEVCNTR29_EL0,
+    30 => PMEVTYPER30_EL0 PMEVCNTR30_EL0,
+}
+
+/// The counts of the events over a run of code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub cycles: u64,
+    events: [Option<Event>; MAX_COUNTERS],
+    counts: [u64; MAX_COUNTERS],
+}
+
+impl Measurement {
+    /// `None` if the event was not counted.
+    pub fn count(&self, event: Event) -> Option<u64> {
+        self.iter()
+            .find(|&(e, _)| e == event)
+            .map(|(_, count)| count)
+    }
+
+    /// The events in the order of `Pmu::start`.
+    pub fn iter(&self) -> impl Iterator<Item = (Event, u64)> + '_ {
+        self.events
+            .iter()
+            .zip(self.counts)
+            .map_while(|(event, count)| Some(((*event)?, count)))
+    }
+}
+
+impl core::fmt::Display for Measurement {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "cycles: {}", self.cycles)?;
+        for (event, count) in self.iter() {
+            write!(f, ", {event}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The PMUv3 of the current CPU
+#[derive(Debug)]
+pub struct Pmu {
+    counters: usize,
+    /// The event counters are 64-bit with FEAT_PMUv3p5, 32-bit otherwise.
+    long_events: bool,
+}
+
+impl Pmu {
+    /// Enables the counters at EL1 and EL0 and resets them, `None`
+    /// without PMUv3.
+    pub fn new() -> Option<Self> {
+        let pmu_ver = DebugFeatures0El1::read().pmu_ver();
+        if matches!(pmu_ver, DbgPmuVer::No | DbgPmuVer::ImpDef) {
+            return None;
+        }
+        // LP is RES0 before FEAT_PMUv3p5.
+        let long_events = u64::from(pmu_ver) >= u64::from(DbgPmuVer::PmuV3p5);
+
+        let pmcr = PerfMonControlEl0::modify(|pmcr| {
+            pmcr.with_e(true)
+                .with_p(true)
+                .with_c(true)
+                .with_lc(true)
+                .with_lp(long_events)
+        });
+        Some(Self {
+            counters: (pmcr.n() as usize).min(MAX_COUNTERS),
+            long_events,
+        })
+    }
+
+    /// The number of the event counters
+    pub fn counters(&self) -> usize {
+        self.counters
+    }
+
+    /// Tells if the implementation counts the event, from PMCEID0_EL0
+    /// and PMCEID1_EL0.
+    pub fn supports(&self, event: Event) -> bool {
+        let number = event as u64;
+        let (ceid, bit) = if number < 32 {
+            (crate::load_sys_reg!(PMCEID0_EL0), number)
+        } else {
+            (crate::load_sys_reg!(PMCEID1_EL0), number - 32)
+        };
+        ceid & (1 << bit) != 0
+    }
+
+    fn counter_mask(events: usize) -> u64 {
+        (1 << events) - 1
+    }
+
+    /// Programs the event counters from 0 on with the events, and enables
+    /// them along with the cycle counter counting at EL1 and EL0. The
+    /// other counters are stopped.
+    pub fn start(&self, events: &[Event]) -> Result<(), PmuError> {
+        if events.len() > self.counters {
+            return Err(PmuError::TooManyEvents {
+                events: events.len(),
+                counters: self.counters,
+            });
+        }
+
+        PerfMonCountEnableClearEl0::new()
+            .with_p(Self::counter_mask(self.counters))
+            .with_c(true)
+            .store();
+        PerfMonCycleCountFilterEl0::new().store();
+        for (index, &event) in events.iter().enumerate() {
+            store_event_type(
+                index,
+                PerfMonEventTypeEl0::new().with_evtcount(event as u64),
+            );
+        }
+        PerfMonCountEnableSetEl0::new()
+            .with_p(Self::counter_mask(events.len()))
+            .with_c(true)
+            .store();
+        Ok(())
+    }
+
+    /// The event the counter is programmed with, if it is one of `Event`.
+    pub fn event(&self, index: usize) -> Option<Event> {
+        let number = load_event_type(index).evtcount();
+        Event::ALL.iter().copied().find(|&e| e as u64 == number)
+    }
+
+    /// The counts of the cycles and the events now.
+    fn sample(&self, events: usize) -> Measurement {
+        let mut sample = Measurement {
+            cycles: PerfMonCycleCountEl0::read().ccnt(),
+            events: [None; MAX_COUNTERS],
+            counts: [0; MAX_COUNTERS],
+        };
+        for index in 0..events {
+            sample.events[index] = self.event(index);
+            sample.counts[index] = load_event_count(index);
+        }
+        sample
+    }
+
+    /// Counts the cycles and the events while `f` runs. The counts are
+    /// the differences, so the counters are not reset.
+    pub fn measure<R>(
+        &self,
+        events: &[Event],
+        f: impl FnOnce() -> R,
+    ) -> Result<(R, Measurement), PmuError> {
+        self.start(events)?;
+        let before = self.sample(events.len());
+        let result = f();
+        let after = self.sample(events.len());
+
+        let event_mask = if self.long_events {
+            u64::MAX
+        } else {
+            u32::MAX as u64
+        };
+        let mut measurement = after;
+        measurement.cycles = after.cycles.wrapping_sub(before.cycles);
+        for (count, before) in measurement.counts.iter_mut().zip(before.counts) {
+            *count = count.wrapping_sub(before) & event_mask;
+        }
+        Ok((result, measurement))
+    }
+
+    /// Enables or disables the overflow interrupt of the counter.
+    pub fn enable_overflow_interrupt(&self, counter: Counter, enable: bool) {
+        let (p, c) = match counter {
+            Counter::Cycles => (0, true),
+            Counter::Event(index) => (1 << index, false),
+        };
+        if enable {
+            PerfMonInterruptEnableSetEl1::new()
+                .with_p(p)
+                .with_c(c)
+                .store();
+        } else {
+            PerfMonInterruptEnableClearEl1::new()
+                .with_p(p)
+                .with_c(c)
+                .store();
+        }
+    }
+
+    /// Presets the cycle counter to overflow after the cycles, raising
+    /// the interrupt if enabled. `measure` counts on regardless.
+    pub fn overflow_after(&self, cycles: u64) {
+        PerfMonCycleCountEl0::new()
+            .with_ccnt(0u64.wrapping_sub(cycles))
+            .store();
+    }
+
+    /// Returns the overflow flags and clears them, for the interrupt
+    /// handler.
+    pub fn take_overflows() -> PerfMonOverflowSetEl0 {
+        let overflows = PerfMonOverflowSetEl0::read();
+        PerfMonOverflowClearEl0::from(u64::from(overflows)).store();
+        overflows
+    }
+}
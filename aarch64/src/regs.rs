@@ -2,6 +2,13 @@
 
 use bitfield_struct::bitfield;
 
+// The field names follow the specification, `LEN` included.
+#[rustfmt::skip]
+#[allow(clippy::len_without_is_empty)]
+mod generated;
+
+pub use generated::*;
+
 #[derive(Debug)]
 #[repr(u64)]
 pub enum El {
@@ -191,43 +198,79 @@ pub struct ExceptionLinkEl1 {
     pub bits: u64,
 }
 
+/// The faulting virtual address of the synchronous aborts, the PC and the
+/// watchpoint exceptions
+#[bitfield(u64)]
+pub struct FaultAddressEl1 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+#[bitfield(u64)]
+pub struct AuxFaultStatus0El1 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+#[bitfield(u64)]
+pub struct AuxFaultStatus1El1 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-#[repr(u8)]
 pub enum ExceptionClass {
-    Unknown = 0b000000,
-    Wf = 0b000001,
-    McrMrc = 0b000011,
-    McrrMrrc = 0b000100,
-    MccMrc1110 = 0b000101,
-    LdcStc = 0b000110,
-    SveAsmindFp = 0b000111,
-    Ld64St64 = 0b001010,
-    Mrrc1110 = 0b001100,
-    BranchTarget = 0b001101,
-    IllegalExecutionState = 0b001110,
-    Svc32bit = 0b010001,
-    Svc64bit = 0b010101,
-    MsrMrs64bit = 0b011000,
-    Sve = 0b011001,
-    PointerAuth = 0b011100,
-    InstructionAbortLowerEl = 0b100000,
-    InstructionAbortSameEl = 0b100001,
-    PcAlignment = 0b100010,
-    DataAbortLowerEl = 0b100100,
-    DataAbortSameEl = 0b100101,
-    SpAlignment = 0b100110,
-    Fp32bit = 0b101000,
-    Fp64bit = 0b101100,
-    SError = 0b101111,
-    BreakpointLowerEl = 0b110000,
-    BreakpointSameEl = 0b110001,
-    StepLowerEl = 0b110010,
-    StepSameEl = 0b110011,
-    WatchpointLowerEl = 0b110100,
-    WatchpointSameEl = 0b110101,
-    Bkpt32bit = 0b111000,
-    Brk64bit = 0b111100,
-    Invalid = 0b110110,
+    Unknown,
+    Wf,
+    McrMrc,
+    McrrMrrc,
+    MccMrc1110,
+    LdcStc,
+    SveAsmindFp,
+    /// PAC instructions disabled by `HCR_EL2.API` or `SCR_EL3.API`
+    PointerAuthTrap,
+    Ld64St64,
+    Mrrc1110,
+    BranchTarget,
+    IllegalExecutionState,
+    Svc32bit,
+    Hvc32bit,
+    Smc32bit,
+    /// MRRS, MSRR and SYSP
+    MsrrMrrs128bit,
+    Svc64bit,
+    Hvc64bit,
+    Smc64bit,
+    MsrMrs64bit,
+    Sve,
+    Eret,
+    TStart,
+    /// Pointer authentication failure, with FEAT_FPAC
+    PointerAuth,
+    Sme,
+    InstructionAbortLowerEl,
+    InstructionAbortSameEl,
+    PcAlignment,
+    DataAbortLowerEl,
+    DataAbortSameEl,
+    SpAlignment,
+    MemoryOperation,
+    Fp32bit,
+    Fp64bit,
+    GuardedControlStack,
+    SError,
+    BreakpointLowerEl,
+    BreakpointSameEl,
+    StepLowerEl,
+    StepSameEl,
+    WatchpointLowerEl,
+    WatchpointSameEl,
+    Bkpt32bit,
+    Brk64bit,
+    Profiling,
+    Invalid,
+    /// Not known to this crate
+    Other(u8),
 }
 
 impl From<ExceptionClass> for u64 {
@@ -240,23 +283,34 @@ impl From<ExceptionClass> for u64 {
             ExceptionClass::MccMrc1110 => 0b000101,
             ExceptionClass::LdcStc => 0b000110,
             ExceptionClass::SveAsmindFp => 0b000111,
+            ExceptionClass::PointerAuthTrap => 0b001001,
             ExceptionClass::Ld64St64 => 0b001010,
             ExceptionClass::Mrrc1110 => 0b001100,
             ExceptionClass::BranchTarget => 0b001101,
             ExceptionClass::IllegalExecutionState => 0b001110,
             ExceptionClass::Svc32bit => 0b010001,
+            ExceptionClass::Hvc32bit => 0b010010,
+            ExceptionClass::Smc32bit => 0b010011,
+            ExceptionClass::MsrrMrrs128bit => 0b010100,
             ExceptionClass::Svc64bit => 0b010101,
+            ExceptionClass::Hvc64bit => 0b010110,
+            ExceptionClass::Smc64bit => 0b010111,
             ExceptionClass::MsrMrs64bit => 0b011000,
             ExceptionClass::Sve => 0b011001,
+            ExceptionClass::Eret => 0b011010,
+            ExceptionClass::TStart => 0b011011,
             ExceptionClass::PointerAuth => 0b011100,
+            ExceptionClass::Sme => 0b011101,
             ExceptionClass::InstructionAbortLowerEl => 0b100000,
             ExceptionClass::InstructionAbortSameEl => 0b100001,
             ExceptionClass::PcAlignment => 0b100010,
             ExceptionClass::DataAbortLowerEl => 0b100100,
             ExceptionClass::DataAbortSameEl => 0b100101,
             ExceptionClass::SpAlignment => 0b100110,
+            ExceptionClass::MemoryOperation => 0b100111,
             ExceptionClass::Fp32bit => 0b101000,
             ExceptionClass::Fp64bit => 0b101100,
+            ExceptionClass::GuardedControlStack => 0b101101,
             ExceptionClass::SError => 0b101111,
             ExceptionClass::BreakpointLowerEl => 0b110000,
             ExceptionClass::BreakpointSameEl => 0b110001,
@@ -266,7 +320,9 @@ impl From<ExceptionClass> for u64 {
             ExceptionClass::WatchpointSameEl => 0b110101,
             ExceptionClass::Bkpt32bit => 0b111000,
             ExceptionClass::Brk64bit => 0b111100,
+            ExceptionClass::Profiling => 0b111101,
             ExceptionClass::Invalid => 0b110110,
+            ExceptionClass::Other(ec) => ec as u64,
         }
     }
 }
@@ -281,23 +337,34 @@ impl From<u64> for ExceptionClass {
             0b000101 => ExceptionClass::MccMrc1110,
             0b000110 => ExceptionClass::LdcStc,
             0b000111 => ExceptionClass::SveAsmindFp,
+            0b001001 => ExceptionClass::PointerAuthTrap,
             0b001010 => ExceptionClass::Ld64St64,
             0b001100 => ExceptionClass::Mrrc1110,
             0b001101 => ExceptionClass::BranchTarget,
             0b001110 => ExceptionClass::IllegalExecutionState,
             0b010001 => ExceptionClass::Svc32bit,
+            0b010010 => ExceptionClass::Hvc32bit,
+            0b010011 => ExceptionClass::Smc32bit,
+            0b010100 => ExceptionClass::MsrrMrrs128bit,
             0b010101 => ExceptionClass::Svc64bit,
+            0b010110 => ExceptionClass::Hvc64bit,
+            0b010111 => ExceptionClass::Smc64bit,
             0b011000 => ExceptionClass::MsrMrs64bit,
             0b011001 => ExceptionClass::Sve,
+            0b011010 => ExceptionClass::Eret,
+            0b011011 => ExceptionClass::TStart,
             0b011100 => ExceptionClass::PointerAuth,
+            0b011101 => ExceptionClass::Sme,
             0b100000 => ExceptionClass::InstructionAbortLowerEl,
             0b100001 => ExceptionClass::InstructionAbortSameEl,
             0b100010 => ExceptionClass::PcAlignment,
             0b100100 => ExceptionClass::DataAbortLowerEl,
             0b100101 => ExceptionClass::DataAbortSameEl,
             0b100110 => ExceptionClass::SpAlignment,
+            0b100111 => ExceptionClass::MemoryOperation,
             0b101000 => ExceptionClass::Fp32bit,
             0b101100 => ExceptionClass::Fp64bit,
+            0b101101 => ExceptionClass::GuardedControlStack,
             0b101111 => ExceptionClass::SError,
             0b110000 => ExceptionClass::BreakpointLowerEl,
             0b110001 => ExceptionClass::BreakpointSameEl,
@@ -307,8 +374,9 @@ impl From<u64> for ExceptionClass {
             0b110101 => ExceptionClass::WatchpointSameEl,
             0b111000 => ExceptionClass::Bkpt32bit,
             0b111100 => ExceptionClass::Brk64bit,
+            0b111101 => ExceptionClass::Profiling,
             0b110110 => ExceptionClass::Invalid,
-            _ => panic!("invalid EC {value:#b}"),
+            _ => ExceptionClass::Other(value as u8),
         }
     }
 }
@@ -321,10 +389,278 @@ pub struct ExceptionSyndromeEl1 {
     pub il: u64,
     #[bits(6)]
     pub ec: ExceptionClass,
-    #[bits(32)]
+    #[bits(24)]
+    pub iss2: u64,
+    #[bits(8)]
     pub _mbz: u64,
 }
 
+/// The Data and Instruction Fault Status Codes. The level is that of
+/// the translation table walk, and is -1 for the 52-bit addresses
+/// with the 4KB granule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FaultStatusCode {
+    AddressSize { level: i8 },
+    Translation { level: i8 },
+    AccessFlag { level: i8 },
+    Permission { level: i8 },
+    SyncExternal,
+    SyncTagCheck,
+    SyncExternalOnWalk { level: i8 },
+    SyncParity,
+    SyncParityOnWalk { level: i8 },
+    Alignment,
+    GranuleProtection,
+    GranuleProtectionOnWalk { level: i8 },
+    TlbConflict,
+    UnsupportedAtomicUpdate,
+    Lockdown,
+    UnsupportedExclusiveOrAtomic,
+    Other(u8),
+}
+
+impl From<u64> for FaultStatusCode {
+    fn from(value: u64) -> Self {
+        let level = (value & 0b11) as i8;
+        match value {
+            0b000000..=0b000011 => FaultStatusCode::AddressSize { level },
+            0b101001 => FaultStatusCode::AddressSize { level: -1 },
+            0b000100..=0b000111 => FaultStatusCode::Translation { level },
+            0b101011 => FaultStatusCode::Translation { level: -1 },
+            0b001000..=0b001011 => FaultStatusCode::AccessFlag { level },
+            0b001100..=0b001111 => FaultStatusCode::Permission { level },
+            0b010000 => FaultStatusCode::SyncExternal,
+            0b010001 => FaultStatusCode::SyncTagCheck,
+            0b010100..=0b010111 => FaultStatusCode::SyncExternalOnWalk { level },
+            0b010011 => FaultStatusCode::SyncExternalOnWalk { level: -1 },
+            0b011000 => FaultStatusCode::SyncParity,
+            0b011100..=0b011111 => FaultStatusCode::SyncParityOnWalk { level },
+            0b011011 => FaultStatusCode::SyncParityOnWalk { level: -1 },
+            0b100001 => FaultStatusCode::Alignment,
+            0b101000 => FaultStatusCode::GranuleProtection,
+            0b100100..=0b100111 => FaultStatusCode::GranuleProtectionOnWalk { level },
+            0b100011 => FaultStatusCode::GranuleProtectionOnWalk { level: -1 },
+            0b110000 => FaultStatusCode::TlbConflict,
+            0b110001 => FaultStatusCode::UnsupportedAtomicUpdate,
+            0b110100 => FaultStatusCode::Lockdown,
+            0b110101 => FaultStatusCode::UnsupportedExclusiveOrAtomic,
+            _ => FaultStatusCode::Other(value as u8),
+        }
+    }
+}
+
+/// The ISS of a Data Abort
+#[bitfield(u64)]
+#[derive(PartialEq, Eq)]
+pub struct DataAbortIss {
+    #[bits(6)]
+    pub dfsc: u64,
+    /// Caused by writing to memory rather than reading
+    pub wnr: bool,
+    /// Caused by the stage 2 fault on the stage 1 translation table walk
+    pub s1ptw: bool,
+    /// Caused by a cache maintenance instruction
+    pub cm: bool,
+    pub ea: bool,
+    /// FAR_EL1 is not valid
+    pub fnv: bool,
+    #[bits(2)]
+    pub set: u64,
+    pub vncr: bool,
+    /// Acquire or Release semantics, valid when `isv` is set
+    pub ar: bool,
+    /// 64-bit register transfer, valid when `isv` is set
+    pub sf: bool,
+    /// The register number, valid when `isv` is set
+    #[bits(5)]
+    pub srt: u64,
+    /// Sign-extended, valid when `isv` is set
+    pub sse: bool,
+    /// The access size is `1 << sas` bytes, valid when `isv` is set
+    #[bits(2)]
+    pub sas: u64,
+    /// The bits [23:14] hold a valid instruction syndrome
+    pub isv: bool,
+    #[bits(39)]
+    _mbz: u64,
+}
+
+impl DataAbortIss {
+    pub fn fault(&self) -> FaultStatusCode {
+        self.dfsc().into()
+    }
+}
+
+/// The ISS of an Instruction Abort
+#[bitfield(u64)]
+#[derive(PartialEq, Eq)]
+pub struct InstructionAbortIss {
+    #[bits(6)]
+    pub ifsc: u64,
+    _mbz0: bool,
+    pub s1ptw: bool,
+    _mbz1: bool,
+    pub ea: bool,
+    /// FAR_EL1 is not valid
+    pub fnv: bool,
+    #[bits(2)]
+    pub set: u64,
+    #[bits(51)]
+    _mbz2: u64,
+}
+
+impl InstructionAbortIss {
+    pub fn fault(&self) -> FaultStatusCode {
+        self.ifsc().into()
+    }
+}
+
+/// The ISS of a trapped MSR, MRS or System instruction
+#[bitfield(u64)]
+#[derive(PartialEq, Eq)]
+pub struct SysRegTrapIss {
+    /// Set for MRS, clear for MSR
+    pub read: bool,
+    #[bits(4)]
+    pub crm: u64,
+    #[bits(5)]
+    pub rt: u64,
+    #[bits(4)]
+    pub crn: u64,
+    #[bits(3)]
+    pub op1: u64,
+    #[bits(3)]
+    pub op2: u64,
+    #[bits(2)]
+    pub op0: u64,
+    #[bits(42)]
+    _mbz: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SErrorType {
+    Uncontainable,
+    UnrecoverableState,
+    RestartableState,
+    RecoverableState,
+    Corrected,
+    Other(u8),
+}
+
+impl From<u64> for SErrorType {
+    fn from(value: u64) -> Self {
+        match value {
+            0b000 => SErrorType::Uncontainable,
+            0b001 => SErrorType::UnrecoverableState,
+            0b010 => SErrorType::RestartableState,
+            0b011 => SErrorType::RecoverableState,
+            0b110 => SErrorType::Corrected,
+            _ => SErrorType::Other(value as u8),
+        }
+    }
+}
+
+impl From<SErrorType> for u64 {
+    fn from(value: SErrorType) -> Self {
+        match value {
+            SErrorType::Uncontainable => 0b000,
+            SErrorType::UnrecoverableState => 0b001,
+            SErrorType::RestartableState => 0b010,
+            SErrorType::RecoverableState => 0b011,
+            SErrorType::Corrected => 0b110,
+            SErrorType::Other(aet) => aet as u64,
+        }
+    }
+}
+
+/// The ISS of an SError interrupt
+#[bitfield(u64)]
+#[derive(PartialEq, Eq)]
+pub struct SErrorIss {
+    #[bits(6)]
+    pub dfsc: u64,
+    #[bits(3)]
+    _mbz0: u64,
+    pub ea: bool,
+    /// The error type, valid when `dfsc` is `0b010001`
+    #[bits(3)]
+    pub aet: SErrorType,
+    /// Synchronized by the implicit error synchronization barrier
+    pub iesb: bool,
+    #[bits(10)]
+    _mbz1: u64,
+    /// The bits [23:0] hold an IMPLEMENTATION DEFINED syndrome
+    pub ids: bool,
+    #[bits(39)]
+    _mbz2: u64,
+}
+
+/// The key of a failed pointer authentication
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerAuthKey {
+    Ia,
+    Ib,
+    Da,
+    Db,
+}
+
+/// The syndrome decoded according to the exception class
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Syndrome {
+    DataAbort(DataAbortIss),
+    InstructionAbort(InstructionAbortIss),
+    /// The immediate of the SVC instruction
+    Svc(u16),
+    Hvc(u16),
+    Smc(u16),
+    SysRegTrap(SysRegTrapIss),
+    /// The comment of the BRK instruction
+    Brk(u16),
+    /// An AUT* instruction failed with FEAT_FPAC
+    PointerAuth(PointerAuthKey),
+    /// The PSTATE.BTYPE of a branch to an instruction that is not
+    /// a landing pad on a guarded page
+    BranchTarget(u8),
+    SError(SErrorIss),
+    /// The raw ISS of the other classes
+    Other(u64),
+}
+
+impl ExceptionSyndromeEl1 {
+    pub fn decode(&self) -> Syndrome {
+        let iss = self.iss();
+        let imm16 = iss as u16;
+        match self.ec() {
+            ExceptionClass::DataAbortLowerEl | ExceptionClass::DataAbortSameEl => {
+                Syndrome::DataAbort(iss.into())
+            }
+            ExceptionClass::InstructionAbortLowerEl | ExceptionClass::InstructionAbortSameEl => {
+                Syndrome::InstructionAbort(iss.into())
+            }
+            ExceptionClass::Svc32bit | ExceptionClass::Svc64bit => Syndrome::Svc(imm16),
+            ExceptionClass::Hvc32bit | ExceptionClass::Hvc64bit => Syndrome::Hvc(imm16),
+            ExceptionClass::Smc32bit | ExceptionClass::Smc64bit => Syndrome::Smc(imm16),
+            ExceptionClass::MsrMrs64bit => Syndrome::SysRegTrap(iss.into()),
+            ExceptionClass::Brk64bit => Syndrome::Brk(imm16),
+            ExceptionClass::PointerAuth => Syndrome::PointerAuth(match iss & 0b11 {
+                0b00 => PointerAuthKey::Ia,
+                0b01 => PointerAuthKey::Ib,
+                0b10 => PointerAuthKey::Da,
+                _ => PointerAuthKey::Db,
+            }),
+            ExceptionClass::BranchTarget => Syndrome::BranchTarget((iss & 0b11) as u8),
+            ExceptionClass::SError => Syndrome::SError(iss.into()),
+            _ => Syndrome::Other(iss),
+        }
+    }
+
+    /// A data abort caused by the Allocation Tag not matching
+    /// the Logical Address Tag of the access.
+    pub fn is_tag_check_fault(&self) -> bool {
+        matches!(self.decode(), Syndrome::DataAbort(iss) if iss.fault() == FaultStatusCode::SyncTagCheck)
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ExceptionKind {
@@ -433,10 +769,124 @@ pub struct SavedProgramStateEl1 {
     pub i: bool,
     pub a: bool,
     pub d: bool,
-    #[bits(54)]
+    /// The PSTATE.BTYPE of the interrupted branch
+    #[bits(2)]
+    pub btype: u64,
+    #[bits(8)]
+    _mbz1: u64,
+    /// Illegal Execution state
+    pub il: bool,
+    /// Software Step, the step is active after the exception return.
+    pub ss: bool,
+    #[bits(42)]
     _rest: u64,
 }
 
+/// The PSTATE interrupt masks
+#[bitfield(u64)]
+pub struct InterruptMask {
+    #[bits(6)]
+    _mbz0: u64,
+    pub f: bool,
+    pub i: bool,
+    pub a: bool,
+    pub d: bool,
+    #[bits(54)]
+    _mbz1: u64,
+}
+
+/// The PSTATE condition flags
+#[bitfield(u64)]
+pub struct ConditionFlags {
+    #[bits(28)]
+    _mbz0: u64,
+    pub v: bool,
+    pub c: bool,
+    pub z: bool,
+    pub n: bool,
+    #[bits(32)]
+    _mbz1: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u64)]
+pub enum CpacrTrap {
+    TrapAll = 0b00,
+    TrapEl0 = 0b01,
+    TrapAllToo = 0b10,
+    NoTrap = 0b11,
+}
+
+impl From<u64> for CpacrTrap {
+    fn from(value: u64) -> Self {
+        match value & 0b11 {
+            0b00 => CpacrTrap::TrapAll,
+            0b01 => CpacrTrap::TrapEl0,
+            0b10 => CpacrTrap::TrapAllToo,
+            _ => CpacrTrap::NoTrap,
+        }
+    }
+}
+
+impl From<CpacrTrap> for u64 {
+    fn from(value: CpacrTrap) -> Self {
+        value as u64
+    }
+}
+
+/// Traps of the SVE, SME, FP and SIMD instructions and of the trace
+/// registers accesses
+#[bitfield(u64)]
+pub struct ArchFeatureAccessControlEl1 {
+    #[bits(16)]
+    _mbz0: u64,
+    #[bits(2)]
+    pub zen: CpacrTrap,
+    #[bits(2)]
+    _mbz1: u64,
+    #[bits(2)]
+    pub fpen: CpacrTrap,
+    #[bits(2)]
+    _mbz2: u64,
+    #[bits(2)]
+    pub smen: CpacrTrap,
+    #[bits(2)]
+    _mbz3: u64,
+    pub tta: bool,
+    pub e0poe: bool,
+    pub tam: bool,
+    pub tcpac: bool,
+    #[bits(32)]
+    _mbz4: u64,
+}
+
+#[bitfield(u64)]
+pub struct StackPointerEl0 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+/// The thread pointer of EL0, readable and writable at EL0
+#[bitfield(u64)]
+pub struct ThreadIdEl0 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+#[bitfield(u64)]
+pub struct ThreadIdEl1 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+#[bitfield(u64)]
+pub struct ContextIdEl1 {
+    #[bits(32)]
+    pub procid: u64,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
 #[bitfield(u64)]
 pub struct MainIdEl1 {
     #[bits(4)]
@@ -453,6 +903,34 @@ pub struct MainIdEl1 {
     _mbz0: u64,
 }
 
+#[bitfield(u64)]
+pub struct RevisionIdEl1 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+#[bitfield(u64)]
+pub struct MultiprocessorAffinityEl1 {
+    #[bits(8)]
+    pub aff0: u64,
+    #[bits(8)]
+    pub aff1: u64,
+    #[bits(8)]
+    pub aff2: u64,
+    /// The lowest affinity level is the threads of a multithreaded core
+    pub mt: bool,
+    #[bits(5)]
+    _mbz0: u64,
+    /// A uniprocessor system
+    pub u: bool,
+    #[bits(1)]
+    _mbo0: u64,
+    #[bits(8)]
+    pub aff3: u64,
+    #[bits(24)]
+    _mbz1: u64,
+}
+
 #[bitfield(u64)]
 pub struct ProcessorFeatures0El1 {
     #[bits(4)]
@@ -525,40 +1003,298 @@ pub struct ProcessorFeatures1El1 {
     pub pfar: u64,
 }
 
+/// The ordering of the accesses to Device memory: Gathering,
+/// Reordering and Early write acknowledgement, each either allowed
+/// or not (`n`).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
+pub enum DeviceMemory {
+    nGnRnE = 0b00,
+    nGnRE = 0b01,
+    nGRE = 0b10,
+    GRE = 0b11,
+}
+
+/// The allocation hints of a cacheable Normal memory. A transient hint
+/// without any allocation has no encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub read_allocate: bool,
+    pub write_allocate: bool,
+    /// The data is not expected to be reused.
+    pub transient: bool,
+}
+
+impl CachePolicy {
+    pub const READ_WRITE_ALLOCATE: Self = Self {
+        read_allocate: true,
+        write_allocate: true,
+        transient: false,
+    };
+    pub const READ_ALLOCATE: Self = Self {
+        read_allocate: true,
+        write_allocate: false,
+        transient: false,
+    };
+    pub const NO_ALLOCATE: Self = Self {
+        read_allocate: false,
+        write_allocate: false,
+        transient: false,
+    };
+
+    const fn bits(self) -> u8 {
+        (self.read_allocate as u8) << 1 | self.write_allocate as u8
+    }
+
+    const fn from_bits(bits: u8) -> Self {
+        Self {
+            read_allocate: bits & 0b10 != 0,
+            write_allocate: bits & 0b01 != 0,
+            transient: bits & 0b1000 == 0,
+        }
+    }
+}
+
+/// The cacheability of Normal memory in the inner or the outer
+/// shareability domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cacheability {
+    NonCacheable,
+    WriteThrough(CachePolicy),
+    WriteBack(CachePolicy),
+}
+
+impl Cacheability {
+    const fn encode(self) -> Option<u8> {
+        let (write_back, policy) = match self {
+            Cacheability::NonCacheable => return Some(0b0100),
+            Cacheability::WriteThrough(policy) => (0, policy),
+            Cacheability::WriteBack(policy) => (1, policy),
+        };
+        if policy.transient && policy.bits() == 0 {
+            return None;
+        }
+        Some((!policy.transient as u8) << 3 | write_back << 2 | policy.bits())
+    }
+
+    const fn decode(bits: u8) -> Option<Self> {
+        match bits {
+            0b0000 => None,
+            0b0100 => Some(Cacheability::NonCacheable),
+            _ if bits & 0b0100 == 0 => {
+                Some(Cacheability::WriteThrough(CachePolicy::from_bits(bits)))
+            }
+            _ => Some(Cacheability::WriteBack(CachePolicy::from_bits(bits))),
+        }
+    }
+}
+
+/// A memory attribute byte of the MAIR registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MemoryAttributeEl1 {
-    Device_nGnRnE = 0,
-    Normal_NonCacheable = 0x44,
-    Normal_WriteThrough = 0xbb,
-    Normal_WriteBack = 0xff,
+    /// The XS attribute is 1 for Device memory unless cleared, which
+    /// requires FEAT_XS.
+    Device { memory: DeviceMemory, xs: bool },
+    /// The XS attribute is 0 for the Write-Back memory, and 1 otherwise.
+    Normal {
+        inner: Cacheability,
+        outer: Cacheability,
+    },
+    /// Inner and Outer Non-cacheable with the XS attribute 0, requires
+    /// FEAT_XS
+    NormalNonCacheableNoXs,
+    /// Inner and Outer Write-Through Read-Allocate No-Write-Allocate
+    /// with the XS attribute 0, requires FEAT_XS
+    NormalWriteThroughNoXs,
+    /// Write-back Normal memory with Allocation Tags, requires FEAT_MTE2
+    NormalTagged,
+}
+
+impl MemoryAttributeEl1 {
+    pub const DEVICE_NGNRNE: Self = Self::Device {
+        memory: DeviceMemory::nGnRnE,
+        xs: true,
+    };
+    pub const DEVICE_NGNRE: Self = Self::Device {
+        memory: DeviceMemory::nGnRE,
+        xs: true,
+    };
+    pub const NORMAL_NON_CACHEABLE: Self = Self::Normal {
+        inner: Cacheability::NonCacheable,
+        outer: Cacheability::NonCacheable,
+    };
+    pub const NORMAL_WRITE_THROUGH: Self = Self::Normal {
+        inner: Cacheability::WriteThrough(CachePolicy::READ_WRITE_ALLOCATE),
+        outer: Cacheability::WriteThrough(CachePolicy::READ_WRITE_ALLOCATE),
+    };
+    pub const NORMAL_WRITE_BACK: Self = Self::Normal {
+        inner: Cacheability::WriteBack(CachePolicy::READ_WRITE_ALLOCATE),
+        outer: Cacheability::WriteBack(CachePolicy::READ_WRITE_ALLOCATE),
+    };
+
+    /// `None` if a cache policy is transient without allocation.
+    pub const fn encode(self) -> Option<u8> {
+        match self {
+            MemoryAttributeEl1::Device { memory, xs } => Some((memory as u8) << 2 | !xs as u8),
+            MemoryAttributeEl1::Normal { inner, outer } => match (inner.encode(), outer.encode()) {
+                (Some(inner), Some(outer)) => Some(outer << 4 | inner),
+                _ => None,
+            },
+            MemoryAttributeEl1::NormalNonCacheableNoXs => Some(0x40),
+            MemoryAttributeEl1::NormalWriteThroughNoXs => Some(0xa0),
+            MemoryAttributeEl1::NormalTagged => Some(0xf0),
+        }
+    }
+
+    /// `None` for the encodings that are reserved or UNPREDICTABLE.
+    pub const fn decode(bits: u8) -> Option<Self> {
+        let (outer, inner) = (bits >> 4, bits & 0xf);
+        if outer == 0 {
+            if inner & 0b10 != 0 {
+                return None;
+            }
+            let memory = match inner >> 2 {
+                0b00 => DeviceMemory::nGnRnE,
+                0b01 => DeviceMemory::nGnRE,
+                0b10 => DeviceMemory::nGRE,
+                _ => DeviceMemory::GRE,
+            };
+            return Some(MemoryAttributeEl1::Device {
+                memory,
+                xs: inner & 1 == 0,
+            });
+        }
+        match (
+            bits,
+            Cacheability::decode(inner),
+            Cacheability::decode(outer),
+        ) {
+            (0x40, _, _) => Some(MemoryAttributeEl1::NormalNonCacheableNoXs),
+            (0xa0, _, _) => Some(MemoryAttributeEl1::NormalWriteThroughNoXs),
+            (0xf0, _, _) => Some(MemoryAttributeEl1::NormalTagged),
+            (_, Some(inner), Some(outer)) => Some(MemoryAttributeEl1::Normal { inner, outer }),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct MemoryAttributeIndirectionEl1([u8; 8]);
+/// A MAIR slot that holds a programmed attribute, see `MairBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MairIndex(u8);
+
+impl MairIndex {
+    /// The slots of MAIR_EL1
+    const COUNT: usize = 8;
+
+    /// `None` past the slots, the only way to make an index.
+    fn new(index: usize) -> Option<Self> {
+        (index < Self::COUNT).then_some(Self(index as u8))
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MairError {
+    /// All eight slots hold other attributes.
+    Full,
+    Unencodable(MemoryAttributeEl1),
+}
+
+impl core::fmt::Display for MairError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MairError::Full => write!(f, "no free MAIR slot"),
+            MairError::Unencodable(attr) => write!(f, "{attr:?} has no encoding"),
+        }
+    }
+}
+
+/// Assigns the MAIR slots in order, the indices it returns are the only
+/// way to refer to the attributes in the page tables.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MairBuilder {
+    attrs: [u8; MairIndex::COUNT],
+    count: usize,
+}
+
+impl MairBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The slot already holding the attribute is reused.
+    pub fn add(&mut self, attr: MemoryAttributeEl1) -> Result<MairIndex, MairError> {
+        let bits = attr.encode().ok_or(MairError::Unencodable(attr))?;
+        let index = match self.attrs[..self.count].iter().position(|&a| a == bits) {
+            Some(index) => index,
+            None if self.count == self.attrs.len() => return Err(MairError::Full),
+            None => {
+                self.attrs[self.count] = bits;
+                self.count += 1;
+                self.count - 1
+            }
+        };
+        MairIndex::new(index).ok_or(MairError::Full)
+    }
+
+    /// The slots left unassigned are Device-nGnRnE.
+    pub fn build(&self) -> MemoryAttributeIndirectionEl1 {
+        MemoryAttributeIndirectionEl1(self.attrs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryAttributeIndirectionEl1([u8; MairIndex::COUNT]);
 
 impl MemoryAttributeIndirectionEl1 {
     pub fn new() -> Self {
         Self([0; 8])
     }
 
-    pub fn get_index(&self, a: MemoryAttributeEl1) -> Option<usize> {
-        self.0.iter().position(|&x| x == a as u8)
+    /// Finds the attribute in a value read back from the register.
+    pub fn get_index(&self, a: MemoryAttributeEl1) -> Option<MairIndex> {
+        let bits = a.encode()?;
+        MairIndex::new(self.0.iter().position(|&x| x == bits)?)
     }
-}
 
-impl Default for MemoryAttributeIndirectionEl1 {
-    fn default() -> Self {
-        Self([
-            MemoryAttributeEl1::Device_nGnRnE as u8,
-            MemoryAttributeEl1::Normal_NonCacheable as u8,
-            MemoryAttributeEl1::Normal_WriteBack as u8,
-            MemoryAttributeEl1::Normal_WriteThrough as u8,
-            MemoryAttributeEl1::Device_nGnRnE as u8,
-            MemoryAttributeEl1::Device_nGnRnE as u8,
-            MemoryAttributeEl1::Device_nGnRnE as u8,
-            MemoryAttributeEl1::Device_nGnRnE as u8,
-        ])
+    pub fn attr(&self, index: MairIndex) -> Option<MemoryAttributeEl1> {
+        MemoryAttributeEl1::decode(self.0[index.index()])
+    }
+
+    // The raw `Attr<n>` fields.
+    pub fn attr0(&self) -> u8 {
+        self.0[0]
+    }
+
+    pub fn attr1(&self) -> u8 {
+        self.0[1]
+    }
+
+    pub fn attr2(&self) -> u8 {
+        self.0[2]
+    }
+
+    pub fn attr3(&self) -> u8 {
+        self.0[3]
+    }
+
+    pub fn attr4(&self) -> u8 {
+        self.0[4]
+    }
+
+    pub fn attr5(&self) -> u8 {
+        self.0[5]
+    }
+
+    pub fn attr6(&self) -> u8 {
+        self.0[6]
+    }
+
+    pub fn attr7(&self) -> u8 {
+        self.0[7]
     }
 }
 
@@ -751,26 +1487,6 @@ pub struct TranslationControlEl1 {
     _mbz2: u64,
 }
 
-#[bitfield(u64)]
-pub struct TranslationBase0El1 {
-    // #[bits(1)]
-    // pub cnp: u64,
-    #[bits(48)]
-    pub baddr: u64,
-    #[bits(16)]
-    pub asid: u64,
-}
-
-#[bitfield(u64)]
-pub struct TranslationBase1El1 {
-    // #[bits(1)]
-    // pub cnp: u64,
-    #[bits(48)]
-    pub baddr: u64,
-    #[bits(16)]
-    pub asid: u64,
-}
-
 #[derive(Debug)]
 #[repr(u64)]
 pub enum MmfPaRange {
@@ -1123,117 +1839,1908 @@ pub struct MmFeatures3El1 {
     pub spec_fpacc: u64,
 }
 
+/// Defines the enum of an ID register field. The fields get new values as
+/// the architecture evolves, the values not listed decode to `Unknown`.
+macro_rules! id_field {
+    ($(#[$attr:meta])* $name:ident { $($variant:ident = $value:literal),+ $(,)? }) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($variant,)+
+            Unknown(u8),
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                match value {
+                    $($value => $name::$variant,)+
+                    _ => $name::Unknown(value as u8),
+                }
+            }
+        }
+
+        impl From<$name> for u64 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)+
+                    $name::Unknown(value) => value as u64,
+                }
+            }
+        }
+
+        impl $crate::regs::fields::FieldValues for $name {
+            const VALUES: &'static [(&'static str, u64)] = &[$((stringify!($variant), $value)),+];
+        }
+    };
+}
+
+pub(crate) use id_field;
+
+id_field!(
+    /// The fields that only tell if a feature is there
+    IdFeature {
+        No = 0b0000,
+        Yes = 0b0001,
+    }
+);
+
+id_field!(IsaAes {
+    No = 0b0000,
+    Aes = 0b0001,
+    AesPmull = 0b0010,
+});
+
+id_field!(IsaSha2 {
+    No = 0b0000,
+    Sha256 = 0b0001,
+    Sha512 = 0b0010,
+});
+
+id_field!(IsaAtomic {
+    No = 0b0000,
+    Lse = 0b0010,
+    Lse128 = 0b0011,
+});
+
+id_field!(IsaTs {
+    No = 0b0000,
+    FlagM = 0b0001,
+    FlagM2 = 0b0010,
+});
+
+id_field!(IsaTlb {
+    No = 0b0000,
+    Os = 0b0001,
+    OsRange = 0b0010,
+});
+
+#[bitfield(u64)]
+pub struct InstructionSetFeatures0El1 {
+    #[bits(4)]
+    _mbz0: u64,
+    #[bits(4)]
+    pub aes: IsaAes,
+    #[bits(4)]
+    pub sha1: IdFeature,
+    #[bits(4)]
+    pub sha2: IsaSha2,
+    #[bits(4)]
+    pub crc32: IdFeature,
+    #[bits(4)]
+    pub atomic: IsaAtomic,
+    #[bits(4)]
+    pub tme: IdFeature,
+    #[bits(4)]
+    pub rdm: IdFeature,
+    #[bits(4)]
+    pub sha3: IdFeature,
+    #[bits(4)]
+    pub sm3: IdFeature,
+    #[bits(4)]
+    pub sm4: IdFeature,
+    #[bits(4)]
+    pub dp: IdFeature,
+    #[bits(4)]
+    pub fhm: IdFeature,
+    #[bits(4)]
+    pub ts: IsaTs,
+    #[bits(4)]
+    pub tlb: IsaTlb,
+    #[bits(4)]
+    pub rndr: IdFeature,
+}
+
+id_field!(IsaDpb {
+    No = 0b0000,
+    Cvap = 0b0001,
+    Cvadp = 0b0010,
+});
+
+id_field!(
+    /// The address and the generic authentication
+    IsaPAuth {
+        No = 0b0000,
+        PAuth = 0b0001,
+        EPac = 0b0010,
+        PAuth2 = 0b0011,
+        Fpac = 0b0100,
+        FpacCombined = 0b0101,
+    }
+);
+
+id_field!(IsaLrcpc {
+    No = 0b0000,
+    Lrcpc = 0b0001,
+    Lrcpc2 = 0b0010,
+    Lrcpc3 = 0b0011,
+});
+
+id_field!(IsaSpecRes {
+    No = 0b0000,
+    SpecRes = 0b0001,
+    SpecRes2 = 0b0010,
+});
+
+id_field!(IsaBf16 {
+    No = 0b0000,
+    Bf16 = 0b0001,
+    EBf16 = 0b0010,
+});
+
+id_field!(IsaLs64 {
+    No = 0b0000,
+    Ls64 = 0b0001,
+    Ls64V = 0b0010,
+    Ls64Accdata = 0b0011,
+});
+
+#[bitfield(u64)]
+pub struct InstructionSetFeatures1El1 {
+    #[bits(4)]
+    pub dpb: IsaDpb,
+    #[bits(4)]
+    pub apa: IsaPAuth,
+    #[bits(4)]
+    pub api: IsaPAuth,
+    #[bits(4)]
+    pub jscvt: IdFeature,
+    #[bits(4)]
+    pub fcma: IdFeature,
+    #[bits(4)]
+    pub lrcpc: IsaLrcpc,
+    #[bits(4)]
+    pub gpa: IdFeature,
+    #[bits(4)]
+    pub gpi: IdFeature,
+    #[bits(4)]
+    pub frintts: IdFeature,
+    #[bits(4)]
+    pub sb: IdFeature,
+    #[bits(4)]
+    pub specres: IsaSpecRes,
+    #[bits(4)]
+    pub bf16: IsaBf16,
+    #[bits(4)]
+    pub dgh: IdFeature,
+    #[bits(4)]
+    pub i8mm: IdFeature,
+    #[bits(4)]
+    pub xs: IdFeature,
+    #[bits(4)]
+    pub ls64: IsaLs64,
+}
+
+id_field!(IsaWfxt {
+    No = 0b0000,
+    Yes = 0b0010,
+});
+
+#[bitfield(u64)]
+pub struct InstructionSetFeatures2El1 {
+    #[bits(4)]
+    pub wfxt: IsaWfxt,
+    #[bits(4)]
+    pub rpres: IdFeature,
+    #[bits(4)]
+    pub gpa3: IdFeature,
+    #[bits(4)]
+    pub apa3: IsaPAuth,
+    #[bits(4)]
+    pub mops: IdFeature,
+    #[bits(4)]
+    pub bc: IdFeature,
+    #[bits(4)]
+    pub pac_frac: IdFeature,
+    #[bits(4)]
+    pub clrbhb: IdFeature,
+    #[bits(4)]
+    pub sysreg_128: IdFeature,
+    #[bits(4)]
+    pub sysinstr_128: IdFeature,
+    #[bits(4)]
+    pub prfmslc: IdFeature,
+    #[bits(4)]
+    pub pcdphint: IdFeature,
+    #[bits(4)]
+    pub rprfm: IdFeature,
+    #[bits(4)]
+    pub cssc: IdFeature,
+    #[bits(4)]
+    pub lut: IdFeature,
+    #[bits(4)]
+    pub ats1a: IdFeature,
+}
+
+id_field!(DbgVer {
+    V8 = 0b0110,
+    V8_Vhe = 0b0111,
+    V8_2 = 0b1000,
+    V8_4 = 0b1001,
+    V8_8 = 0b1010,
+    V8_9 = 0b1011,
+});
+
+id_field!(DbgPmuVer {
+    No = 0b0000,
+    PmuV3 = 0b0001,
+    PmuV3p1 = 0b0100,
+    PmuV3p4 = 0b0101,
+    PmuV3p5 = 0b0110,
+    PmuV3p7 = 0b0111,
+    PmuV3p8 = 0b1000,
+    PmuV3p9 = 0b1001,
+    ImpDef = 0b1111,
+});
+
+id_field!(DbgPmsVer {
+    No = 0b0000,
+    Spe = 0b0001,
+    SpeV1p1 = 0b0010,
+    SpeV1p2 = 0b0011,
+    SpeV1p3 = 0b0100,
+    SpeV1p4 = 0b0101,
+});
+
+id_field!(DbgDoubleLock {
+    Yes = 0b0000,
+    No = 0b1111,
+});
+
+id_field!(DbgTraceBuffer {
+    No = 0b0000,
+    Trbe = 0b0001,
+    TrbeV1p1 = 0b0010,
+});
+
+id_field!(DbgMtPmu {
+    NoOrImpDef = 0b0000,
+    Yes = 0b0001,
+    No = 0b1111,
+});
+
+id_field!(DbgBrbe {
+    No = 0b0000,
+    Brbe = 0b0001,
+    BrbeV1p1 = 0b0010,
+});
+
+#[bitfield(u64)]
+pub struct DebugFeatures0El1 {
+    #[bits(4)]
+    pub debug_ver: DbgVer,
+    #[bits(4)]
+    pub trace_ver: IdFeature,
+    #[bits(4)]
+    pub pmu_ver: DbgPmuVer,
+    /// The number of breakpoints minus 1
+    #[bits(4)]
+    pub brps: u64,
+    #[bits(4)]
+    pub pmss: IdFeature,
+    /// The number of watchpoints minus 1
+    #[bits(4)]
+    pub wrps: u64,
+    #[bits(4)]
+    pub sebep: IdFeature,
+    /// The number of context-aware breakpoints minus 1
+    #[bits(4)]
+    pub ctx_cmps: u64,
+    #[bits(4)]
+    pub pms_ver: DbgPmsVer,
+    #[bits(4)]
+    pub double_lock: DbgDoubleLock,
+    #[bits(4)]
+    pub trace_filt: IdFeature,
+    #[bits(4)]
+    pub trace_buffer: DbgTraceBuffer,
+    #[bits(4)]
+    pub mtpmu: DbgMtPmu,
+    #[bits(4)]
+    pub brbe: DbgBrbe,
+    #[bits(4)]
+    pub ext_trc_buff: IdFeature,
+    #[bits(4)]
+    pub hpmn0: IdFeature,
+}
+
+id_field!(SveVer {
+    Sve = 0b0000,
+    Sve2 = 0b0001,
+    Sve2p1 = 0b0010,
+});
+
+id_field!(SveAes {
+    No = 0b0000,
+    Aes = 0b0001,
+    AesPmull128 = 0b0010,
+});
+
+id_field!(SveBf16 {
+    No = 0b0000,
+    Bf16 = 0b0001,
+    EBf16 = 0b0010,
+});
+
+/// Valid only if SVE is implemented, reads as zero otherwise
 #[bitfield(u64)]
-pub struct MmFeatures4El1 {
+pub struct SveFeatures0El1 {
     #[bits(4)]
+    pub sve_ver: SveVer,
+    #[bits(4)]
+    pub aes: SveAes,
+    #[bits(8)]
     _mbz0: u64,
     #[bits(4)]
-    pub eiesb: u64,
-    #[bits(56)]
+    pub bit_perm: IdFeature,
+    #[bits(4)]
+    pub bf16: SveBf16,
+    #[bits(4)]
+    pub b16b16: IdFeature,
+    #[bits(4)]
     _mbz1: u64,
+    #[bits(4)]
+    pub sha3: IdFeature,
+    #[bits(4)]
+    _mbz2: u64,
+    #[bits(4)]
+    pub sm4: IdFeature,
+    #[bits(4)]
+    pub i8mm: IdFeature,
+    #[bits(4)]
+    _mbz3: u64,
+    #[bits(4)]
+    pub f32mm: IdFeature,
+    #[bits(4)]
+    pub f64mm: IdFeature,
+    #[bits(4)]
+    _mbz4: u64,
 }
 
-pub mod access {
-    use super::*;
-    use core::arch::asm;
+id_field!(SmeVer {
+    Sme = 0b0000,
+    Sme2 = 0b0001,
+    Sme2p1 = 0b0010,
+});
 
-    #[macro_export]
-    macro_rules! load_sys_reg {
-        ($reg:ident) => {{
-            let reg_val: u64;
-            unsafe {
-                asm!(concat!("mrs {}, ", stringify!($reg)), out(reg) reg_val);
-            }
-            reg_val
-        }};
+id_field!(
+    /// The SME instructions accumulating into integers are there or not
+    SmeIntAccumulate {
+        No = 0b0000,
+        Yes = 0b1111,
     }
+);
 
-    #[macro_export]
-    macro_rules! store_sys_reg {
-        ($reg:ident, $val:expr) => {{
-            let val: u64 = $val;
-            unsafe {
-                asm!(concat!("msr ", stringify!($reg), ", {}; ", "dsb ishst; dsb ish; isb"), in(reg) val);
-            }
-        }};
+id_field!(SmeI16I32 {
+    No = 0b0000,
+    Yes = 0b0101,
+});
+
+/// Valid only if SME is implemented, reads as zero otherwise
+#[bitfield(u64)]
+pub struct SmeFeatures0El1 {
+    #[bits(32)]
+    _mbz0: u64,
+    pub f32f32: bool,
+    pub bi32i32: bool,
+    pub b16f32: bool,
+    pub f16f32: bool,
+    #[bits(4)]
+    pub i8i32: SmeIntAccumulate,
+    pub f8f32: bool,
+    pub f8f16: bool,
+    pub f16f16: bool,
+    pub b16b16: bool,
+    #[bits(4)]
+    pub i16i32: SmeI16I32,
+    pub f64f64: bool,
+    #[bits(3)]
+    _mbz1: u64,
+    #[bits(4)]
+    pub i16i64: SmeIntAccumulate,
+    #[bits(4)]
+    pub sme_ver: SmeVer,
+    #[bits(3)]
+    _mbz2: u64,
+    pub fa64: bool,
+}
+
+/// Stage 1 base permissions a PIIndex selects in PIR_EL1 and PIRE0_EL1.
+/// The `_O` permissions can be further restricted by the Permission Overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagePermission {
+    NoAccess_O,
+    Read_O,
+    Execute_O,
+    ReadExecute_O,
+    ReadWrite_O,
+    ReadWriteNx_O,
+    ReadWriteExecute_O,
+    Read,
+    ReadGcs,
+    ReadExecute,
+    ReadWrite,
+    ReadWriteExecute,
+    /// 0x4, 0xb, 0xd and 0xf, a register may still hold them
+    Reserved(u8),
+}
+
+impl From<PagePermission> for u64 {
+    fn from(value: PagePermission) -> Self {
+        match value {
+            PagePermission::NoAccess_O => 0x0,
+            PagePermission::Read_O => 0x1,
+            PagePermission::Execute_O => 0x2,
+            PagePermission::ReadExecute_O => 0x3,
+            PagePermission::ReadWrite_O => 0x5,
+            PagePermission::ReadWriteNx_O => 0x6,
+            PagePermission::ReadWriteExecute_O => 0x7,
+            PagePermission::Read => 0x8,
+            PagePermission::ReadGcs => 0x9,
+            PagePermission::ReadExecute => 0xa,
+            PagePermission::ReadWrite => 0xc,
+            PagePermission::ReadWriteExecute => 0xe,
+            PagePermission::Reserved(value) => u64::from(value & 0xf),
+        }
     }
+}
 
-    pub trait Aarch64Register: core::fmt::Debug {
-        fn load(&mut self);
-        fn name(&self) -> &'static str;
-        fn bits(&self) -> u64;
+impl From<u64> for PagePermission {
+    fn from(value: u64) -> Self {
+        match value & 0xf {
+            0x0 => PagePermission::NoAccess_O,
+            0x1 => PagePermission::Read_O,
+            0x2 => PagePermission::Execute_O,
+            0x3 => PagePermission::ReadExecute_O,
+            0x5 => PagePermission::ReadWrite_O,
+            0x6 => PagePermission::ReadWriteNx_O,
+            0x7 => PagePermission::ReadWriteExecute_O,
+            0x8 => PagePermission::Read,
+            0x9 => PagePermission::ReadGcs,
+            0xa => PagePermission::ReadExecute,
+            0xc => PagePermission::ReadWrite,
+            0xe => PagePermission::ReadWriteExecute,
+            reserved => PagePermission::Reserved(reserved as u8),
+        }
     }
+}
 
-    macro_rules! impl_register_access {
-        ($register_type:ident, $register:ident) => {
-            impl Aarch64Register for $register_type {
-                fn load(&mut self) {
-                    let val: u64 = load_sys_reg!($register).into();
-                    *self = Self::from(val);
-                }
+/// The number of PIIndex values, and of POIndex values too
+pub const PERMISSION_INDEX_COUNT: usize = 16;
 
-                fn name(&self) -> &'static str {
-                    stringify!($register)
-                }
+/// PIR_EL1, the EL1 permissions for each PIIndex
+#[derive(Debug, Clone, Copy)]
+pub struct PermissionIndirectionEl1(u64);
 
-                fn bits(&self) -> u64 {
-                    (*self).into()
-                }
+/// PIRE0_EL1, the EL0 permissions for each PIIndex
+#[derive(Debug, Clone, Copy)]
+pub struct PermissionIndirectionE0El1(u64);
+
+macro_rules! impl_permission_indirection {
+    ($register_type:ident) => {
+        impl $register_type {
+            pub fn new() -> Self {
+                Self(0)
             }
 
-            impl $register_type {
-                pub fn store(&mut self) {
-                    let val: u64 = (*self).into();
-                    store_sys_reg!($register, val)
-                }
+            pub fn get(&self, pi_index: usize) -> PagePermission {
+                assert!(pi_index < PERMISSION_INDEX_COUNT);
+                PagePermission::from((self.0 >> (4 * pi_index)) & 0xf)
             }
-        };
-    }
 
-    macro_rules! impl_register_access_ro {
-        ($register_type:ident, $register:ident) => {
-            impl Aarch64Register for $register_type {
-                fn load(&mut self) {
-                    let val: u64 = load_sys_reg!($register).into();
-                    *self = Self::from(val);
-                }
+            pub fn with(mut self, pi_index: usize, perm: PagePermission) -> Self {
+                assert!(pi_index < PERMISSION_INDEX_COUNT);
+                self.0 &= !(0xf << (4 * pi_index));
+                self.0 |= u64::from(perm) << (4 * pi_index);
+                self
+            }
+        }
 
-                fn name(&self) -> &'static str {
-                    stringify!($register)
-                }
+        impl From<u64> for $register_type {
+            fn from(value: u64) -> Self {
+                Self(value)
+            }
+        }
 
-                fn bits(&self) -> u64 {
-                    (*self).into()
-                }
+        impl From<$register_type> for u64 {
+            fn from(value: $register_type) -> Self {
+                value.0
             }
-        };
+        }
+    };
+}
+
+impl_permission_indirection!(PermissionIndirectionEl1);
+impl_permission_indirection!(PermissionIndirectionE0El1);
+
+// The PIIndex of a descriptor is {UXN, PXN, DBM, AP[1]}, see `PageBlockEntry`.
+const PI_INDEX_UXN: usize = 1 << 3;
+const PI_INDEX_PXN: usize = 1 << 2;
+const PI_INDEX_AP1: usize = 1 << 0;
+
+impl Default for PermissionIndirectionEl1 {
+    /// Follows the PXN bit of the descriptors: read-write where it is
+    /// set, read-write-execute elsewhere.
+    fn default() -> Self {
+        (0..PERMISSION_INDEX_COUNT).fold(Self::new(), |pir, pi_index| {
+            pir.with(
+                pi_index,
+                if pi_index & PI_INDEX_PXN != 0 {
+                    PagePermission::ReadWrite
+                } else {
+                    PagePermission::ReadWriteExecute
+                },
+            )
+        })
+    }
+}
+
+impl Default for PermissionIndirectionE0El1 {
+    /// Follows the AP and UXN bits of the descriptors.
+    fn default() -> Self {
+        (0..PERMISSION_INDEX_COUNT).fold(Self::new(), |pir, pi_index| {
+            pir.with(
+                pi_index,
+                if pi_index & PI_INDEX_AP1 == 0 {
+                    PagePermission::NoAccess_O
+                } else if pi_index & PI_INDEX_UXN != 0 {
+                    PagePermission::ReadWrite
+                } else {
+                    PagePermission::ReadWriteExecute
+                },
+            )
+        })
     }
+}
 
-    impl_register_access_ro!(MainIdEl1, MIDR_EL1);
-    impl_register_access_ro!(ProcessorFeatures0El1, ID_AA64PFR0_EL1);
-    impl_register_access_ro!(ProcessorFeatures1El1, ID_AA64PFR1_EL1);
-    impl_register_access_ro!(MmFeatures0El1, ID_AA64MMFR0_EL1);
-    impl_register_access_ro!(MmFeatures1El1, ID_AA64MMFR1_EL1);
-    impl_register_access_ro!(MmFeatures2El1, ID_AA64MMFR2_EL1);
-    impl_register_access_ro!(MmFeatures3El1, ID_AA64MMFR3_EL1);
-    impl_register_access_ro!(MmFeatures4El1, ID_AA64MMFR4_EL1);
+/// The permissions a POIndex selects in POR_EL1
+#[bitfield(u8)]
+#[derive(PartialEq, Eq)]
+pub struct OverlayPermission {
+    pub r: bool,
+    pub x: bool,
+    pub w: bool,
+    #[bits(5)]
+    _mbz0: u8,
+}
 
-    impl_register_access_ro!(CurrentEl, CurrentEL);
+/// POR_EL1, restricts the `_O` permissions for each POIndex
+#[derive(Debug, Clone, Copy)]
+pub struct PermissionOverlayEl1(u64);
 
-    impl_register_access!(SystemControlEl1, SCTLR_EL1);
-    impl_register_access!(VectorBaseEl1, VBAR_EL1);
-    impl_register_access!(ExceptionLinkEl1, ELR_EL1);
-    impl_register_access!(ExceptionSyndromeEl1, ESR_EL1);
-    impl_register_access!(SavedProgramStateEl1, SPSR_EL1);
-    impl_register_access!(TranslationControlEl1, TCR_EL1);
-    impl_register_access!(TranslationBase0El1, TTBR0_EL1);
-    impl_register_access!(TranslationBase1El1, TTBR1_EL1);
-    impl_register_access!(MemoryAttributeIndirectionEl1, MAIR_EL1);
+impl PermissionOverlayEl1 {
+    pub fn new() -> Self {
+        Self(0)
+    }
 
-    #[macro_export]
-    macro_rules! register {
-        ($reg:ident) => {
-            &mut $reg::new() as &mut dyn Aarch64Register
-        };
+    pub fn get(&self, po_index: usize) -> OverlayPermission {
+        assert!(po_index < PERMISSION_INDEX_COUNT);
+        OverlayPermission::from(((self.0 >> (4 * po_index)) & 0xf) as u8)
+    }
+
+    pub fn with(mut self, po_index: usize, perm: OverlayPermission) -> Self {
+        assert!(po_index < PERMISSION_INDEX_COUNT);
+        self.0 &= !(0xf << (4 * po_index));
+        self.0 |= (u8::from(perm) as u64 & 0xf) << (4 * po_index);
+        self
     }
 }
+
+impl Default for PermissionOverlayEl1 {
+    /// No restrictions for the POIndex 0, no access for the others.
+    fn default() -> Self {
+        Self::new().with(
+            0,
+            OverlayPermission::new()
+                .with_r(true)
+                .with_w(true)
+                .with_x(true),
+        )
+    }
+}
+
+impl From<u64> for PermissionOverlayEl1 {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<PermissionOverlayEl1> for u64 {
+    fn from(value: PermissionOverlayEl1) -> Self {
+        value.0
+    }
+}
+
+/// TCR2_EL1, requires FEAT_TCR2
+#[bitfield(u64)]
+pub struct TranslationControl2El1 {
+    #[bits(1)]
+    pub pnch: u64,
+    /// Use the Permission Indirection
+    #[bits(1)]
+    pub pie: u64,
+    #[bits(1)]
+    pub e0poe: u64,
+    /// Apply the Permission Overlay at EL1
+    #[bits(1)]
+    pub poe: u64,
+    #[bits(1)]
+    pub aie: u64,
+    #[bits(1)]
+    pub d128: u64,
+    #[bits(4)]
+    _mbz0: u64,
+    #[bits(1)]
+    pub pttwi: u64,
+    #[bits(1)]
+    pub haft: u64,
+    #[bits(52)]
+    _rest: u64,
+}
+
+/// GCR_EL1, controls the tags generated by IRG
+#[bitfield(u64)]
+pub struct TagControlEl1 {
+    /// Tags IRG must not generate, one bit per tag value
+    #[bits(16)]
+    pub exclude: u64,
+    /// Use the IMPLEMENTATION DEFINED random generator and not RGSR_EL1
+    #[bits(1)]
+    pub rrnd: u64,
+    #[bits(47)]
+    _mbz0: u64,
+}
+
+/// RGSR_EL1, the seed of the pseudo-random tag generator
+#[bitfield(u64)]
+pub struct RandomTagSeedEl1 {
+    #[bits(4)]
+    pub tag: u64,
+    #[bits(4)]
+    _mbz0: u64,
+    #[bits(16)]
+    pub seed: u64,
+    #[bits(40)]
+    _mbz1: u64,
+}
+
+/// TFSR_EL1, accumulates asynchronous Tag Check Faults
+#[bitfield(u64)]
+pub struct TagFaultStatusEl1 {
+    /// Fault on an access via TTBR0_EL1
+    #[bits(1)]
+    pub tf0: u64,
+    /// Fault on an access via TTBR1_EL1
+    #[bits(1)]
+    pub tf1: u64,
+    #[bits(62)]
+    _mbz0: u64,
+}
+
+/// Write-only, locks the debug registers against the external debugger
+#[bitfield(u64)]
+pub struct OsLockAccessEl1 {
+    pub oslk: bool,
+    #[bits(63)]
+    _mbz0: u64,
+}
+
+/// Hypervisor configuration. The layout is that of `HCR_EL2.E2H` clear,
+/// the lab does not use the Virtualization Host Extensions.
+#[bitfield(u64)]
+pub struct HypervisorConfigEl2 {
+    /// Stage 2 translation of the EL1&0 regime
+    pub vm: bool,
+    pub swio: bool,
+    pub ptw: bool,
+    /// Route the physical FIQ, IRQ and SError to EL2
+    pub fmo: bool,
+    pub imo: bool,
+    pub amo: bool,
+    /// Pending virtual FIQ, IRQ and SError
+    pub vf: bool,
+    pub vi: bool,
+    pub vse: bool,
+    pub fb: bool,
+    #[bits(2)]
+    pub bsu: u64,
+    pub dc: bool,
+    pub twi: bool,
+    pub twe: bool,
+    pub tid0: bool,
+    pub tid1: bool,
+    pub tid2: bool,
+    pub tid3: bool,
+    pub tsc: bool,
+    pub tidcp: bool,
+    pub tacr: bool,
+    pub tsw: bool,
+    pub tpcp: bool,
+    pub tpu: bool,
+    pub ttlb: bool,
+    pub tvm: bool,
+    /// Route the exceptions of EL0 to EL2
+    pub tge: bool,
+    pub tdz: bool,
+    pub hcd: bool,
+    pub trvm: bool,
+    /// EL1 is AArch64
+    pub rw: bool,
+    pub cd: bool,
+    pub id: bool,
+    pub e2h: bool,
+    pub tlor: bool,
+    pub terr: bool,
+    pub tea: bool,
+    pub miocnce: bool,
+    pub tme: bool,
+    pub apk: bool,
+    pub api: bool,
+    pub nv: bool,
+    pub nv1: bool,
+    pub at: bool,
+    pub nv2: bool,
+    pub fwb: bool,
+    pub fien: bool,
+    pub gpf: bool,
+    pub tid4: bool,
+    pub ticab: bool,
+    pub amvoffen: bool,
+    pub tocu: bool,
+    pub en_scxt: bool,
+    pub ttlbis: bool,
+    pub ttlbos: bool,
+    pub ata: bool,
+    pub dct: bool,
+    pub tid5: bool,
+    pub twed_en: bool,
+    #[bits(4)]
+    pub twedel: u64,
+}
+
+#[bitfield(u64)]
+pub struct SystemControlEl2 {
+    #[bits(1)]
+    pub m: u64,
+    #[bits(1)]
+    pub a: u64,
+    #[bits(1)]
+    pub c: u64,
+    #[bits(1)]
+    pub sa: u64,
+    #[bits(2)]
+    _mbo0: u64,
+    #[bits(1)]
+    pub n_aa: u64,
+    #[bits(4)]
+    _mbz0: u64,
+    #[bits(1)]
+    pub eos: u64,
+    #[bits(1)]
+    pub i: u64,
+    #[bits(1)]
+    pub en_db: u64,
+    #[bits(2)]
+    _mbz1: u64,
+    #[bits(1)]
+    _mbo1: u64,
+    #[bits(1)]
+    _mbz2: u64,
+    #[bits(1)]
+    _mbo2: u64,
+    #[bits(1)]
+    pub wxn: u64,
+    #[bits(1)]
+    _mbz3: u64,
+    #[bits(1)]
+    pub iesb: u64,
+    #[bits(1)]
+    pub eis: u64,
+    #[bits(1)]
+    pub span: u64,
+    #[bits(1)]
+    _mbz4: u64,
+    #[bits(1)]
+    pub ee: u64,
+    #[bits(1)]
+    _mbz5: u64,
+    #[bits(1)]
+    pub en_da: u64,
+    #[bits(2)]
+    _mbo3: u64,
+    #[bits(1)]
+    pub en_ib: u64,
+    #[bits(1)]
+    pub en_ia: u64,
+    #[bits(4)]
+    _mbz6: u64,
+    #[bits(1)]
+    pub bt: u64,
+    #[bits(1)]
+    pub itfsb: u64,
+    #[bits(2)]
+    _mbz7: u64,
+    #[bits(2)]
+    pub tcf: u64,
+    #[bits(1)]
+    _mbz8: u64,
+    #[bits(1)]
+    pub ata: u64,
+    #[bits(1)]
+    pub dssbs: u64,
+    #[bits(19)]
+    _rest: u64,
+}
+
+impl SystemControlEl2 {
+    const RES1: u64 = 0x30c5_0830;
+}
+
+impl Default for SystemControlEl2 {
+    fn default() -> Self {
+        Self::from(Self::RES1)
+    }
+}
+
+// Must be aligned to a 2KB boundary
+#[bitfield(u64)]
+pub struct VectorBaseEl2 {
+    #[bits(11)]
+    _mbz0: u64,
+    #[bits(53)]
+    pub vbar_shift_11: u64,
+}
+
+#[bitfield(u64)]
+pub struct ExceptionSyndromeEl2 {
+    #[bits(25)]
+    pub iss: u64,
+    #[bits(1)]
+    pub il: u64,
+    #[bits(6)]
+    pub ec: ExceptionClass,
+    #[bits(24)]
+    pub iss2: u64,
+    #[bits(8)]
+    pub _mbz: u64,
+}
+
+impl ExceptionSyndromeEl2 {
+    /// The syndromes of EL2 are encoded as those of EL1.
+    pub fn decode(&self) -> Syndrome {
+        ExceptionSyndromeEl1::from(u64::from(*self)).decode()
+    }
+}
+
+#[bitfield(u64)]
+pub struct ExceptionLinkEl2 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+#[bitfield(u64)]
+pub struct SavedProgramStateEl2 {
+    #[bits(4)]
+    pub mode: SavedProgramStateMode,
+    pub aarch32: bool,
+    #[bits(1)]
+    _mbz0: u64,
+    pub f: bool,
+    pub i: bool,
+    pub a: bool,
+    pub d: bool,
+    /// The PSTATE.BTYPE of the interrupted branch
+    #[bits(2)]
+    pub btype: u64,
+    #[bits(8)]
+    _mbz1: u64,
+    /// Illegal Execution state
+    pub il: bool,
+    /// Software Step, the step is active after the exception return.
+    pub ss: bool,
+    #[bits(42)]
+    _rest: u64,
+}
+
+#[bitfield(u64)]
+pub struct FaultAddressEl2 {
+    #[bits(64)]
+    pub bits: u64,
+}
+
+/// The faulting intermediate physical address of the stage 2 aborts
+#[bitfield(u64)]
+pub struct HypervisorIpaFaultAddressEl2 {
+    #[bits(4)]
+    _mbz0: u64,
+    /// The bits [55:12] of the IPA
+    #[bits(44)]
+    pub fipa: u64,
+    #[bits(15)]
+    _mbz1: u64,
+    pub ns: bool,
+}
+
+impl HypervisorIpaFaultAddressEl2 {
+    /// The page of the faulting IPA, the offset in it comes from FAR_EL2.
+    pub fn ipa(&self) -> u64 {
+        self.fipa() << 12
+    }
+}
+
+#[bitfield(u64)]
+pub struct TranslationControlEl2 {
+    #[bits(6)]
+    pub t0sz: u64,
+    #[bits(2)]
+    _mbz0: u64,
+    #[bits(2)]
+    pub irgn0: u64,
+    #[bits(2)]
+    pub orgn0: u64,
+    #[bits(2)]
+    pub sh0: u64,
+    #[bits(2)]
+    pub tg0: TranslationGranule0,
+    #[bits(3)]
+    pub ps: IntermPhysAddrSize,
+    #[bits(1)]
+    _mbz1: u64,
+    #[bits(1)]
+    pub tbi: u64,
+    #[bits(1)]
+    pub ha: u64,
+    #[bits(1)]
+    pub hd: u64,
+    #[bits(1)]
+    _mbo0: u64,
+    #[bits(1)]
+    pub hpd: u64,
+    #[bits(4)]
+    pub hwu: u64,
+    #[bits(1)]
+    pub tbid: u64,
+    #[bits(1)]
+    pub tcma: u64,
+    #[bits(1)]
+    _mbo1: u64,
+    #[bits(1)]
+    pub ds: u64,
+    #[bits(31)]
+    _rest: u64,
+}
+
+impl TranslationControlEl2 {
+    const RES1: u64 = (1 << 31) | (1 << 23);
+}
+
+impl Default for TranslationControlEl2 {
+    fn default() -> Self {
+        Self::from(Self::RES1)
+    }
+}
+
+#[bitfield(u64)]
+pub struct TranslationBase0El2 {
+    #[bits(48)]
+    pub baddr: u64,
+    /// Used only with `HCR_EL2.E2H` set
+    #[bits(16)]
+    pub asid: u64,
+}
+
+/// MAIR_EL2 has the same attribute encodings as MAIR_EL1.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryAttributeIndirectionEl2(pub MemoryAttributeIndirectionEl1);
+
+impl MemoryAttributeIndirectionEl2 {
+    pub fn new() -> Self {
+        Self(MemoryAttributeIndirectionEl1::new())
+    }
+}
+
+impl From<u64> for MemoryAttributeIndirectionEl2 {
+    fn from(value: u64) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<MemoryAttributeIndirectionEl2> for u64 {
+    fn from(value: MemoryAttributeIndirectionEl2) -> Self {
+        value.0.into()
+    }
+}
+
+/// Traps to EL2 of the SVE, SME, FP and SIMD instructions, the trace and
+/// the activity monitor registers accesses
+#[bitfield(u64)]
+pub struct ArchFeatureTrapEl2 {
+    #[bits(8)]
+    _mbo0: u64,
+    pub tz: bool,
+    #[bits(1)]
+    _mbo1: u64,
+    pub tfp: bool,
+    #[bits(1)]
+    _mbz0: u64,
+    pub tsm: bool,
+    #[bits(1)]
+    _mbo2: u64,
+    #[bits(6)]
+    _mbz1: u64,
+    pub tta: bool,
+    #[bits(9)]
+    _mbz2: u64,
+    pub tam: bool,
+    pub tcpac: bool,
+    #[bits(32)]
+    _mbz3: u64,
+}
+
+impl ArchFeatureTrapEl2 {
+    const RES1: u64 = 0x22ff;
+}
+
+impl Default for ArchFeatureTrapEl2 {
+    /// Traps nothing
+    fn default() -> Self {
+        Self::from(Self::RES1)
+    }
+}
+
+/// Access to the physical counter and timer from EL1 and EL0, and the
+/// event stream
+#[bitfield(u64)]
+pub struct CounterHypControlEl2 {
+    pub el1pcten: bool,
+    pub el1pcen: bool,
+    pub evnten: bool,
+    pub evntdir: bool,
+    #[bits(4)]
+    pub evnti: u64,
+    #[bits(4)]
+    _mbz0: u64,
+    pub ecv: bool,
+    pub el1tvt: bool,
+    pub el1tvct: bool,
+    pub el1nvpct: bool,
+    pub el1nvvct: bool,
+    pub evntis: bool,
+    pub cntvmask: bool,
+    pub cntpmask: bool,
+    #[bits(44)]
+    _rest: u64,
+}
+
+/// The value EL1 reads from MIDR_EL1
+#[bitfield(u64)]
+pub struct VirtualizationProcessorIdEl2 {
+    #[bits(4)]
+    pub revision: u64,
+    #[bits(12)]
+    pub part_num: u64,
+    #[bits(4)]
+    pub architecture: u64,
+    #[bits(4)]
+    pub variant: u64,
+    #[bits(8)]
+    pub implementer: u64,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+/// The value EL1 reads from MPIDR_EL1
+#[bitfield(u64)]
+pub struct VirtualizationMultiprocessorIdEl2 {
+    #[bits(8)]
+    pub aff0: u64,
+    #[bits(8)]
+    pub aff1: u64,
+    #[bits(8)]
+    pub aff2: u64,
+    pub mt: bool,
+    #[bits(5)]
+    _mbz0: u64,
+    pub u: bool,
+    #[bits(1)]
+    _mbo0: u64,
+    #[bits(8)]
+    pub aff3: u64,
+    #[bits(24)]
+    _mbz1: u64,
+}
+
+pub mod access {
+    use super::*;
+
+    /// Where the system register accesses go. The `native` closures
+    /// execute the MRS and MSR instructions, the backend decides whether
+    /// to run them.
+    pub trait RegisterBackend {
+        fn load(name: &'static str, native: impl FnOnce() -> u64) -> u64;
+        fn store(name: &'static str, value: u64, native: impl FnOnce(u64));
+        fn load128(name: &'static str, native: impl FnOnce() -> u128) -> u128;
+        fn store128(name: &'static str, value: u128, native: impl FnOnce(u128));
+    }
+
+    /// Accesses the registers of the CPU
+    pub struct Native;
+
+    impl RegisterBackend for Native {
+        fn load(_name: &'static str, native: impl FnOnce() -> u64) -> u64 {
+            native()
+        }
+
+        fn store(_name: &'static str, value: u64, native: impl FnOnce(u64)) {
+            native(value)
+        }
+
+        fn load128(_name: &'static str, native: impl FnOnce() -> u128) -> u128 {
+            native()
+        }
+
+        fn store128(_name: &'static str, value: u128, native: impl FnOnce(u128)) {
+            native(value)
+        }
+    }
+
+    /// Keeps the registers in memory, so the code accessing them runs in
+    /// the unit tests and on the host. The registers never written read
+    /// as zero. Each test thread has its own set of registers.
+    #[cfg(any(test, not(target_arch = "aarch64")))]
+    pub struct Simulated;
+
+    #[cfg(any(test, not(target_arch = "aarch64")))]
+    impl Simulated {
+        /// Presets a register, e.g. an ID register before detecting
+        /// the features.
+        pub fn set(name: &'static str, value: u64) {
+            simulated::with_register_file(|file| file.set(name, value as u128));
+        }
+
+        pub fn get(name: &str) -> Option<u64> {
+            simulated::with_register_file(|file| file.get(name).map(|value| value as u64))
+        }
+
+        pub fn get128(name: &str) -> Option<u128> {
+            simulated::with_register_file(|file| file.get(name))
+        }
+
+        /// Forgets all the registers.
+        pub fn reset() {
+            simulated::with_register_file(|file| *file = simulated::RegisterFile::new());
+        }
+    }
+
+    #[cfg(any(test, not(target_arch = "aarch64")))]
+    impl RegisterBackend for Simulated {
+        fn load(name: &'static str, _native: impl FnOnce() -> u64) -> u64 {
+            Self::get(name).unwrap_or_default()
+        }
+
+        /// A 64-bit write clears the bits [127:64].
+        fn store(name: &'static str, value: u64, _native: impl FnOnce(u64)) {
+            Self::set(name, value)
+        }
+
+        fn load128(name: &'static str, _native: impl FnOnce() -> u128) -> u128 {
+            Self::get128(name).unwrap_or_default()
+        }
+
+        fn store128(name: &'static str, value: u128, _native: impl FnOnce(u128)) {
+            simulated::with_register_file(|file| file.set(name, value));
+        }
+    }
+
+    #[cfg(any(test, not(target_arch = "aarch64")))]
+    mod simulated {
+        const MAX_REGISTERS: usize = 128;
+
+        pub(super) struct RegisterFile {
+            registers: [(&'static str, u128); MAX_REGISTERS],
+            count: usize,
+        }
+
+        impl RegisterFile {
+            pub(super) const fn new() -> Self {
+                Self {
+                    registers: [("", 0); MAX_REGISTERS],
+                    count: 0,
+                }
+            }
+
+            pub(super) fn get(&self, name: &str) -> Option<u128> {
+                self.registers[..self.count]
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|&(_, value)| value)
+            }
+
+            pub(super) fn set(&mut self, name: &'static str, value: u128) {
+                if let Some(r) = self.registers[..self.count]
+                    .iter_mut()
+                    .find(|(n, _)| *n == name)
+                {
+                    r.1 = value;
+                    return;
+                }
+                assert!(self.count < MAX_REGISTERS, "too many simulated registers");
+                self.registers[self.count] = (name, value);
+                self.count += 1;
+            }
+        }
+
+        #[cfg(test)]
+        std::thread_local! {
+            static REGISTER_FILE: core::cell::RefCell<RegisterFile> =
+                const { core::cell::RefCell::new(RegisterFile::new()) };
+        }
+
+        #[cfg(test)]
+        pub(super) fn with_register_file<R>(f: impl FnOnce(&mut RegisterFile) -> R) -> R {
+            REGISTER_FILE.with_borrow_mut(f)
+        }
+
+        /// Without the threads of std, one register file guarded by a spin lock.
+        #[cfg(not(test))]
+        pub(super) fn with_register_file<R>(f: impl FnOnce(&mut RegisterFile) -> R) -> R {
+            use core::sync::atomic::AtomicBool;
+            use core::sync::atomic::Ordering;
+
+            struct Shared(core::cell::UnsafeCell<RegisterFile>);
+            // SAFETY: accessed only with LOCK held.
+            unsafe impl Sync for Shared {}
+
+            static LOCK: AtomicBool = AtomicBool::new(false);
+            static REGISTER_FILE: Shared = Shared(core::cell::UnsafeCell::new(RegisterFile::new()));
+
+            while LOCK
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                core::hint::spin_loop();
+            }
+            // SAFETY: LOCK is held.
+            let result = f(unsafe { &mut *REGISTER_FILE.0.get() });
+            LOCK.store(false, Ordering::Release);
+            result
+        }
+    }
+
+    #[cfg(all(target_arch = "aarch64", not(test)))]
+    pub type Backend = Native;
+    #[cfg(any(test, not(target_arch = "aarch64")))]
+    pub type Backend = Simulated;
+
+    #[macro_export]
+    macro_rules! load_sys_reg {
+        ($reg:ident $(, $ext:literal)?) => {{
+            #[cfg(target_arch = "aarch64")]
+            let native = || {
+                let reg_val: u64;
+                unsafe {
+                    core::arch::asm!(concat!($(".arch_extension ", $ext, "\n",)? "mrs {}, ", stringify!($reg)), out(reg) reg_val);
+                }
+                reg_val
+            };
+            #[cfg(not(target_arch = "aarch64"))]
+            let native = || unreachable!("no {} on the host", stringify!($reg));
+
+            <$crate::regs::access::Backend as $crate::regs::access::RegisterBackend>::load(
+                stringify!($reg),
+                native,
+            )
+        }};
+    }
+
+    #[macro_export]
+    macro_rules! store_sys_reg {
+        ($reg:ident, $ext:literal, $val:expr) => {
+            $crate::store_sys_reg!(@store $reg, $val, ".arch_extension ", $ext, "\n")
+        };
+        ($reg:ident, $val:expr) => {
+            $crate::store_sys_reg!(@store $reg, $val,)
+        };
+        (@store $reg:ident, $val:expr, $($prefix:literal),*) => {{
+            #[cfg(target_arch = "aarch64")]
+            let native = |val: u64| unsafe {
+                core::arch::asm!(concat!($($prefix,)* "msr ", stringify!($reg), ", {}; ", "dsb ishst; dsb ish; isb"), in(reg) val);
+            };
+            #[cfg(not(target_arch = "aarch64"))]
+            let native = |_: u64| unreachable!("no {} on the host", stringify!($reg));
+
+            <$crate::regs::access::Backend as $crate::regs::access::RegisterBackend>::store(
+                stringify!($reg),
+                $val,
+                native,
+            )
+        }};
+    }
+
+    pub trait Aarch64Register: core::fmt::Debug {
+        fn load(&mut self);
+        fn name(&self) -> &'static str;
+        fn bits(&self) -> u64;
+    }
+
+    /// The access markers of the system registers
+    #[derive(Debug)]
+    pub struct ReadOnly;
+    #[derive(Debug)]
+    pub struct WriteOnly;
+    #[derive(Debug)]
+    pub struct ReadWrite;
+
+    pub trait Readable {}
+    pub trait Writable {}
+
+    impl Readable for ReadOnly {}
+    impl Readable for ReadWrite {}
+    impl Writable for WriteOnly {}
+    impl Writable for ReadWrite {}
+
+    pub trait SystemRegister: Copy + From<u64> + Into<u64> {
+        /// One of `ReadOnly`, `WriteOnly` and `ReadWrite`
+        type Access;
+        const NAME: &'static str;
+    }
+
+    pub trait ReadableRegister: SystemRegister<Access: Readable> {
+        fn read() -> Self;
+    }
+
+    pub trait WritableRegister: SystemRegister<Access: Writable> {
+        /// Writes the register and waits for the write to take effect.
+        fn store(self);
+    }
+
+    pub trait ReadWriteRegister: ReadableRegister + WritableRegister {
+        /// Reads the register, updates the fields with `f` and writes
+        /// the result back. Returns the value written.
+        fn modify(f: impl FnOnce(Self) -> Self) -> Self {
+            let value = f(Self::read());
+            value.store();
+            value
+        }
+    }
+
+    impl<T: ReadableRegister + WritableRegister> ReadWriteRegister for T {}
+
+    macro_rules! impl_aarch64_register {
+        ($register_type:ident) => {
+            impl $crate::regs::access::Aarch64Register for $register_type {
+                fn load(&mut self) {
+                    *self = <Self as $crate::regs::access::ReadableRegister>::read();
+                }
+
+                fn name(&self) -> &'static str {
+                    <Self as $crate::regs::access::SystemRegister>::NAME
+                }
+
+                fn bits(&self) -> u64 {
+                    (*self).into()
+                }
+            }
+        };
+    }
+
+    macro_rules! impl_register_access {
+        ($register_type:ident, $register:ident $(, $ext:literal)?) => {
+            impl $crate::regs::access::SystemRegister for $register_type {
+                type Access = $crate::regs::access::ReadWrite;
+                const NAME: &'static str = stringify!($register);
+            }
+
+            impl $crate::regs::access::ReadableRegister for $register_type {
+                fn read() -> Self {
+                    let val: u64 = $crate::load_sys_reg!($register $(, $ext)?).into();
+                    Self::from(val)
+                }
+            }
+
+            impl $crate::regs::access::WritableRegister for $register_type {
+                fn store(self) {
+                    let val: u64 = self.into();
+                    $crate::store_sys_reg!($register, $($ext,)? val)
+                }
+            }
+
+            $crate::regs::access::impl_aarch64_register!($register_type);
+        };
+    }
+
+    macro_rules! impl_register_access_ro {
+        ($register_type:ident, $register:ident $(, $ext:literal)?) => {
+            impl $crate::regs::access::SystemRegister for $register_type {
+                type Access = $crate::regs::access::ReadOnly;
+                const NAME: &'static str = stringify!($register);
+            }
+
+            impl $crate::regs::access::ReadableRegister for $register_type {
+                fn read() -> Self {
+                    let val: u64 = $crate::load_sys_reg!($register $(, $ext)?).into();
+                    Self::from(val)
+                }
+            }
+
+            $crate::regs::access::impl_aarch64_register!($register_type);
+        };
+    }
+
+    /// The write-only registers cannot be loaded, so they are not `Aarch64Register`s.
+    macro_rules! impl_register_access_wo {
+        ($register_type:ident, $register:ident $(, $ext:literal)?) => {
+            impl $crate::regs::access::SystemRegister for $register_type {
+                type Access = $crate::regs::access::WriteOnly;
+                const NAME: &'static str = stringify!($register);
+            }
+
+            impl $crate::regs::access::WritableRegister for $register_type {
+                fn store(self) {
+                    let val: u64 = self.into();
+                    $crate::store_sys_reg!($register, $($ext,)? val)
+                }
+            }
+        };
+    }
+
+    pub(crate) use impl_aarch64_register;
+    pub(crate) use impl_register_access;
+    pub(crate) use impl_register_access_ro;
+    pub(crate) use impl_register_access_wo;
+
+    impl_register_access_ro!(MainIdEl1, MIDR_EL1);
+    impl_register_access_ro!(ProcessorFeatures0El1, ID_AA64PFR0_EL1);
+    impl_register_access_ro!(ProcessorFeatures1El1, ID_AA64PFR1_EL1);
+    impl_register_access_ro!(MmFeatures0El1, ID_AA64MMFR0_EL1);
+    impl_register_access_ro!(MmFeatures1El1, ID_AA64MMFR1_EL1);
+    impl_register_access_ro!(MmFeatures2El1, ID_AA64MMFR2_EL1);
+    impl_register_access_ro!(MmFeatures3El1, ID_AA64MMFR3_EL1);
+    impl_register_access_ro!(InstructionSetFeatures0El1, ID_AA64ISAR0_EL1);
+    impl_register_access_ro!(InstructionSetFeatures1El1, ID_AA64ISAR1_EL1);
+    impl_register_access_ro!(InstructionSetFeatures2El1, ID_AA64ISAR2_EL1);
+    impl_register_access_ro!(DebugFeatures0El1, ID_AA64DFR0_EL1);
+    impl_register_access_ro!(SveFeatures0El1, ID_AA64ZFR0_EL1, "sve");
+    impl_register_access_ro!(SmeFeatures0El1, ID_AA64SMFR0_EL1, "sme");
+
+    impl_register_access_ro!(CurrentEl, CurrentEL);
+    impl_register_access_ro!(RevisionIdEl1, REVIDR_EL1);
+    impl_register_access_ro!(MultiprocessorAffinityEl1, MPIDR_EL1);
+
+    impl_register_access!(SystemControlEl1, SCTLR_EL1);
+    impl_register_access!(VectorBaseEl1, VBAR_EL1);
+    impl_register_access!(ExceptionLinkEl1, ELR_EL1);
+    impl_register_access!(ExceptionSyndromeEl1, ESR_EL1);
+    impl_register_access!(SavedProgramStateEl1, SPSR_EL1);
+    impl_register_access!(FaultAddressEl1, FAR_EL1);
+    impl_register_access!(AuxFaultStatus0El1, AFSR0_EL1);
+    impl_register_access!(AuxFaultStatus1El1, AFSR1_EL1);
+    impl_register_access!(InterruptMask, DAIF);
+    impl_register_access!(ConditionFlags, NZCV);
+    impl_register_access!(ArchFeatureAccessControlEl1, CPACR_EL1);
+    impl_register_access!(StackPointerEl0, SP_EL0);
+    impl_register_access!(ThreadIdEl0, TPIDR_EL0);
+    impl_register_access!(ThreadIdEl1, TPIDR_EL1);
+    impl_register_access!(ContextIdEl1, CONTEXTIDR_EL1);
+    impl_register_access!(TranslationControlEl1, TCR_EL1);
+    impl_register_access!(MemoryAttributeIndirectionEl1, MAIR_EL1);
+
+    impl_register_access!(TranslationControl2El1, TCR2_EL1);
+    impl_register_access!(PermissionIndirectionEl1, PIR_EL1);
+    impl_register_access!(PermissionIndirectionE0El1, PIRE0_EL1);
+    impl_register_access!(PermissionOverlayEl1, POR_EL1);
+
+    impl_register_access!(TagControlEl1, GCR_EL1, "memtag");
+    impl_register_access!(RandomTagSeedEl1, RGSR_EL1, "memtag");
+    impl_register_access!(TagFaultStatusEl1, TFSR_EL1, "memtag");
+
+    impl_register_access_wo!(OsLockAccessEl1, OSLAR_EL1);
+
+    impl_register_access!(HypervisorConfigEl2, HCR_EL2);
+    impl_register_access!(SystemControlEl2, SCTLR_EL2);
+    impl_register_access!(VectorBaseEl2, VBAR_EL2);
+    impl_register_access!(ExceptionSyndromeEl2, ESR_EL2);
+    impl_register_access!(ExceptionLinkEl2, ELR_EL2);
+    impl_register_access!(SavedProgramStateEl2, SPSR_EL2);
+    impl_register_access!(FaultAddressEl2, FAR_EL2);
+    impl_register_access!(HypervisorIpaFaultAddressEl2, HPFAR_EL2);
+    impl_register_access!(TranslationControlEl2, TCR_EL2);
+    impl_register_access!(TranslationBase0El2, TTBR0_EL2);
+    impl_register_access!(MemoryAttributeIndirectionEl2, MAIR_EL2);
+    impl_register_access!(ArchFeatureTrapEl2, CPTR_EL2);
+    impl_register_access!(CounterHypControlEl2, CNTHCTL_EL2);
+    impl_register_access!(VirtualizationProcessorIdEl2, VPIDR_EL2);
+    impl_register_access!(VirtualizationMultiprocessorIdEl2, VMPIDR_EL2);
+
+    impl TranslationBase0El1 {
+        /// Reads TTBR0_EL1 as a 128-bit register (FEAT_D128), returns
+        /// the bits [127:64].
+        pub fn load128(&mut self) -> u64 {
+            #[cfg(target_arch = "aarch64")]
+            let native = || {
+                let (low, high): (u64, u64);
+                unsafe {
+                    core::arch::asm!(
+                        ".arch_extension d128",
+                        "mrrs x0, x1, TTBR0_EL1",
+                        out("x0") low,
+                        out("x1") high
+                    );
+                }
+                ((high as u128) << 64) | low as u128
+            };
+            #[cfg(not(target_arch = "aarch64"))]
+            let native = || unreachable!("no TTBR0_EL1 on the host");
+
+            let value = Backend::load128(Self::NAME, native);
+            *self = Self::from(value as u64);
+            (value >> 64) as u64
+        }
+
+        /// Writes TTBR0_EL1 as a 128-bit register (FEAT_D128), `high`
+        /// goes to the bits [127:64].
+        pub fn store128(&mut self, high: u64) {
+            let low: u64 = (*self).into();
+            #[cfg(target_arch = "aarch64")]
+            let native = |value: u128| unsafe {
+                core::arch::asm!(
+                    ".arch_extension d128",
+                    "msrr TTBR0_EL1, x0, x1",
+                    "dsb ishst; dsb ish; isb",
+                    in("x0") value as u64,
+                    in("x1") (value >> 64) as u64
+                );
+            };
+            #[cfg(not(target_arch = "aarch64"))]
+            let native = |_: u128| unreachable!("no TTBR0_EL1 on the host");
+
+            Backend::store128(Self::NAME, ((high as u128) << 64) | low as u128, native);
+        }
+    }
+
+    #[macro_export]
+    macro_rules! register {
+        ($reg:ident) => {
+            &mut $reg::new() as &mut dyn Aarch64Register
+        };
+    }
+}
+
+/// The layout of the registers as data, for composing and checking
+/// the values where the types are not at hand, e.g. from the command line.
+pub mod fields {
+    use super::*;
+    use crate::regs::access::SystemRegister;
+
+    /// The names of the values a field can take
+    pub trait FieldValues {
+        const VALUES: &'static [(&'static str, u64)];
+    }
+
+    /// The bits `lsb..lsb + width` of a register
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Field {
+        pub name: &'static str,
+        pub lsb: u32,
+        pub width: u32,
+        /// Empty for the numeric fields.
+        pub values: &'static [(&'static str, u64)],
+    }
+
+    impl Field {
+        pub const fn mask(&self) -> u64 {
+            (u64::MAX >> (64 - self.width)) << self.lsb
+        }
+
+        pub const fn get(&self, bits: u64) -> u64 {
+            (bits & self.mask()) >> self.lsb
+        }
+
+        /// `None` if the value does not fit.
+        pub const fn insert(&self, bits: u64, value: u64) -> Option<u64> {
+            if value > self.mask() >> self.lsb {
+                return None;
+            }
+            Some(bits & !self.mask() | value << self.lsb)
+        }
+
+        /// The value of a name from `values`.
+        pub fn value(&self, name: &str) -> Option<u64> {
+            self.values
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|&(_, value)| value)
+        }
+
+        /// The name of a value from `values`.
+        pub fn value_name(&self, value: u64) -> Option<&'static str> {
+            self.values
+                .iter()
+                .find(|&&(_, v)| v == value)
+                .map(|&(name, _)| name)
+        }
+    }
+
+    /// The fields of a register, named as the accessors of its type
+    pub trait RegisterFields: SystemRegister {
+        const FIELDS: &'static [Field];
+
+        /// The field of `bits` as the accessor of the type reads it, to
+        /// check `FIELDS` against the type.
+        fn read_field(bits: u64, name: &str) -> Option<u64>;
+
+        fn field(name: &str) -> Option<&'static Field> {
+            Self::FIELDS.iter().find(|f| f.name == name)
+        }
+    }
+
+    macro_rules! impl_field_values {
+        ($type:ident { $($variant:ident),+ $(,)? }) => {
+            impl $crate::regs::fields::FieldValues for $type {
+                const VALUES: &'static [(&'static str, u64)] =
+                    &[$((stringify!($variant), $type::$variant as u64)),+];
+            }
+        };
+    }
+
+    macro_rules! impl_register_fields {
+        (@values $values:ty) => {
+            <$values as $crate::regs::fields::FieldValues>::VALUES
+        };
+        (@values) => {
+            &[]
+        };
+        ($register_type:ident { $($field:ident: $lsb:literal $(..= $msb:literal)? $(as $values:ty)?),+ $(,)? }) => {
+            impl $crate::regs::fields::RegisterFields for $register_type {
+                const FIELDS: &'static [$crate::regs::fields::Field] = &[$(
+                    $crate::regs::fields::Field {
+                        name: stringify!($field),
+                        lsb: $lsb,
+                        width: 1 $(+ $msb - $lsb)?,
+                        values: impl_register_fields!(@values $($values)?),
+                    }
+                ),+];
+
+                fn read_field(bits: u64, name: &str) -> Option<u64> {
+                    let register = Self::from(bits);
+                    match name {
+                        $(stringify!($field) => Some(u64::from(register.$field())),)+
+                        _ => None,
+                    }
+                }
+            }
+        };
+    }
+
+    pub(crate) use impl_register_fields;
+
+    impl_field_values!(El { EL0, EL1, EL2, EL3 });
+    impl_field_values!(SavedProgramStateMode {
+        EL0t,
+        EL1t,
+        EL1h,
+        EL2t,
+        EL2h,
+        EL3t,
+        EL3h
+    });
+    impl_field_values!(TranslationGranule0 { _4KB, _64KB, _16KB });
+    impl_field_values!(TranslationGranule1 {
+        _Invalid,
+        _16KB,
+        _4KB,
+        _64KB
+    });
+    impl_field_values!(IntermPhysAddrSize {
+        _32_bits_4GB,
+        _36_bits_64GB,
+        _40_bits_1TB,
+        _42_bits_4TB,
+        _44_bits_16TB,
+        _48_bits_256TB,
+        _52_bits_4PB,
+        _56_bits_64PB,
+    });
+    impl_field_values!(MmfPaRange {
+        _32_bits_4GB,
+        _36_bits_64GB,
+        _40_bits_1TB,
+        _42_bits_4TB,
+        _44_bits_16TB,
+        _48_bits_256TB,
+        _52_bits_4PB,
+        _56_bits_64PB,
+    });
+    impl_field_values!(MmfAsidBits {
+        _8_bits_ASID,
+        _16_bits_ASID
+    });
+    impl_field_values!(MmfTGran4KB { Yes, Yes_52bit, No });
+    impl_field_values!(MmfTGran16KB { No, Yes, Yes_52bit });
+    impl_field_values!(MmfTGran64KB { Yes, No });
+    impl_field_values!(MmfTGran4KBStage2 {
+        AsStage1,
+        No,
+        Yes,
+        Yes_52bit
+    });
+    impl_field_values!(MmfTGran16KBStage2 {
+        AsStage1,
+        No,
+        Yes,
+        Yes_52bit
+    });
+    impl_field_values!(MmfTGran64KBStage2 { AsStage1, No, Yes });
+
+    impl_register_fields!(CurrentEl { el: 2..=3 as El });
+    impl_register_fields!(MainIdEl1 {
+        revision: 0..=3,
+        part_num: 4..=15,
+        architecture: 16..=19,
+        variant: 20..=23,
+        implementer: 24..=31,
+    });
+    impl_register_fields!(SavedProgramStateEl1 {
+        mode: 0..=3 as SavedProgramStateMode,
+        aarch32: 4,
+        f: 6,
+        i: 7,
+        a: 8,
+        d: 9,
+        btype: 10..=11,
+        il: 20,
+        ss: 21,
+    });
+    impl_register_fields!(ExceptionSyndromeEl1 {
+        iss: 0..=24,
+        il: 25,
+        ec: 26..=31,
+        iss2: 32..=55,
+    });
+    impl_register_fields!(MemoryAttributeIndirectionEl1 {
+        attr0: 0..=7,
+        attr1: 8..=15,
+        attr2: 16..=23,
+        attr3: 24..=31,
+        attr4: 32..=39,
+        attr5: 40..=47,
+        attr6: 48..=55,
+        attr7: 56..=63,
+    });
+    impl_register_fields!(TranslationControlEl1 {
+        t0sz: 0..=5,
+        epd0: 7,
+        irgn0: 8..=9,
+        orgn0: 10..=11,
+        sh0: 12..=13,
+        tg0: 14..=15 as TranslationGranule0,
+        t1sz: 16..=21,
+        a1: 22,
+        epd1: 23,
+        irgn1: 24..=25,
+        orgn1: 26..=27,
+        sh1: 28..=29,
+        tg1: 30..=31 as TranslationGranule1,
+        ips: 32..=34 as IntermPhysAddrSize,
+        a_s: 36,
+        tbi0: 37,
+        tbi1: 38,
+        ha: 39,
+        hd: 40,
+        hpd0: 41,
+        hpd1: 42,
+        hwu059: 43,
+        hwu060: 44,
+        hwu061: 45,
+        hwu062: 46,
+        hwu159: 47,
+        hwu160: 48,
+        hwu161: 49,
+        hwu162: 50,
+        tbid0: 51,
+        tbid1: 52,
+        nfd0: 53,
+        nfd1: 54,
+        e0pd0: 55,
+        e0pd1: 56,
+        tcma0: 57,
+        tcma1: 58,
+        ds: 59,
+        mtx0: 60,
+        mtx1: 61,
+    });
+    impl_register_fields!(SystemControlEl1 {
+        m: 0,
+        a: 1,
+        c: 2,
+        sa: 3,
+        sa0: 4,
+        cp15ben: 5,
+        n_aa: 6,
+        itd: 7,
+        sed: 8,
+        uma: 9,
+        en_rctx: 10,
+        eos: 11,
+        i: 12,
+        en_db: 13,
+        dze: 14,
+        uct: 15,
+        n_twi: 16,
+        n_twe: 18,
+        wxn: 19,
+        tscxt: 20,
+        iesb: 21,
+        eis: 22,
+        span: 23,
+        e0e: 24,
+        ee: 25,
+        uci: 26,
+        en_da: 27,
+        n_tlsmd: 28,
+        lsmaoe: 29,
+        en_ib: 30,
+        en_ia: 31,
+        cmow: 32,
+        msc_en: 33,
+        bt0: 35,
+        bt1: 36,
+        itfsb: 37,
+        tcf0: 38..=39,
+        tcf: 40..=41,
+        ata0: 42,
+        ata: 43,
+        dssbs: 44,
+        twed_en: 45,
+        twedel: 46..=49,
+        tmt0: 50,
+        tmt: 51,
+        tme0: 52,
+        tme: 53,
+        en_asr: 54,
+        en_as0: 55,
+        en_als: 56,
+        epan: 57,
+        tcso0: 58,
+        tcso: 59,
+        en_tp2: 60,
+        nmi: 61,
+        spintmask: 62,
+        tidcp: 63,
+    });
+    impl_register_fields!(MmFeatures0El1 {
+        pa_range: 0..=3 as MmfPaRange,
+        asid_bits: 4..=7 as MmfAsidBits,
+        big_end: 8..=11,
+        sns_mem: 12..=15,
+        big_end_el0: 16..=19,
+        t_gran16: 20..=23 as MmfTGran16KB,
+        t_gran64: 24..=27 as MmfTGran64KB,
+        t_gran4: 28..=31 as MmfTGran4KB,
+        t_gran16_2: 32..=35 as MmfTGran16KBStage2,
+        t_gran64_2: 36..=39 as MmfTGran64KBStage2,
+        t_gran4_2: 40..=43 as MmfTGran4KBStage2,
+        ex_s: 44..=47,
+        fgt: 56..=59,
+        ecv: 60..=63,
+    });
+}
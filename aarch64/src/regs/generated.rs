@@ -0,0 +1,672 @@
+// Generated by `tools/src/bin/regs-gen.rs` from `tools/aarchmrs/`, do not edit.
+
+use bitfield_struct::bitfield;
+
+use super::access::impl_register_access;
+use super::access::impl_register_access_ro;
+use super::fields::impl_register_fields;
+use super::id_field;
+
+/// Translation Table Base Register 0 (EL1)
+#[bitfield(u64)]
+pub struct TranslationBase0El1 {
+    pub cnp: bool,
+    #[bits(47)]
+    pub baddr: u64,
+    #[bits(16)]
+    pub asid: u64,
+}
+
+impl_register_access!(TranslationBase0El1, TTBR0_EL1);
+
+impl_register_fields!(TranslationBase0El1 {
+    cnp: 0,
+    baddr: 1..=47,
+    asid: 48..=63,
+});
+
+/// Translation Table Base Register 1 (EL1)
+#[bitfield(u64)]
+pub struct TranslationBase1El1 {
+    pub cnp: bool,
+    #[bits(47)]
+    pub baddr: u64,
+    #[bits(16)]
+    pub asid: u64,
+}
+
+impl_register_access!(TranslationBase1El1, TTBR1_EL1);
+
+impl_register_fields!(TranslationBase1El1 {
+    cnp: 0,
+    baddr: 1..=47,
+    asid: 48..=63,
+});
+
+id_field!(
+    /// ID_AA64MMFR4_EL1.EIESB
+    Mmf4Eiesb {
+        NotImplemented = 0b0000,
+        ToEl3 = 0b0001,
+        ToElx = 0b0010,
+        Any = 0b1111,
+    }
+);
+
+id_field!(
+    /// ID_AA64MMFR4_EL1.ASID2
+    Mmf4Asid2 {
+        NotImplemented = 0b0000,
+        Implemented = 0b0001,
+    }
+);
+
+id_field!(
+    /// ID_AA64MMFR4_EL1.HACDBS
+    Mmf4Hacdbs {
+        NotImplemented = 0b0000,
+        Implemented = 0b0001,
+    }
+);
+
+id_field!(
+    /// ID_AA64MMFR4_EL1.FGWTE3
+    Mmf4Fgwte3 {
+        NotImplemented = 0b0000,
+        Implemented = 0b0001,
+    }
+);
+
+id_field!(
+    /// ID_AA64MMFR4_EL1.NV_frac
+    Mmf4NvFrac {
+        NvAndNv2 = 0b0000,
+        Nv2Only = 0b0001,
+    }
+);
+
+id_field!(
+    /// ID_AA64MMFR4_EL1.E2H0
+    Mmf4E2h0 {
+        Implemented = 0b0000,
+        NotImplementedNv1 = 0b1110,
+        NotImplemented = 0b1111,
+    }
+);
+
+/// AArch64 Memory Model Feature Register 4
+#[bitfield(u64)]
+pub struct MmFeatures4El1 {
+    #[bits(4)]
+    _mbz0: u64,
+    #[bits(4)]
+    pub eiesb: Mmf4Eiesb,
+    #[bits(4)]
+    pub asid2: Mmf4Asid2,
+    #[bits(4)]
+    pub hacdbs: Mmf4Hacdbs,
+    #[bits(4)]
+    pub fgwte3: Mmf4Fgwte3,
+    #[bits(4)]
+    pub nv_frac: Mmf4NvFrac,
+    #[bits(4)]
+    pub e2h0: Mmf4E2h0,
+    #[bits(36)]
+    _mbz1: u64,
+}
+
+impl_register_access_ro!(MmFeatures4El1, ID_AA64MMFR4_EL1);
+
+impl_register_fields!(MmFeatures4El1 {
+    eiesb: 4..=7 as Mmf4Eiesb,
+    asid2: 8..=11 as Mmf4Asid2,
+    hacdbs: 12..=15 as Mmf4Hacdbs,
+    fgwte3: 16..=19 as Mmf4Fgwte3,
+    nv_frac: 20..=23 as Mmf4NvFrac,
+    e2h0: 24..=27 as Mmf4E2h0,
+});
+
+/// Performance Monitors Control Register
+#[bitfield(u64)]
+pub struct PerfMonControlEl0 {
+    pub e: bool,
+    pub p: bool,
+    pub c: bool,
+    pub d: bool,
+    pub x: bool,
+    pub dp: bool,
+    pub lc: bool,
+    pub lp: bool,
+    #[bits(3)]
+    _mbz0: u64,
+    #[bits(5)]
+    pub n: u64,
+    #[bits(8)]
+    pub idcode: u64,
+    #[bits(8)]
+    pub imp: u64,
+    #[bits(32)]
+    _mbz1: u64,
+}
+
+impl_register_access!(PerfMonControlEl0, PMCR_EL0);
+
+impl_register_fields!(PerfMonControlEl0 {
+    e: 0,
+    p: 1,
+    c: 2,
+    d: 3,
+    x: 4,
+    dp: 5,
+    lc: 6,
+    lp: 7,
+    n: 11..=15,
+    idcode: 16..=23,
+    imp: 24..=31,
+});
+
+/// Performance Monitors Count Enable Set Register
+#[bitfield(u64)]
+pub struct PerfMonCountEnableSetEl0 {
+    #[bits(31)]
+    pub p: u64,
+    pub c: bool,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonCountEnableSetEl0, PMCNTENSET_EL0);
+
+impl_register_fields!(PerfMonCountEnableSetEl0 {
+    p: 0..=30,
+    c: 31,
+});
+
+/// Performance Monitors Count Enable Clear Register
+#[bitfield(u64)]
+pub struct PerfMonCountEnableClearEl0 {
+    #[bits(31)]
+    pub p: u64,
+    pub c: bool,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonCountEnableClearEl0, PMCNTENCLR_EL0);
+
+impl_register_fields!(PerfMonCountEnableClearEl0 {
+    p: 0..=30,
+    c: 31,
+});
+
+/// Performance Monitors Overflow Flag Status Set Register
+#[bitfield(u64)]
+pub struct PerfMonOverflowSetEl0 {
+    #[bits(31)]
+    pub p: u64,
+    pub c: bool,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonOverflowSetEl0, PMOVSSET_EL0);
+
+impl_register_fields!(PerfMonOverflowSetEl0 {
+    p: 0..=30,
+    c: 31,
+});
+
+/// Performance Monitors Overflow Flag Status Clear Register
+#[bitfield(u64)]
+pub struct PerfMonOverflowClearEl0 {
+    #[bits(31)]
+    pub p: u64,
+    pub c: bool,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonOverflowClearEl0, PMOVSCLR_EL0);
+
+impl_register_fields!(PerfMonOverflowClearEl0 {
+    p: 0..=30,
+    c: 31,
+});
+
+/// Performance Monitors Interrupt Enable Set Register
+#[bitfield(u64)]
+pub struct PerfMonInterruptEnableSetEl1 {
+    #[bits(31)]
+    pub p: u64,
+    pub c: bool,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonInterruptEnableSetEl1, PMINTENSET_EL1);
+
+impl_register_fields!(PerfMonInterruptEnableSetEl1 {
+    p: 0..=30,
+    c: 31,
+});
+
+/// Performance Monitors Interrupt Enable Clear Register
+#[bitfield(u64)]
+pub struct PerfMonInterruptEnableClearEl1 {
+    #[bits(31)]
+    pub p: u64,
+    pub c: bool,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonInterruptEnableClearEl1, PMINTENCLR_EL1);
+
+impl_register_fields!(PerfMonInterruptEnableClearEl1 {
+    p: 0..=30,
+    c: 31,
+});
+
+/// Performance Monitors User Enable Register
+#[bitfield(u64)]
+pub struct PerfMonUserEnableEl0 {
+    pub en: bool,
+    pub sw: bool,
+    pub cr: bool,
+    pub er: bool,
+    #[bits(60)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PerfMonUserEnableEl0, PMUSERENR_EL0);
+
+impl_register_fields!(PerfMonUserEnableEl0 {
+    en: 0,
+    sw: 1,
+    cr: 2,
+    er: 3,
+});
+
+/// Performance Monitors Cycle Count Register
+#[bitfield(u64)]
+pub struct PerfMonCycleCountEl0 {
+    #[bits(64)]
+    pub ccnt: u64,
+}
+
+impl_register_access!(PerfMonCycleCountEl0, PMCCNTR_EL0);
+
+impl_register_fields!(PerfMonCycleCountEl0 {
+    ccnt: 0..=63,
+});
+
+/// Performance Monitors Cycle Count Filter Register
+#[bitfield(u64)]
+pub struct PerfMonCycleCountFilterEl0 {
+    #[bits(20)]
+    _mbz0: u64,
+    pub rlh: bool,
+    pub rlu: bool,
+    pub rlk: bool,
+    #[bits(1)]
+    _mbz1: u64,
+    pub sh: bool,
+    #[bits(1)]
+    _mbz2: u64,
+    pub m: bool,
+    pub nsh: bool,
+    pub nsu: bool,
+    pub nsk: bool,
+    pub u: bool,
+    pub p: bool,
+    #[bits(32)]
+    _mbz3: u64,
+}
+
+impl_register_access!(PerfMonCycleCountFilterEl0, PMCCFILTR_EL0);
+
+impl_register_fields!(PerfMonCycleCountFilterEl0 {
+    rlh: 20,
+    rlu: 21,
+    rlk: 22,
+    sh: 24,
+    m: 26,
+    nsh: 27,
+    nsu: 28,
+    nsk: 29,
+    u: 30,
+    p: 31,
+});
+
+/// Performance Monitors Event Type Registers
+#[bitfield(u64)]
+pub struct PerfMonEventTypeEl0 {
+    #[bits(16)]
+    pub evtcount: u64,
+    #[bits(11)]
+    _mbz0: u64,
+    pub nsh: bool,
+    pub nsu: bool,
+    pub nsk: bool,
+    pub u: bool,
+    pub p: bool,
+    #[bits(32)]
+    _mbz1: u64,
+}
+
+/// Monitor Debug System Control Register
+#[bitfield(u64)]
+pub struct MonitorDebugSystemControlEl1 {
+    pub ss: bool,
+    #[bits(5)]
+    _mbz0: u64,
+    pub err: bool,
+    #[bits(5)]
+    _mbz1: u64,
+    pub tdcc: bool,
+    pub kde: bool,
+    pub hde: bool,
+    pub mde: bool,
+    #[bits(3)]
+    _mbz2: u64,
+    pub sc2: bool,
+    #[bits(1)]
+    _mbz3: u64,
+    pub tda: bool,
+    #[bits(2)]
+    pub intdis: u64,
+    #[bits(2)]
+    _mbz4: u64,
+    pub txu: bool,
+    pub rxo: bool,
+    #[bits(1)]
+    _mbz5: u64,
+    pub txfull: bool,
+    pub rxfull: bool,
+    pub tfo: bool,
+    #[bits(32)]
+    _mbz6: u64,
+}
+
+impl_register_access!(MonitorDebugSystemControlEl1, MDSCR_EL1);
+
+impl_register_fields!(MonitorDebugSystemControlEl1 {
+    ss: 0,
+    err: 6,
+    tdcc: 12,
+    kde: 13,
+    hde: 14,
+    mde: 15,
+    sc2: 19,
+    tda: 21,
+    intdis: 22..=23,
+    txu: 26,
+    rxo: 27,
+    txfull: 29,
+    rxfull: 30,
+    tfo: 31,
+});
+
+/// Debug Breakpoint Control Registers
+#[bitfield(u64)]
+pub struct BreakpointControlEl1 {
+    pub e: bool,
+    #[bits(2)]
+    pub pmc: u64,
+    #[bits(2)]
+    _mbz0: u64,
+    #[bits(4)]
+    pub bas: u64,
+    #[bits(4)]
+    _mbz1: u64,
+    pub hmc: bool,
+    #[bits(2)]
+    pub ssc: u64,
+    #[bits(4)]
+    pub lbn: u64,
+    #[bits(4)]
+    pub bt: u64,
+    pub ssce: bool,
+    #[bits(39)]
+    _mbz2: u64,
+}
+
+/// Debug Watchpoint Control Registers
+#[bitfield(u64)]
+pub struct WatchpointControlEl1 {
+    pub e: bool,
+    #[bits(2)]
+    pub pac: u64,
+    #[bits(2)]
+    pub lsc: u64,
+    #[bits(8)]
+    pub bas: u64,
+    pub hmc: bool,
+    #[bits(2)]
+    pub ssc: u64,
+    #[bits(4)]
+    pub lbn: u64,
+    pub wt: bool,
+    #[bits(3)]
+    _mbz0: u64,
+    #[bits(5)]
+    pub mask: u64,
+    #[bits(35)]
+    _mbz1: u64,
+}
+
+/// SVE Control Register (EL1)
+#[bitfield(u64)]
+pub struct SveControlEl1 {
+    #[bits(4)]
+    pub len: u64,
+    #[bits(60)]
+    _mbz0: u64,
+}
+
+impl_register_access!(SveControlEl1, ZCR_EL1, "sve");
+
+impl_register_fields!(SveControlEl1 {
+    len: 0..=3,
+});
+
+/// SME Control Register (EL1)
+#[bitfield(u64)]
+pub struct SmeControlEl1 {
+    #[bits(4)]
+    pub len: u64,
+    #[bits(26)]
+    _mbz0: u64,
+    pub ezt0: bool,
+    pub fa64: bool,
+    #[bits(32)]
+    _mbz1: u64,
+}
+
+impl_register_access!(SmeControlEl1, SMCR_EL1, "sme");
+
+impl_register_fields!(SmeControlEl1 {
+    len: 0..=3,
+    ezt0: 30,
+    fa64: 31,
+});
+
+/// Streaming Vector Control Register
+#[bitfield(u64)]
+pub struct StreamingVectorControl {
+    pub sm: bool,
+    pub za: bool,
+    #[bits(62)]
+    _mbz0: u64,
+}
+
+impl_register_access!(StreamingVectorControl, SVCR, "sme");
+
+impl_register_fields!(StreamingVectorControl {
+    sm: 0,
+    za: 1,
+});
+
+/// Counter-timer Frequency Register
+#[bitfield(u64)]
+pub struct CounterFrequencyEl0 {
+    #[bits(32)]
+    pub clockfreq: u64,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access_ro!(CounterFrequencyEl0, CNTFRQ_EL0);
+
+impl_register_fields!(CounterFrequencyEl0 {
+    clockfreq: 0..=31,
+});
+
+/// Counter-timer Virtual Count Register
+#[bitfield(u64)]
+pub struct VirtualCountEl0 {
+    #[bits(64)]
+    pub virtualcount: u64,
+}
+
+impl_register_access_ro!(VirtualCountEl0, CNTVCT_EL0);
+
+impl_register_fields!(VirtualCountEl0 {
+    virtualcount: 0..=63,
+});
+
+/// Counter-timer Physical Count Register
+#[bitfield(u64)]
+pub struct PhysicalCountEl0 {
+    #[bits(64)]
+    pub physicalcount: u64,
+}
+
+impl_register_access_ro!(PhysicalCountEl0, CNTPCT_EL0);
+
+impl_register_fields!(PhysicalCountEl0 {
+    physicalcount: 0..=63,
+});
+
+/// Counter-timer Virtual Timer Control Register
+#[bitfield(u64)]
+pub struct VirtualTimerControlEl0 {
+    pub enable: bool,
+    pub imask: bool,
+    pub istatus: bool,
+    #[bits(61)]
+    _mbz0: u64,
+}
+
+impl_register_access!(VirtualTimerControlEl0, CNTV_CTL_EL0);
+
+impl_register_fields!(VirtualTimerControlEl0 {
+    enable: 0,
+    imask: 1,
+    istatus: 2,
+});
+
+/// Counter-timer Virtual Timer CompareValue Register
+#[bitfield(u64)]
+pub struct VirtualTimerCompareEl0 {
+    #[bits(64)]
+    pub comparevalue: u64,
+}
+
+impl_register_access!(VirtualTimerCompareEl0, CNTV_CVAL_EL0);
+
+impl_register_fields!(VirtualTimerCompareEl0 {
+    comparevalue: 0..=63,
+});
+
+/// Counter-timer Virtual Timer TimerValue Register
+#[bitfield(u64)]
+pub struct VirtualTimerValueEl0 {
+    #[bits(32)]
+    pub timervalue: u64,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(VirtualTimerValueEl0, CNTV_TVAL_EL0);
+
+impl_register_fields!(VirtualTimerValueEl0 {
+    timervalue: 0..=31,
+});
+
+/// Counter-timer Physical Timer Control Register
+#[bitfield(u64)]
+pub struct PhysicalTimerControlEl0 {
+    pub enable: bool,
+    pub imask: bool,
+    pub istatus: bool,
+    #[bits(61)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PhysicalTimerControlEl0, CNTP_CTL_EL0);
+
+impl_register_fields!(PhysicalTimerControlEl0 {
+    enable: 0,
+    imask: 1,
+    istatus: 2,
+});
+
+/// Counter-timer Physical Timer CompareValue Register
+#[bitfield(u64)]
+pub struct PhysicalTimerCompareEl0 {
+    #[bits(64)]
+    pub comparevalue: u64,
+}
+
+impl_register_access!(PhysicalTimerCompareEl0, CNTP_CVAL_EL0);
+
+impl_register_fields!(PhysicalTimerCompareEl0 {
+    comparevalue: 0..=63,
+});
+
+/// Counter-timer Physical Timer TimerValue Register
+#[bitfield(u64)]
+pub struct PhysicalTimerValueEl0 {
+    #[bits(32)]
+    pub timervalue: u64,
+    #[bits(32)]
+    _mbz0: u64,
+}
+
+impl_register_access!(PhysicalTimerValueEl0, CNTP_TVAL_EL0);
+
+impl_register_fields!(PhysicalTimerValueEl0 {
+    timervalue: 0..=31,
+});
+
+/// Counter-timer Kernel Control Register
+#[bitfield(u64)]
+pub struct CounterKernelControlEl1 {
+    pub el0pcten: bool,
+    pub el0vcten: bool,
+    pub evnten: bool,
+    pub evntdir: bool,
+    #[bits(4)]
+    pub evnti: u64,
+    pub el0vten: bool,
+    pub el0pten: bool,
+    #[bits(7)]
+    _mbz0: u64,
+    pub evntis: bool,
+    #[bits(46)]
+    _mbz1: u64,
+}
+
+impl_register_access!(CounterKernelControlEl1, CNTKCTL_EL1);
+
+impl_register_fields!(CounterKernelControlEl1 {
+    el0pcten: 0,
+    el0vcten: 1,
+    evnten: 2,
+    evntdir: 3,
+    evnti: 4..=7,
+    el0vten: 8,
+    el0pten: 9,
+    evntis: 17,
+});
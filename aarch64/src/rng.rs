@@ -0,0 +1,141 @@
+//! Random numbers
+//!
+//! `RNDR` and `RNDRRS` of FEAT_RNG, the latter reseeding the generator
+//! before the read. Both may fail transiently, setting `NZCV` to `0b0100`
+//! and returning 0, and are retried a few times then. Without FEAT_RNG,
+//! or when the retries run out, a SplitMix64 generator seeded from
+//! `CNTVCT_EL0` takes over: good for the tests and the layout
+//! randomization, not for the secrets.
+
+use crate::regs::access::ReadableRegister;
+use crate::regs::InstructionSetFeatures0El1;
+
+/// The reads of `RNDR` or `RNDRRS` before falling back.
+const RETRIES: usize = 10;
+
+/// Where the numbers come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Rndr,
+    /// SplitMix64 seeded from the virtual count
+    Counter,
+}
+
+/// Reads `RNDR` or `RNDRRS`, `None` on failure.
+#[cfg(all(target_arch = "aarch64", not(test)))]
+fn read(reseed: bool) -> Option<u64> {
+    let value: u64;
+    let ok: u64;
+    // SAFETY: only reads the system registers, and FEAT_RNG is there.
+    unsafe {
+        if reseed {
+            core::arch::asm!(
+                ".arch_extension rng",
+                "mrs {value}, RNDRRS",
+                "cset {ok}, ne",
+                value = out(reg) value,
+                ok = out(reg) ok,
+                options(nomem, nostack)
+            );
+        } else {
+            core::arch::asm!(
+                ".arch_extension rng",
+                "mrs {value}, RNDR",
+                "cset {ok}, ne",
+                value = out(reg) value,
+                ok = out(reg) ok,
+                options(nomem, nostack)
+            );
+        }
+    }
+    (ok != 0).then_some(value)
+}
+
+/// The simulated registers fail while not set.
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+fn read(reseed: bool) -> Option<u64> {
+    crate::regs::access::Simulated::get(if reseed { "RNDRRS" } else { "RNDR" })
+}
+
+/// The random number generator of the current CPU
+#[derive(Debug, Clone)]
+pub struct Rng {
+    source: Source,
+    /// The SplitMix64 state
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rng {
+    /// Detects FEAT_RNG from `ID_AA64ISAR0_EL1.RNDR`, and seeds the
+    /// fallback generator.
+    pub fn new() -> Self {
+        let source = if u64::from(InstructionSetFeatures0El1::read().rndr()) >= 1 {
+            Source::Rndr
+        } else {
+            Source::Counter
+        };
+        Self {
+            source,
+            state: crate::load_sys_reg!(CNTVCT_EL0),
+        }
+    }
+
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    fn split_mix(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn read_or_fallback(&mut self, reseed: bool) -> u64 {
+        if self.source == Source::Rndr {
+            for _ in 0..RETRIES {
+                if let Some(value) = read(reseed) {
+                    return value;
+                }
+            }
+        }
+        self.split_mix()
+    }
+
+    /// From `RNDR`
+    pub fn next_u64(&mut self) -> u64 {
+        self.read_or_fallback(false)
+    }
+
+    /// From `RNDRRS`, slower as the generator is reseeded first.
+    pub fn next_u64_reseeded(&mut self) -> u64 {
+        self.read_or_fallback(true)
+    }
+
+    /// A number in `0..bound`, `bound` must not be 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "empty range");
+        // Rejects the low end of the range to avoid the modulo bias.
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u64();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+
+    pub fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let value = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&value[..chunk.len()]);
+        }
+    }
+}
@@ -4,6 +4,7 @@
 //! for futher details.
 
 use core::arch::asm;
+use core::ffi::CStr;
 use core::hint::unreachable_unchecked;
 
 macro_rules! host_trap {
@@ -56,6 +57,19 @@ impl Semihosting {
         }
     }
 
+    /// Opens a file on the host, the path is relative to the working
+    /// directory of the debugger or of QEMU.
+    pub fn open(&self, path: &CStr, mode: OpenMode) -> Option<HostFile> {
+        const SYS_OPEN: u32 = 0x01;
+        let data = [
+            path.as_ptr() as u64,
+            mode as u64,
+            path.to_bytes().len() as u64,
+        ];
+        let handle = unsafe { semi_call(SYS_OPEN, data.as_ptr()) };
+        (handle as i64 != -1).then_some(HostFile { handle })
+    }
+
     pub fn write_hex(&self, h: u64) {
         let mut hs = [0_u16; 11];
         hs[0] = u16::from_le_bytes([b'0', b'x']);
@@ -94,3 +108,44 @@ impl core::fmt::Write for Semihosting {
         Ok(())
     }
 }
+
+/// The `fopen()` modes of `SYS_OPEN`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    Read = 0,
+    Write = 4,
+    Append = 8,
+}
+
+/// A file on the host, closed when dropped.
+pub struct HostFile {
+    handle: u64,
+}
+
+impl HostFile {
+    /// Returns the number of the bytes not written.
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        const SYS_WRITE: u32 = 0x05;
+        let args = [self.handle, data.as_ptr() as u64, data.len() as u64];
+        unsafe { semi_call(SYS_WRITE, args.as_ptr()) as usize }
+    }
+}
+
+impl Drop for HostFile {
+    fn drop(&mut self) {
+        const SYS_CLOSE: u32 = 0x02;
+        let args = [self.handle];
+        unsafe {
+            semi_call(SYS_CLOSE, args.as_ptr());
+        }
+    }
+}
+
+impl core::fmt::Write for HostFile {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        match self.write(s.as_bytes()) {
+            0 => Ok(()),
+            _ => Err(core::fmt::Error),
+        }
+    }
+}
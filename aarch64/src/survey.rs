@@ -0,0 +1,180 @@
+//! Register survey records
+//!
+//! One JSON object per line for each register, with the fields decoded
+//! the same way `{:x?}` prints them:
+//!
+//! ```text
+//! {"stage":"boot","register":"MIDR_EL1","bits":"0x000000410fd083","fields":{"revision":"3",...}}
+//! ```
+//!
+//! The records from several environments are merged into the comparison
+//! tables of the README by `tools/`.
+
+use core::fmt::Write;
+
+use crate::regs::access::Aarch64Register;
+
+/// Writes the record of a loaded register, `stage` tells apart the
+/// records of the same register taken at different points of the boot.
+pub fn write_record(
+    out: &mut dyn Write,
+    stage: &str,
+    reg: &dyn Aarch64Register,
+) -> core::fmt::Result {
+    out.write_str("{\"stage\":\"")?;
+    JsonString(out).write_str(stage)?;
+    out.write_str("\",\"register\":\"")?;
+    JsonString(out).write_str(reg.name())?;
+    write!(out, "\",\"bits\":\"{:#016x}\",\"fields\":{{", reg.bits())?;
+
+    let mut fields = DebugFields::new(out);
+    write!(fields, "{reg:x?}")?;
+    fields.finish()?;
+
+    out.write_str("}}\n")
+}
+
+/// Escapes the string contents.
+struct JsonString<'a>(&'a mut dyn Write);
+
+impl Write for JsonString<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                c if (c as u32) < 0x20 => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The type name before the fields.
+    Name,
+    /// A field name of `Name { field: value, .. }`.
+    Key,
+    /// A field value, or the only value of `Name(value)`.
+    Value,
+    Done,
+}
+
+/// Turns the `Debug` output of a struct into the members of a JSON
+/// object, `"field":"value"` for each field. The value of a tuple struct
+/// is named `value`. The values are kept as printed, so the nested
+/// structs and arrays are one string.
+struct DebugFields<'a> {
+    out: &'a mut dyn Write,
+    state: State,
+    /// The first character of the key or the value is written.
+    started: bool,
+    /// The brackets open in the value.
+    depth: usize,
+    /// The spaces not written yet, dropped at the end of a value.
+    spaces: usize,
+    /// A field is written already, the next needs a comma.
+    separator: bool,
+    tuple: bool,
+}
+
+impl<'a> DebugFields<'a> {
+    fn new(out: &'a mut dyn Write) -> Self {
+        Self {
+            out,
+            state: State::Name,
+            started: false,
+            depth: 0,
+            spaces: 0,
+            separator: false,
+            tuple: false,
+        }
+    }
+
+    fn begin_field(&mut self) -> core::fmt::Result {
+        if self.separator {
+            self.out.write_char(',')?;
+        }
+        self.separator = true;
+        self.out.write_char('"')
+    }
+
+    fn begin_value(&mut self) -> core::fmt::Result {
+        self.out.write_str("\":\"")?;
+        self.state = State::Value;
+        self.started = false;
+        self.depth = 0;
+        Ok(())
+    }
+
+    fn end_value(&mut self, next: State) -> core::fmt::Result {
+        self.spaces = 0;
+        self.state = next;
+        self.started = false;
+        self.out.write_char('"')
+    }
+
+    fn push(&mut self, c: char) -> core::fmt::Result {
+        for _ in 0..core::mem::take(&mut self.spaces) {
+            self.out.write_char(' ')?;
+        }
+        self.started = true;
+        JsonString(self.out).write_char(c)
+    }
+
+    /// Closes the value if the output ended in the middle of it.
+    fn finish(mut self) -> core::fmt::Result {
+        match self.state {
+            State::Value => self.end_value(State::Done),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Write for DebugFields<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            match self.state {
+                State::Name => match c {
+                    '{' => self.state = State::Key,
+                    '(' => {
+                        self.begin_field()?;
+                        self.out.write_str("value")?;
+                        self.tuple = true;
+                        self.begin_value()?;
+                    }
+                    _ => {}
+                },
+                State::Key => match c {
+                    c if c.is_whitespace() => {}
+                    '}' => self.state = State::Done,
+                    ':' => self.begin_value()?,
+                    c => {
+                        if !self.started {
+                            self.begin_field()?;
+                        }
+                        self.push(c)?;
+                    }
+                },
+                State::Value => match c {
+                    c if c.is_whitespace() => self.spaces += self.started as usize,
+                    '(' | '[' | '{' => {
+                        self.depth += 1;
+                        self.push(c)?;
+                    }
+                    ')' | ']' | '}' if self.depth > 0 => {
+                        self.depth -= 1;
+                        self.push(c)?;
+                    }
+                    ')' | '}' => self.end_value(State::Done)?,
+                    ',' if self.depth == 0 && !self.tuple => self.end_value(State::Key)?,
+                    c => self.push(c)?,
+                },
+                State::Done => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+//! Scalable Vector and Scalable Matrix Extensions
+//!
+//! The vector length (VL) of SVE and the streaming vector length (SVL) of
+//! SME are chosen by the `LEN` field of `ZCR_EL1` and `SMCR_EL1` as
+//! `(LEN + 1) * 128` bits. An implementation supports some of the lengths
+//! and picks the largest supported one not above the requested, so the
+//! lengths are found by requesting each of them.
+//!
+//! The context routines are for the context switch paths: the low 128 bits
+//! of the Z registers are the V registers the compiler uses.
+
+use crate::features::CpuFeatures;
+use crate::features::Feature;
+use crate::regs::access::ReadWriteRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::ArchFeatureAccessControlEl1;
+use crate::regs::CpacrTrap;
+use crate::regs::SmeControlEl1;
+use crate::regs::SveControlEl1;
+
+/// The largest VL and SVL in bytes, 2048 bits.
+pub const MAX_VL: usize = 256;
+
+/// The `LEN` values, the lengths in 128-bit quadwords minus one.
+const MAX_LEN: u64 = 15;
+
+/// The supported vector lengths, bit `n` stands for `(n + 1) * 16` bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VectorLengths(u16);
+
+impl VectorLengths {
+    /// Requests each length with `set_len`, which returns the length in
+    /// bytes the implementation chose.
+    pub fn probe(mut set_len: impl FnMut(u64) -> usize) -> Self {
+        let mut lengths = 0;
+        for len in 0..=MAX_LEN {
+            let vl = set_len(len);
+            assert!(
+                vl % 16 == 0 && (16..=MAX_VL).contains(&vl),
+                "invalid vector length {vl}"
+            );
+            lengths |= 1 << (vl / 16 - 1);
+        }
+        Self(lengths)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn max(&self) -> Option<usize> {
+        self.iter().last()
+    }
+
+    /// The lengths in bytes, from the smallest.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..16)
+            .filter(|n| self.0 & (1 << n) != 0)
+            .map(|n| (n + 1) * 16)
+    }
+}
+
+impl core::fmt::Display for VectorLengths {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (index, vl) in self.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", vl * 8)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stops trapping the SVE instructions at EL1 and EL0, and selects the
+/// largest VL. Nothing happens without SVE.
+pub fn enable_sve(features: &CpuFeatures) -> bool {
+    if !features.has(Feature::Sve) {
+        return false;
+    }
+    ArchFeatureAccessControlEl1::modify(|cpacr| {
+        cpacr
+            .with_fpen(CpacrTrap::NoTrap)
+            .with_zen(CpacrTrap::NoTrap)
+    });
+    SveControlEl1::new().with_len(MAX_LEN).store();
+    true
+}
+
+/// Stops trapping the SME instructions at EL1 and EL0, selects the largest
+/// SVL and the full A64 instruction set in the streaming mode where
+/// implemented. Nothing happens without SME.
+pub fn enable_sme(features: &CpuFeatures) -> bool {
+    if !features.has(Feature::Sme) {
+        return false;
+    }
+    ArchFeatureAccessControlEl1::modify(|cpacr| {
+        cpacr
+            .with_fpen(CpacrTrap::NoTrap)
+            .with_smen(CpacrTrap::NoTrap)
+    });
+    SmeControlEl1::new()
+        .with_len(MAX_LEN)
+        .with_fa64(features.smfr0.fa64())
+        .with_ezt0(features.has(Feature::Sme2))
+        .store();
+    true
+}
+
+/// The Z and P registers and FFR, laid out for the VL at the save
+#[derive(Clone)]
+#[repr(C, align(16))]
+pub struct SveContext {
+    /// Z0 to Z31, VL bytes each.
+    z: [u8; 32 * MAX_VL],
+    /// P0 to P15 and FFR, VL / 8 bytes each.
+    p: [u8; 17 * MAX_VL / 8],
+    vl: usize,
+}
+
+impl Default for SveContext {
+    fn default() -> Self {
+        Self {
+            z: [0; 32 * MAX_VL],
+            p: [0; 17 * MAX_VL / 8],
+            vl: 0,
+        }
+    }
+}
+
+impl SveContext {
+    /// The VL the context was saved with, 0 if never saved.
+    pub fn vl(&self) -> usize {
+        self.vl
+    }
+
+    pub fn z(&self, index: usize) -> &[u8] {
+        &self.z[index * self.vl..(index + 1) * self.vl]
+    }
+
+    /// P0 to P15, and FFR as 16.
+    pub fn p(&self, index: usize) -> &[u8] {
+        let size = self.vl / 8;
+        &self.p[index * size..(index + 1) * size]
+    }
+}
+
+/// The ZA array, SVL by SVL bytes
+#[derive(Clone)]
+#[repr(C, align(16))]
+pub struct ZaContext {
+    za: [u8; MAX_VL * MAX_VL],
+    svl: usize,
+    /// `PSTATE.ZA` was set, the array is saved only then.
+    active: bool,
+}
+
+impl Default for ZaContext {
+    fn default() -> Self {
+        Self {
+            za: [0; MAX_VL * MAX_VL],
+            svl: 0,
+            active: false,
+        }
+    }
+}
+
+impl ZaContext {
+    pub fn svl(&self) -> usize {
+        self.svl
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The horizontal slice `index` of ZA.
+    pub fn row(&self, index: usize) -> &[u8] {
+        &self.za[index * self.svl..(index + 1) * self.svl]
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod native {
+    use super::SveContext;
+    use super::VectorLengths;
+    use super::ZaContext;
+    use super::MAX_LEN;
+    use super::MAX_VL;
+    use crate::regs::access::ReadableRegister;
+    use crate::regs::access::WritableRegister;
+    use crate::regs::SmeControlEl1;
+    use crate::regs::StreamingVectorControl;
+    use crate::regs::SveControlEl1;
+
+    // The `mul vl` offsets of STR and LDR are in the units of the register
+    // size, VL for Z and VL / 8 for P.
+    core::arch::global_asm!(
+        ".arch_extension sve",
+        ".arch_extension sme",
+        ".pushsection .text.aarch64_sve_save, \"ax\", %progbits",
+        ".balign 4",
+        ".global aarch64_sve_save",
+        "aarch64_sve_save:",
+        "hint #34", // BTI C
+        ".irp n,0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31",
+        "str z\\n, [x0, #\\n, mul vl]",
+        ".endr",
+        ".irp n,0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15",
+        "str p\\n, [x1, #\\n, mul vl]",
+        ".endr",
+        "rdffr p0.b",
+        "str p0, [x1, #16, mul vl]",
+        "ldr p0, [x1]",
+        "ret",
+        ".popsection",
+        ".pushsection .text.aarch64_sve_restore, \"ax\", %progbits",
+        ".balign 4",
+        ".global aarch64_sve_restore",
+        "aarch64_sve_restore:",
+        "hint #34", // BTI C
+        "ldr p0, [x1, #16, mul vl]",
+        "wrffr p0.b",
+        ".irp n,0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15",
+        "ldr p\\n, [x1, #\\n, mul vl]",
+        ".endr",
+        ".irp n,0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31",
+        "ldr z\\n, [x0, #\\n, mul vl]",
+        ".endr",
+        "ret",
+        ".popsection",
+        ".pushsection .text.aarch64_za_save, \"ax\", %progbits",
+        ".balign 4",
+        ".global aarch64_za_save",
+        "aarch64_za_save:",
+        "hint #34", // BTI C
+        "rdsvl x2, #1",
+        "mov w12, wzr",
+        "1:",
+        "str za[w12, 0], [x0]",
+        "addsvl x0, x0, #1",
+        "add w12, w12, #1",
+        "cmp w12, w2",
+        "b.lo 1b",
+        "ret",
+        ".popsection",
+        ".pushsection .text.aarch64_za_restore, \"ax\", %progbits",
+        ".balign 4",
+        ".global aarch64_za_restore",
+        "aarch64_za_restore:",
+        "hint #34", // BTI C
+        "rdsvl x2, #1",
+        "mov w12, wzr",
+        "1:",
+        "ldr za[w12, 0], [x0]",
+        "addsvl x0, x0, #1",
+        "add w12, w12, #1",
+        "cmp w12, w2",
+        "b.lo 1b",
+        "ret",
+        ".popsection",
+    );
+
+    extern "C" {
+        fn aarch64_sve_save(z: *mut u8, p: *mut u8);
+        fn aarch64_sve_restore(z: *const u8, p: *const u8);
+        fn aarch64_za_save(za: *mut u8);
+        fn aarch64_za_restore(za: *const u8);
+    }
+
+    /// The current VL in bytes
+    pub fn vl() -> usize {
+        let vl: usize;
+        // SAFETY: SVE is enabled by the caller.
+        unsafe {
+            core::arch::asm!(
+                ".arch_extension sve",
+                "rdvl {}, #1",
+                out(reg) vl,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        vl
+    }
+
+    /// The current SVL in bytes
+    pub fn svl() -> usize {
+        let svl: usize;
+        // SAFETY: SME is enabled by the caller.
+        unsafe {
+            core::arch::asm!(
+                ".arch_extension sme",
+                "rdsvl {}, #1",
+                out(reg) svl,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        svl
+    }
+
+    /// The supported VLs, the largest one is selected afterwards. Needs
+    /// `enable_sve`.
+    pub fn sve_vector_lengths() -> VectorLengths {
+        let lengths = VectorLengths::probe(|len| {
+            SveControlEl1::new().with_len(len).store();
+            vl()
+        });
+        SveControlEl1::new().with_len(MAX_LEN).store();
+        lengths
+    }
+
+    /// The supported SVLs, the largest one is selected afterwards. Needs
+    /// `enable_sme`.
+    pub fn sme_vector_lengths() -> VectorLengths {
+        let smcr = SmeControlEl1::read();
+        let lengths = VectorLengths::probe(|len| {
+            smcr.with_len(len).store();
+            svl()
+        });
+        smcr.with_len(MAX_LEN).store();
+        lengths
+    }
+
+    /// Saves Z, P and FFR, outside the streaming mode.
+    pub fn save_sve(context: &mut SveContext) {
+        assert!(
+            !StreamingVectorControl::read().sm(),
+            "in the streaming mode"
+        );
+        context.vl = vl();
+        debug_assert!(context.vl <= MAX_VL);
+        // SAFETY: the buffers fit the registers of up to MAX_VL bytes.
+        unsafe { aarch64_sve_save(context.z.as_mut_ptr(), context.p.as_mut_ptr()) };
+    }
+
+    /// Restores Z, P and FFR saved with the current VL.
+    ///
+    /// # Safety
+    ///
+    /// The V registers, the low 128 bits of Z, change under the compiler,
+    /// so the caller must not hold values in them: this is for the context
+    /// switch code.
+    pub unsafe fn restore_sve(context: &SveContext) {
+        assert_eq!(context.vl, vl(), "saved with another VL");
+        assert!(
+            !StreamingVectorControl::read().sm(),
+            "in the streaming mode"
+        );
+        // SAFETY: the layout is for the current VL, the caller takes care
+        // of the V registers.
+        unsafe { aarch64_sve_restore(context.z.as_ptr(), context.p.as_ptr()) };
+    }
+
+    /// Saves ZA if `PSTATE.ZA` is set.
+    pub fn save_za(context: &mut ZaContext) {
+        context.svl = svl();
+        context.active = StreamingVectorControl::read().za();
+        if context.active {
+            // SAFETY: the buffer fits ZA of up to MAX_VL by MAX_VL bytes.
+            unsafe { aarch64_za_save(context.za.as_mut_ptr()) };
+        }
+    }
+
+    /// Restores ZA, setting `PSTATE.ZA` as it was at the save.
+    pub fn restore_za(context: &ZaContext) {
+        assert_eq!(context.svl, svl(), "saved with another SVL");
+        let svcr = StreamingVectorControl::read();
+        svcr.with_za(context.active).store();
+        if context.active {
+            // SAFETY: the layout is for the current SVL.
+            unsafe { aarch64_za_restore(context.za.as_ptr()) };
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+pub use native::*;
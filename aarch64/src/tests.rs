@@ -1,24 +1,150 @@
 #![cfg(test)]
 
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+use crate::debug::Debug;
+use crate::debug::DebugError;
+use crate::debug::DebugEvent;
+use crate::debug::Resume;
+use crate::debug::WatchAccess;
+use crate::fdt::Fdt;
+use crate::fdt::FdtError;
+use crate::features::CpuFeatures;
+use crate::features::Feature;
+use crate::gic;
+use crate::gic::Affinity;
+use crate::gic::Gic;
+use crate::gic::GicVersion;
+use crate::gic::InterruptGroup;
+use crate::gic::SgiGroup;
+use crate::gic::SpiRoute;
+use crate::gic::Trigger;
+use crate::gic::GICD_ICENABLER_OFFSET;
+use crate::gic::GICD_ICFGR_OFFSET;
+use crate::gic::GICD_IGROUPR_OFFSET;
+use crate::gic::GICD_IGRPMODR_OFFSET;
+use crate::gic::GICD_IPRIORITYR_OFFSET;
+use crate::gic::GICD_IROUTER_OFFSET;
+use crate::gic::GICD_ISENABLER_OFFSET;
+use crate::gic::GICD_ISPENDR_OFFSET;
+use crate::gic::GICD_PIDR2_OFFSET;
+use crate::gic::GICD_TYPER_OFFSET;
+use crate::gic::GICR_FRAME_SIZE;
+use crate::gic::GICR_ICENABLER0_OFFSET;
+use crate::gic::GICR_ISENABLER0_OFFSET;
+use crate::gic::GICR_PIDR2_OFFSET;
+use crate::gic::GICR_TYPER_OFFSET;
+use crate::ipi::IpiError;
+use crate::ipi::Mailbox;
+use crate::irq::Dispatcher;
+use crate::irq::IrqError;
+use crate::memmap::MemoryMap;
+use crate::memmap::MemoryMapError;
+use crate::memmap::Region;
+use crate::memmap::RegionKind;
+use crate::memmap::Reservation;
+use crate::mmu;
+use crate::mmu::DescriptorFormat;
+use crate::mmu::PageBlockEntry;
 use crate::mmu::PageMapError;
 use crate::mmu::PageSize;
 use crate::mmu::PageTableSpace;
+use crate::mmu::PermissionModel;
+use crate::mmu::TranslationConfig;
 use crate::mmu::VirtualAddress;
+use crate::mte;
+use crate::pauth;
+use crate::pauth::Keys;
+use crate::pauth::Protection;
+use crate::pmu::Event;
+use crate::pmu::Pmu;
+use crate::pmu::PmuError;
+use crate::regs::access::ReadOnly;
+use crate::regs::access::ReadWrite;
+use crate::regs::access::ReadableRegister;
+use crate::regs::access::Simulated;
+use crate::regs::access::SystemRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::access::WriteOnly;
+use crate::regs::fields::RegisterFields;
+use crate::regs::ArchFeatureAccessControlEl1;
+use crate::regs::ArchFeatureTrapEl2;
+use crate::regs::CachePolicy;
+use crate::regs::Cacheability;
+use crate::regs::CpacrTrap;
+use crate::regs::CurrentEl;
+use crate::regs::DeviceMemory;
+use crate::regs::ExceptionClass;
+use crate::regs::ExceptionFrame;
+use crate::regs::ExceptionSource;
+use crate::regs::ExceptionSyndromeEl1;
+use crate::regs::ExceptionSyndromeEl2;
+use crate::regs::FaultStatusCode;
+use crate::regs::HypervisorConfigEl2;
+use crate::regs::HypervisorIpaFaultAddressEl2;
+use crate::regs::IdFeature;
+use crate::regs::InstructionSetFeatures0El1;
+use crate::regs::InstructionSetFeatures1El1;
+use crate::regs::IntermPhysAddrSize;
+use crate::regs::IsaAtomic;
+use crate::regs::IsaPAuth;
+use crate::regs::IsaSha2;
+use crate::regs::MainIdEl1;
+use crate::regs::MairBuilder;
+use crate::regs::MairError;
+use crate::regs::MairIndex;
 use crate::regs::MemoryAttributeEl1;
 use crate::regs::MemoryAttributeIndirectionEl1;
+use crate::regs::MmFeatures0El1;
+use crate::regs::MmfPaRange;
+use crate::regs::MmfTGran4KB;
+use crate::regs::MultiprocessorAffinityEl1;
+use crate::regs::OsLockAccessEl1;
+use crate::regs::PagePermission;
+use crate::regs::PermissionIndirectionE0El1;
+use crate::regs::PermissionIndirectionEl1;
+use crate::regs::PointerAuthKey;
+use crate::regs::ProcessorFeatures1El1;
+use crate::regs::SErrorType;
+use crate::regs::SavedProgramStateEl1;
+use crate::regs::SveBf16;
+use crate::regs::Syndrome;
+use crate::regs::SystemControlEl1;
+use crate::regs::SystemControlEl2;
+use crate::regs::TranslationBase0El1;
+use crate::regs::TranslationBase1El1;
+use crate::regs::TranslationControlEl1;
+use crate::regs::TranslationGranule0;
+use crate::regs::TranslationGranule1;
+use crate::rng::Rng;
+use crate::rng::Source;
+use crate::survey;
+use crate::sve;
+use crate::sve::VectorLengths;
+use crate::timer::Instant;
+use crate::timer::Timer;
+use crate::timer::TimerKind;
 
 const DUMP_PAGE_TABLES: bool = false;
 
+/// The write-back slot of the MAIR layout the expected entries use:
+/// Device-nGnRnE, Normal Non-cacheable, Normal Write-Back.
+fn wb_index() -> MairIndex {
+    let mut mair = MairBuilder::new();
+    mair.add(MemoryAttributeEl1::DEVICE_NGNRNE).unwrap();
+    mair.add(MemoryAttributeEl1::NORMAL_NON_CACHEABLE).unwrap();
+    mair.add(MemoryAttributeEl1::NORMAL_WRITE_BACK).unwrap()
+}
+
 #[test]
 fn test_mmu_small_pages() {
     let mut space = vec![0xaa; 0x100000];
     let mut page_tables =
         PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
 
-    let mair_el1 = MemoryAttributeIndirectionEl1::default();
-    let wb_index = mair_el1
-        .get_index(MemoryAttributeEl1::Normal_WriteBack)
-        .expect("must be some WB memory available");
+    let wb_index = wb_index();
 
     let res = page_tables.map_pages(
         0x4000,
@@ -101,10 +227,7 @@ fn test_mmu_large_pages() {
     let mut page_tables =
         PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
 
-    let mair_el1 = MemoryAttributeIndirectionEl1::default();
-    let wb_index = mair_el1
-        .get_index(MemoryAttributeEl1::Normal_WriteBack)
-        .expect("must be some WB memory available");
+    let wb_index = wb_index();
 
     let res = page_tables.map_pages(
         0,
@@ -123,7 +246,15 @@ fn test_mmu_large_pages() {
         PageSize::Small,
         wb_index,
     );
-    assert_eq!(res, Err(PageMapError::AlreadyMapped));
+    assert_eq!(
+        res,
+        Err(PageMapError::AlreadyMapped {
+            virt_addr: 0x4000,
+            level: 2,
+            entry: 0x749,
+            page_size: PageSize::Small,
+        })
+    );
     assert_eq!(page_tables.lvl_stats(), [1, 1, 16, 0]);
 
     if DUMP_PAGE_TABLES {
@@ -137,10 +268,7 @@ fn test_mmu_huge_pages() {
     let mut page_tables =
         PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
 
-    let mair_el1 = MemoryAttributeIndirectionEl1::default();
-    let wb_index = mair_el1
-        .get_index(MemoryAttributeEl1::Normal_WriteBack)
-        .expect("must be some WB memory available");
+    let wb_index = wb_index();
 
     let res = page_tables.map_pages(0, VirtualAddress::from(0), 4, PageSize::Huge, wb_index);
     assert_eq!(res, Ok(()));
@@ -153,7 +281,15 @@ fn test_mmu_huge_pages() {
         PageSize::Small,
         wb_index,
     );
-    assert_eq!(res, Err(PageMapError::AlreadyMapped));
+    assert_eq!(
+        res,
+        Err(PageMapError::AlreadyMapped {
+            virt_addr: 0x4000_0000,
+            level: 1,
+            entry: 0x4000_0749,
+            page_size: PageSize::Small,
+        })
+    );
     assert_eq!(page_tables.lvl_stats(), [1, 1, 0, 0]);
 
     if DUMP_PAGE_TABLES {
@@ -161,21 +297,1568 @@ fn test_mmu_huge_pages() {
     }
 }
 
+const ONE_GIB: u64 = 1 << 30;
+
+/// (phys_addr, virt_addr, size): pages, then blocks, then pages again
+const PAGE_MIX: (u64, u64, u64) = (ONE_GIB - 0x1000, ONE_GIB - 0x1000, 3 * ONE_GIB);
+
 #[test]
 fn test_mmu_page_mix() {
     let mut space = vec![0xaa; 0x100000];
     let mut page_tables =
         PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
 
-    let mair_el1 = MemoryAttributeIndirectionEl1::default();
-    let wb_index = mair_el1
-        .get_index(MemoryAttributeEl1::Normal_WriteBack)
-        .expect("must be some WB memory available");
-
-    const ONE_GIB: u64 = 1 << 30;
+    let wb_index = wb_index();
 
-    let addr = ONE_GIB - 0x1000;
-    let res = page_tables.map_range(addr, VirtualAddress::from(addr), 3 * ONE_GIB, wb_index);
+    let (phys_addr, virt_addr, size) = PAGE_MIX;
+    let res = page_tables.map_range(phys_addr, VirtualAddress::from(virt_addr), size, wb_index);
     assert_eq!(res, Ok(()));
     assert_eq!(page_tables.lvl_stats(), [1, 1, 2, 2]);
 }
+
+#[test]
+fn test_mmu_out_of_memory() {
+    let mut space = vec![0xaa; 0x2000];
+    let mut page_tables =
+        PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
+
+    let wb_index = wb_index();
+
+    let res = page_tables.map_pages(
+        0x4000,
+        VirtualAddress::from(0x4000),
+        1,
+        PageSize::Small,
+        wb_index,
+    );
+    assert_eq!(
+        res,
+        Err(PageMapError::OutOfMemory {
+            virt_addr: 0x4000,
+            level: 2,
+        })
+    );
+    assert_eq!(page_tables.lvl_stats(), [1, 1, 0, 0]);
+}
+
+#[test]
+fn test_mte_tags() {
+    let ptr = 0x4020_0010 as *mut u64;
+    assert_eq!(mte::tag_of(ptr), 0);
+
+    let tagged = mte::with_tag(ptr, 0xa);
+    assert_eq!(tagged as u64, 0x0a00_0000_4020_0010);
+    assert_eq!(mte::tag_of(tagged), 0xa);
+    assert_eq!(mte::with_tag(tagged, 0), ptr);
+
+    let mut mair = MairBuilder::new();
+    mair.add(MemoryAttributeEl1::DEVICE_NGNRNE).unwrap();
+    mair.add(MemoryAttributeEl1::NORMAL_WRITE_BACK).unwrap();
+    let tagged_index = mair.add(MemoryAttributeEl1::NormalTagged).unwrap();
+    assert_eq!(tagged_index.index(), 2);
+    assert_eq!(u64::from(mair.build()), 0x0000_0000_00f0_ff00);
+
+    let esr = ExceptionSyndromeEl1::new()
+        .with_ec(ExceptionClass::DataAbortSameEl)
+        .with_il(1)
+        .with_iss(0b010001);
+    assert!(esr.is_tag_check_fault());
+    assert!(!esr.with_iss(0b000111).is_tag_check_fault());
+    assert!(!esr
+        .with_ec(ExceptionClass::InstructionAbortSameEl)
+        .is_tag_check_fault());
+}
+
+#[test]
+fn test_memory_attributes() {
+    for (attr, bits) in [
+        (MemoryAttributeEl1::DEVICE_NGNRNE, 0x00),
+        (MemoryAttributeEl1::DEVICE_NGNRE, 0x04),
+        (
+            MemoryAttributeEl1::Device {
+                memory: DeviceMemory::GRE,
+                xs: false,
+            },
+            0x0d,
+        ),
+        (MemoryAttributeEl1::NORMAL_NON_CACHEABLE, 0x44),
+        (MemoryAttributeEl1::NORMAL_WRITE_THROUGH, 0xbb),
+        (MemoryAttributeEl1::NORMAL_WRITE_BACK, 0xff),
+        (
+            MemoryAttributeEl1::Normal {
+                inner: Cacheability::WriteBack(CachePolicy {
+                    read_allocate: true,
+                    write_allocate: false,
+                    transient: true,
+                }),
+                outer: Cacheability::NonCacheable,
+            },
+            0x46,
+        ),
+        (MemoryAttributeEl1::NormalNonCacheableNoXs, 0x40),
+        (MemoryAttributeEl1::NormalWriteThroughNoXs, 0xa0),
+        (MemoryAttributeEl1::NormalTagged, 0xf0),
+    ] {
+        assert_eq!(attr.encode(), Some(bits), "{attr:?}");
+        assert_eq!(MemoryAttributeEl1::decode(bits), Some(attr));
+    }
+    // Device memory with bit 1 set, and inner 0 but for the FEAT_XS and
+    // FEAT_MTE2 encodings.
+    assert_eq!(MemoryAttributeEl1::decode(0x02), None);
+    assert_eq!(MemoryAttributeEl1::decode(0x80), None);
+
+    let transient_no_allocate = MemoryAttributeEl1::Normal {
+        inner: Cacheability::WriteThrough(CachePolicy {
+            transient: true,
+            ..CachePolicy::NO_ALLOCATE
+        }),
+        outer: Cacheability::NonCacheable,
+    };
+    assert_eq!(transient_no_allocate.encode(), None);
+
+    let mut mair = MairBuilder::new();
+    assert_eq!(
+        mair.add(transient_no_allocate),
+        Err(MairError::Unencodable(transient_no_allocate))
+    );
+    let device = mair.add(MemoryAttributeEl1::DEVICE_NGNRNE).unwrap();
+    let normal = mair.add(MemoryAttributeEl1::NORMAL_WRITE_BACK).unwrap();
+    assert_eq!(mair.add(MemoryAttributeEl1::DEVICE_NGNRNE), Ok(device));
+    assert_eq!((device.index(), normal.index()), (0, 1));
+    for memory in [DeviceMemory::nGnRE, DeviceMemory::nGRE, DeviceMemory::GRE] {
+        mair.add(MemoryAttributeEl1::Device { memory, xs: true })
+            .unwrap();
+    }
+    mair.add(MemoryAttributeEl1::NORMAL_NON_CACHEABLE).unwrap();
+    mair.add(MemoryAttributeEl1::NORMAL_WRITE_THROUGH).unwrap();
+    let tagged = mair.add(MemoryAttributeEl1::NormalTagged).unwrap();
+    assert_eq!(
+        mair.add(MemoryAttributeEl1::NormalNonCacheableNoXs),
+        Err(MairError::Full)
+    );
+
+    let mair_el1 = mair.build();
+    assert_eq!(u64::from(mair_el1), 0xf0bb_440c_0804_ff00);
+    assert_eq!(
+        mair_el1.attr(tagged),
+        Some(MemoryAttributeEl1::NormalTagged)
+    );
+    assert_eq!(
+        MemoryAttributeIndirectionEl1::from(0xf0bb_440c_0804_ff00_u64)
+            .get_index(MemoryAttributeEl1::NORMAL_WRITE_BACK),
+        Some(normal)
+    );
+}
+
+#[test]
+fn test_mmu_permission_indirection() {
+    let pir_el1 = PermissionIndirectionEl1::default();
+    let pire0_el1 = PermissionIndirectionE0El1::default();
+    assert_eq!(u64::from(pir_el1), 0xcccc_eeee_cccc_eeee);
+    assert_eq!(u64::from(pire0_el1), 0xc0c0_c0c0_e0e0_e0e0);
+
+    // The legacy read-write entry written with the AP bits has PIIndex 1.
+    let legacy = PageBlockEntry::new().with_valid(true).with_access_perm(1);
+    assert_eq!(legacy.pi_index(), 1);
+    assert_eq!(pir_el1.get(1), PagePermission::ReadWriteExecute);
+    assert_eq!(pire0_el1.get(1), PagePermission::ReadWriteExecute);
+    // The reserved encodings read back as they are.
+    let reserved = PermissionIndirectionEl1::from(0xf << 4);
+    assert_eq!(reserved.get(1), PagePermission::Reserved(0xf));
+    assert_eq!(u64::from(reserved.with(1, reserved.get(1))), 0xf << 4);
+
+    for pi_index in 0..16 {
+        assert_eq!(legacy.with_pi_index(pi_index).pi_index(), pi_index);
+    }
+
+    let mut space = vec![0xaa; 0x100000];
+    let mut page_tables =
+        PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
+    page_tables.set_permission_model(PermissionModel::Indirect(0b1100));
+
+    let wb_index = wb_index();
+
+    let res = page_tables.map_pages(
+        0x4000,
+        VirtualAddress::from(0x4000),
+        1,
+        PageSize::Small,
+        wb_index,
+    );
+    assert_eq!(res, Ok(()));
+    assert_eq!(page_tables.lvl_stats(), [1, 1, 1, 1]);
+
+    // The level 3 table is the fourth page, the entry for 0x4000 is #4.
+    let pos = 0x3000 + 4 * 8;
+    let entry = PageBlockEntry::from(u64::from_le_bytes(
+        space[pos..pos + 8].try_into().expect("8 bytes"),
+    ));
+    assert_eq!(u64::from(entry), 0x0060_0000_0000_470b);
+    assert_eq!(entry.pi_index(), 0b1100);
+    assert_eq!(entry.access_perm(), 0);
+}
+
+#[test]
+fn test_mmu_guarded_pages() {
+    let mut space = vec![0xaa; 0x100000];
+    let mut page_tables =
+        PageTableSpace::new(0x00000040248000, &mut space).expect("Can initialize page tables");
+    let wb_index = wb_index();
+
+    page_tables.set_guarded(true);
+    let res = page_tables.map_pages(
+        0x4000,
+        VirtualAddress::from(0x4000),
+        1,
+        PageSize::Small,
+        wb_index,
+    );
+    assert_eq!(res, Ok(()));
+    page_tables.set_guarded(false);
+    let res = page_tables.map_pages(
+        0x5000,
+        VirtualAddress::from(0x5000),
+        1,
+        PageSize::Small,
+        wb_index,
+    );
+    assert_eq!(res, Ok(()));
+
+    let entry = |index: usize| {
+        let pos = 0x3000 + index * 8;
+        PageBlockEntry::from(u64::from_le_bytes(
+            space[pos..pos + 8].try_into().expect("8 bytes"),
+        ))
+    };
+    assert_eq!(u64::from(entry(4)), 1 << 50 | 0x4000 | 0x74b);
+    assert!(entry(4).guarded());
+    assert!(!entry(5).guarded());
+}
+
+#[test]
+fn test_mmu_d128_translations() {
+    let wb_index = wb_index();
+
+    // (phys_addr, virt_addr, size) and the table counts of D64 and D128:
+    // small pages, a large page, a huge page, large pages, then the page
+    // mix. Each in page tables of its own, so nothing maps past its end.
+    let ranges = [
+        ((0x4000, 0x4000, 0x2000), [1, 1, 1, 1], [1, 1, 1, 1]),
+        (
+            (0x20_0000, 0x20_0000, 0x20_0000),
+            [1, 1, 1, 0],
+            [1, 1, 1, 0],
+        ),
+        (
+            (0x4000_0000, 0x4000_0000, ONE_GIB),
+            [1, 1, 0, 0],
+            [1, 1, 0, 0],
+        ),
+        (
+            (0x1_0000_0000, 0x1_0000_0000, 0x2000_0000),
+            [1, 1, 1, 0],
+            [1, 1, 2, 0],
+        ),
+        (PAGE_MIX, [1, 1, 2, 2], [1, 1, 5, 3]),
+    ];
+    for ((phys_addr, virt_addr, size), stats_d64, stats_d128) in ranges {
+        let mut space_d64 = vec![0xaa; 0x100000];
+        let mut space_d128 = vec![0xaa; 0x100000];
+        let mut page_tables_d64 = PageTableSpace::new(0x00000040248000, &mut space_d64)
+            .expect("Can initialize page tables");
+        let mut page_tables_d128 = PageTableSpace::new_d128(0x00000040248000, &mut space_d128)
+            .expect("Can initialize page tables");
+        assert_eq!(page_tables_d128.format(), DescriptorFormat::D128);
+
+        for page_tables in [&mut page_tables_d64, &mut page_tables_d128] {
+            let res =
+                page_tables.map_range(phys_addr, VirtualAddress::from(virt_addr), size, wb_index);
+            assert_eq!(res, Ok(()));
+        }
+        assert_eq!(page_tables_d64.lvl_stats(), stats_d64);
+        assert_eq!(page_tables_d128.lvl_stats(), stats_d128);
+
+        let probes = (0..size)
+            .step_by(0x10_1008)
+            .chain([0, 0xfff, size - 8, size, size + 0x1000]);
+        for offset in probes {
+            let va = VirtualAddress::from(virt_addr + offset);
+            let d64 = page_tables_d64.translate(va);
+            let d128 = page_tables_d128.translate(va);
+            if offset < size {
+                let d64 = d64.expect("mapped VA");
+                let d128 = d128.expect("mapped VA");
+                assert_eq!(d64.phys_addr, phys_addr + offset);
+                assert_eq!(d128.phys_addr, phys_addr + offset);
+                assert_eq!(d64.mair_idx, d128.mair_idx);
+                assert_eq!(d64.pi_index, d128.pi_index);
+            } else {
+                assert_eq!(d64, None, "{virt_addr:#x} + {offset:#x} past the end");
+                assert_eq!(d128, None, "{virt_addr:#x} + {offset:#x} past the end");
+            }
+        }
+    }
+
+    let mut space_d128 = vec![0xaa; 0x100000];
+    let mut page_tables_d128 = PageTableSpace::new_d128(0x00000040248000, &mut space_d128)
+        .expect("Can initialize page tables");
+    let (phys_addr, virt_addr, size) = PAGE_MIX;
+    let res =
+        page_tables_d128.map_range(phys_addr, VirtualAddress::from(virt_addr), size, wb_index);
+    assert_eq!(res, Ok(()));
+
+    // 2^44 is out of the D128 VA space only.
+    let res = page_tables_d128.map_range(0, VirtualAddress::from(1 << 44), 0x1000, wb_index);
+    assert_eq!(res, Err(PageMapError::NonCanonicalVirtAddress(1 << 44)));
+    let res = page_tables_d128.map_pages(
+        phys_addr,
+        VirtualAddress::from(virt_addr),
+        1,
+        PageSize::Small,
+        wb_index,
+    );
+    assert!(matches!(
+        res,
+        Err(PageMapError::AlreadyMapped {
+            level: 3,
+            entry,
+            ..
+        }) if entry >> 64 != 0
+    ));
+}
+
+/// Builds a DTB with the nodes the closure adds.
+fn build_fdt(mem_rsvmap: &[(u64, u64)], nodes: impl FnOnce(&mut FdtBuilder)) -> Vec<u8> {
+    let mut builder = FdtBuilder::default();
+    nodes(&mut builder);
+    builder.structs.extend(9u32.to_be_bytes());
+
+    let mut rsvmap = Vec::new();
+    for (address, size) in mem_rsvmap.iter().chain([&(0, 0)]) {
+        rsvmap.extend(address.to_be_bytes());
+        rsvmap.extend(size.to_be_bytes());
+    }
+
+    let off_mem_rsvmap = 40;
+    let off_dt_struct = off_mem_rsvmap + rsvmap.len();
+    let off_dt_strings = off_dt_struct + builder.structs.len();
+    let total_size = off_dt_strings + builder.strings.len();
+    let header = [
+        0xd00d_feed,
+        total_size,
+        off_dt_struct,
+        off_dt_strings,
+        off_mem_rsvmap,
+        17,
+        16,
+        0,
+        builder.strings.len(),
+        builder.structs.len(),
+    ];
+
+    let mut blob: Vec<u8> = header
+        .iter()
+        .flat_map(|&x| (x as u32).to_be_bytes())
+        .collect();
+    blob.extend(rsvmap);
+    blob.extend(builder.structs);
+    blob.extend(builder.strings);
+    blob
+}
+
+#[derive(Default)]
+struct FdtBuilder {
+    structs: Vec<u8>,
+    strings: Vec<u8>,
+}
+
+impl FdtBuilder {
+    fn pad(&mut self) {
+        while self.structs.len() % 4 != 0 {
+            self.structs.push(0);
+        }
+    }
+
+    fn begin_node(&mut self, name: &str) -> &mut Self {
+        self.structs.extend(1u32.to_be_bytes());
+        self.structs.extend(name.as_bytes());
+        self.structs.push(0);
+        self.pad();
+        self
+    }
+
+    fn end_node(&mut self) -> &mut Self {
+        self.structs.extend(2u32.to_be_bytes());
+        self
+    }
+
+    fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+        let name_offset = self.strings.len() as u32;
+        self.strings.extend(name.as_bytes());
+        self.strings.push(0);
+        self.structs.extend(3u32.to_be_bytes());
+        self.structs.extend((value.len() as u32).to_be_bytes());
+        self.structs.extend(name_offset.to_be_bytes());
+        self.structs.extend(value);
+        self.pad();
+        self
+    }
+
+    fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+        let value: Vec<u8> = cells.iter().flat_map(|c| c.to_be_bytes()).collect();
+        self.prop(name, &value)
+    }
+}
+
+#[test]
+fn test_memmap_from_fdt() {
+    // The secmon reservation repeats, the last two run past the RAM.
+    let mem_rsvmap = [
+        (0x4000_0000, 0x1000),
+        (0x4300_0000, 0x10_0000),
+        (0x43f0_0000, 0x20_0000),
+        (0x900_0000, 0x1000),
+    ];
+    let blob = build_fdt(&mem_rsvmap, |b| {
+        b.begin_node("")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .begin_node("memory@40000000")
+            .prop_cells("reg", &[0, 0x4000_0000, 0, 0x400_0000])
+            .prop("device_type", b"memory\0")
+            .end_node()
+            .begin_node("reserved-memory")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1])
+            .begin_node("secmon@43000000")
+            .prop_cells("reg", &[0x4300_0000, 0x10_0000])
+            .end_node()
+            .end_node()
+            .begin_node("pl011@9000000")
+            .prop("compatible", b"arm,pl011\0arm,primecell\0")
+            .prop_cells("reg", &[0, 0x900_0000, 0, 0x1000])
+            .end_node()
+            .begin_node("soc")
+            .begin_node("intc@8000000")
+            .prop("compatible", b"arm,gic-v3\0")
+            .prop_cells("reg", &[0, 0x800_0000, 0x1_0000, 0, 0x80a_0000, 0xf6_0000])
+            .prop_cells("interrupts", &[1, 9, 4])
+            .end_node()
+            .end_node()
+            .end_node();
+    });
+
+    let fdt = Fdt::new(&blob).expect("valid DTB");
+    assert_eq!(fdt.total_size(), blob.len());
+    let memory_map = MemoryMap::from_fdt(&fdt).expect("valid memory map");
+
+    let ram = RegionKind::Ram;
+    let firmware = RegionKind::Reserved(Reservation::Firmware);
+    assert_eq!(
+        memory_map.regions().copied().collect::<Vec<_>>(),
+        [
+            Region {
+                base: 0x4000_0000,
+                size: 0x1000,
+                kind: firmware,
+            },
+            Region {
+                base: 0x4000_1000,
+                size: 0x2ff_f000,
+                kind: ram,
+            },
+            Region {
+                base: 0x4300_0000,
+                size: 0x10_0000,
+                kind: firmware,
+            },
+            Region {
+                base: 0x4310_0000,
+                size: 0xe0_0000,
+                kind: ram,
+            },
+            Region {
+                base: 0x43f0_0000,
+                size: 0x10_0000,
+                kind: firmware,
+            },
+        ]
+    );
+
+    let pl011 = fdt.find_compatible(b"arm,primecell").unwrap().unwrap();
+    assert_eq!(pl011.name, b"pl011@9000000");
+    assert_eq!(pl011.reg().collect::<Vec<_>>(), [(0x900_0000, 0x1000)]);
+    // The cells of a node without them are 2 and 1, not those of the root.
+    let gic = fdt.find_compatible(b"arm,gic-v3").unwrap().unwrap();
+    assert_eq!(
+        gic.reg().collect::<Vec<_>>(),
+        [(0x800_0000, 0x1_0000), (0x80a_0000, 0xf6_0000)]
+    );
+    assert_eq!(gic.interrupts().collect::<Vec<_>>(), [1, 9, 4]);
+    assert_eq!(gic::dt_intid(gic.interrupts()), Some(25));
+    assert_eq!(gic::dt_intid([0, 1, 4]), Some(33));
+    assert_eq!(gic::dt_intid([1, 16, 4]), None);
+    assert_eq!(gic::dt_intid([2, 0, 4]), None);
+    assert_eq!(gic::dt_intid([1]), None);
+    assert!(fdt.find_compatible(b"arm,pl0").unwrap().is_none());
+
+    let unbalanced = build_fdt(&[], |b| {
+        b.begin_node("").end_node().end_node();
+    });
+    let fdt = Fdt::new(&unbalanced).expect("valid header");
+    assert!(matches!(
+        MemoryMap::from_fdt(&fdt),
+        Err(MemoryMapError::Fdt(FdtError::UnbalancedEndNode(_)))
+    ));
+
+    let mut bad_magic = blob.clone();
+    bad_magic[0] = 0;
+    assert_eq!(
+        Fdt::new(&bad_magic).err(),
+        Some(FdtError::BadMagic(0x000d_feed))
+    );
+    assert_eq!(
+        Fdt::new(&blob[..blob.len() - 1]).err(),
+        Some(FdtError::Truncated)
+    );
+}
+
+#[test]
+fn test_memmap_carve_out_and_allocate() {
+    let mut memory_map = MemoryMap::new();
+    assert_eq!(
+        memory_map.add(0x4000_0000, 0x400_0000, RegionKind::Ram),
+        Ok(())
+    );
+    assert_eq!(memory_map.add(0x900_0000, 0x1000, RegionKind::Mmio), Ok(()));
+    assert_eq!(
+        memory_map.add(0x4300_0000, 0x1000, RegionKind::Mmio),
+        Err(MemoryMapError::Overlap {
+            base: 0x4300_0000,
+            size: 0x1000,
+            existing_base: 0x4000_0000,
+        })
+    );
+
+    // The stack inside the image first, then the image around it.
+    let stack = Reservation::Stack;
+    let image = Reservation::Image;
+    assert_eq!(memory_map.carve_out(0x4021_0000, 0x2_0000, stack), Ok(()));
+    assert_eq!(memory_map.carve_out(0x4020_0000, 0x10_0000, image), Ok(()));
+    assert_eq!(
+        memory_map
+            .regions()
+            .map(|r| (r.base, r.size, r.kind))
+            .collect::<Vec<_>>(),
+        [
+            (0x900_0000, 0x1000, RegionKind::Mmio),
+            (0x4000_0000, 0x20_0000, RegionKind::Ram),
+            (0x4020_0000, 0x1_0000, RegionKind::Reserved(image)),
+            (0x4021_0000, 0x2_0000, RegionKind::Reserved(stack)),
+            (0x4023_0000, 0xd_0000, RegionKind::Reserved(image)),
+            (0x4030_0000, 0x3d0_0000, RegionKind::Ram),
+        ]
+    );
+
+    assert_eq!(
+        memory_map.carve_out(0x43ff_f000, 0x2000, image),
+        Err(MemoryMapError::NotRam {
+            base: 0x43ff_f000,
+            size: 0x2000
+        })
+    );
+    assert_eq!(
+        memory_map.carve_out(0x900_0000, 0x1000, image),
+        Err(MemoryMapError::NotRam {
+            base: 0x900_0000,
+            size: 0x1000
+        })
+    );
+
+    let payload = Reservation::Payload;
+    assert_eq!(
+        memory_map.allocate(0x30_0000, 0x1000, payload),
+        Ok(0x4030_0000)
+    );
+    assert_eq!(
+        memory_map.allocate(0x1000, 0x1000, payload),
+        Ok(0x4000_0000)
+    );
+    assert_eq!(
+        memory_map.allocate(0x1000, 0x20_0000, payload),
+        Ok(0x4060_0000)
+    );
+    assert_eq!(
+        memory_map.allocate(0x400_0000, 0x1000, payload),
+        Err(MemoryMapError::OutOfMemory {
+            size: 0x400_0000,
+            align: 0x1000
+        })
+    );
+    assert_eq!(
+        memory_map.find(0x4060_0800).map(|r| r.kind),
+        Some(RegionKind::Reserved(payload))
+    );
+    assert_eq!(
+        memory_map.free_ram().map(|r| r.size).sum::<u64>(),
+        0x400_0000 - 0x10_0000 - 0x2000 - 0x30_0000
+    );
+}
+
+#[test]
+fn test_cpu_features() {
+    let mut features = CpuFeatures::default();
+    assert!(features.has(Feature::Fp));
+    assert!(!features.has(Feature::Lse));
+    assert!(!features.has(Feature::PAuth));
+    assert_eq!(
+        features.iter().collect::<Vec<_>>(),
+        [Feature::Fp, Feature::AdvSimd]
+    );
+
+    features.isar0 = InstructionSetFeatures0El1::new()
+        .with_atomic(IsaAtomic::Lse)
+        .with_sha2(IsaSha2::Sha256);
+    features.isar1 = InstructionSetFeatures1El1::new().with_api(IsaPAuth::PAuth2);
+    features.pfr1 = ProcessorFeatures1El1::new().with_bt(1).with_mte(2);
+    assert!(features.has(Feature::Lse));
+    assert!(!features.has(Feature::Lse128));
+    assert!(features.has(Feature::Sha256));
+    assert!(!features.has(Feature::Sha512));
+    assert!(features.has(Feature::PAuth));
+    assert!(features.has(Feature::PAuth2));
+    assert!(!features.has(Feature::Fpac));
+    assert!(features.has(Feature::Bti));
+    assert!(features.has(Feature::Mte2));
+    assert!(!features.has(Feature::Mte3));
+
+    // The values from the later architecture versions still decode.
+    let isar0 = InstructionSetFeatures0El1::from(0x7u64 << 20);
+    assert_eq!(isar0.atomic(), IsaAtomic::Unknown(7));
+    features.isar0 = isar0;
+    assert!(features.has(Feature::Lse128));
+}
+
+#[test]
+fn test_esr_decode() {
+    // ldr x3, [x1] faulting at level 3 on a missing page.
+    let esr = ExceptionSyndromeEl1::from(0x9600_0007u64);
+    assert_eq!(esr.ec(), ExceptionClass::DataAbortSameEl);
+    let Syndrome::DataAbort(iss) = esr.decode() else {
+        panic!("not a data abort: {:?}", esr.decode());
+    };
+    assert!(!iss.wnr());
+    assert!(!iss.fnv());
+    assert_eq!(iss.fault(), FaultStatusCode::Translation { level: 3 });
+
+    // str w2, [x0] hitting a read-only level 2 block.
+    let Syndrome::DataAbort(iss) = ExceptionSyndromeEl1::from(0x9382_004eu64).decode() else {
+        panic!("not a data abort");
+    };
+    assert!(iss.isv());
+    assert!(iss.wnr());
+    assert!(!iss.sf());
+    assert_eq!(iss.srt(), 2);
+    assert_eq!(iss.sas(), 2);
+    assert_eq!(iss.fault(), FaultStatusCode::Permission { level: 2 });
+
+    let Syndrome::InstructionAbort(iss) = ExceptionSyndromeEl1::from(0x8600_002bu64).decode()
+    else {
+        panic!("not an instruction abort");
+    };
+    assert_eq!(iss.fault(), FaultStatusCode::Translation { level: -1 });
+
+    assert_eq!(
+        ExceptionSyndromeEl1::from(0x5600_0042u64).decode(),
+        Syndrome::Svc(0x42)
+    );
+    assert_eq!(
+        ExceptionSyndromeEl1::from(0x5a00_0001u64).decode(),
+        Syndrome::Hvc(1)
+    );
+    assert_eq!(
+        ExceptionSyndromeEl1::from(0xf200_03e8u64).decode(),
+        Syndrome::Brk(1000)
+    );
+
+    // mrs x5, cntvct_el0
+    let Syndrome::SysRegTrap(iss) = ExceptionSyndromeEl1::from(0x6234_f8a1u64).decode() else {
+        panic!("not a system register trap");
+    };
+    assert!(iss.read());
+    assert_eq!(iss.rt(), 5);
+    assert_eq!(
+        (iss.op0(), iss.op1(), iss.crn(), iss.crm(), iss.op2()),
+        (3, 3, 14, 0, 2)
+    );
+
+    let Syndrome::SError(iss) = ExceptionSyndromeEl1::from(0xbe00_0c11u64).decode() else {
+        panic!("not an SError");
+    };
+    assert_eq!(iss.aet(), SErrorType::RecoverableState);
+
+    // autia x0, x1 failing with FEAT_FPAC, and the B key for data.
+    assert_eq!(
+        ExceptionSyndromeEl1::from(0x7200_0000u64).decode(),
+        Syndrome::PointerAuth(PointerAuthKey::Ia)
+    );
+    assert_eq!(
+        ExceptionSyndromeEl1::from(0x7200_0003u64).decode(),
+        Syndrome::PointerAuth(PointerAuthKey::Db)
+    );
+    // blr to a guarded page without a landing pad.
+    assert_eq!(
+        ExceptionSyndromeEl1::from(0x3600_0002u64).decode(),
+        Syndrome::BranchTarget(0b10)
+    );
+
+    // The classes this crate does not know about decode too.
+    let esr = ExceptionSyndromeEl1::from(0xfc00_0000u64 | (0x3f << 26));
+    assert_eq!(esr.ec(), ExceptionClass::Other(0x3f));
+    assert_eq!(esr.decode(), Syndrome::Other(0));
+    assert_eq!(u64::from(ExceptionClass::Other(0x3f)), 0x3f);
+
+    // ISS2 is ESR[55:32].
+    let esr = ExceptionSyndromeEl1::from(0xff_ff80_0000_0000 | 0x9600_0007u64);
+    assert_eq!(esr.iss2(), 0xff_ff80);
+}
+
+#[test]
+fn test_core_registers() {
+    // What start.S writes.
+    let cpacr = ArchFeatureAccessControlEl1::from((3 << 20) | (3 << 16));
+    assert_eq!(cpacr.fpen(), CpacrTrap::NoTrap);
+    assert_eq!(cpacr.zen(), CpacrTrap::NoTrap);
+    assert_eq!(cpacr.smen(), CpacrTrap::TrapAll);
+    assert_eq!(
+        u64::from(ArchFeatureAccessControlEl1::new().with_fpen(CpacrTrap::TrapEl0)),
+        1 << 20
+    );
+
+    let mpidr = MultiprocessorAffinityEl1::from(0x0000_0002_8103_0201);
+    assert_eq!(
+        (mpidr.aff3(), mpidr.aff2(), mpidr.aff1(), mpidr.aff0()),
+        (2, 3, 2, 1)
+    );
+    assert!(mpidr.mt());
+    assert!(!mpidr.u());
+}
+
+#[test]
+fn test_el2_registers() {
+    let hcr = HypervisorConfigEl2::new()
+        .with_rw(true)
+        .with_vm(true)
+        .with_imo(true)
+        .with_fmo(true);
+    assert_eq!(u64::from(hcr), 0x8000_0019);
+    assert_eq!(u64::from(SystemControlEl2::default()), 0x30c5_0830);
+    assert_eq!(
+        u64::from(ArchFeatureTrapEl2::default().with_tfp(true)),
+        0x26ff
+    );
+
+    let hpfar = HypervisorIpaFaultAddressEl2::from(0x0000_0000_0412_3450);
+    assert_eq!(hpfar.ipa(), 0x4_1234_5000);
+
+    // HVC #7 taken to EL2
+    let esr = ExceptionSyndromeEl2::from(0x5a00_0007);
+    assert_eq!(esr.decode(), Syndrome::Hvc(7));
+}
+
+static_assertions::assert_not_impl_any!(MainIdEl1: WritableRegister);
+static_assertions::assert_not_impl_any!(OsLockAccessEl1: ReadableRegister);
+static_assertions::assert_impl_all!(SystemControlEl1: ReadableRegister, WritableRegister);
+
+#[test]
+fn test_register_access_markers() {
+    fn access<R: SystemRegister>() -> &'static str {
+        core::any::type_name::<R::Access>()
+    }
+
+    assert_eq!(access::<MainIdEl1>(), core::any::type_name::<ReadOnly>());
+    assert_eq!(
+        access::<OsLockAccessEl1>(),
+        core::any::type_name::<WriteOnly>()
+    );
+    assert_eq!(
+        access::<SystemControlEl1>(),
+        core::any::type_name::<ReadWrite>()
+    );
+    assert_eq!(SystemControlEl1::NAME, "SCTLR_EL1");
+}
+
+#[test]
+fn test_simulated_cpu_features() {
+    Simulated::reset();
+    Simulated::set("ID_AA64ISAR0_EL1", 2 << 20);
+    Simulated::set("ID_AA64PFR0_EL1", 1 << 32);
+    // SVE2 with BitPerm at [19:16] and BF16 at [23:20].
+    Simulated::set("ID_AA64ZFR0_EL1", 0x11_0001);
+
+    let features = CpuFeatures::detect();
+    assert!(features.has(Feature::Lse));
+    assert!(features.has(Feature::Sve));
+    assert!(features.has(Feature::Sve2));
+    assert_eq!(features.zfr0.bit_perm(), IdFeature::Yes);
+    assert_eq!(features.zfr0.bf16(), SveBf16::Bf16);
+    assert_eq!(features.zfr0.b16b16(), IdFeature::No);
+    assert_eq!(features.zfr0.sha3(), IdFeature::No);
+    assert!(!features.has(Feature::Sme));
+    assert_eq!(Simulated::get("ID_AA64SMFR0_EL1"), None);
+}
+
+#[test]
+fn test_simulated_debug() {
+    Simulated::reset();
+    // Two breakpoints and four watchpoints.
+    Simulated::set("ID_AA64DFR0_EL1", 1 << 12 | 3 << 20);
+    Simulated::set("OSLAR_EL1", 1);
+    let debug = Debug::new();
+    debug.enable();
+    assert_eq!((debug.breakpoints(), debug.watchpoints()), (2, 4));
+    assert_eq!(Simulated::get("OSLAR_EL1"), Some(0));
+    assert_eq!(Simulated::get("MDSCR_EL1"), Some(1 << 15 | 1 << 13));
+
+    assert_eq!(
+        debug.set_breakpoint(2, 0x4008_0000),
+        Err(DebugError::NoSlot { slot: 2, slots: 2 })
+    );
+    assert_eq!(
+        debug.set_breakpoint(0, 0x4008_0002),
+        Err(DebugError::Unaligned(0x4008_0002))
+    );
+    debug.set_breakpoint(1, 0x4008_0000).unwrap();
+    assert_eq!(Simulated::get("DBGBVR1_EL1"), Some(0x4008_0000));
+    assert_eq!(
+        Simulated::get("DBGBCR1_EL1"),
+        Some(0b1111 << 5 | 0b01 << 1 | 1)
+    );
+
+    debug
+        .set_watchpoint(0, 0x4010_0004, 2, WatchAccess::Store)
+        .unwrap();
+    assert_eq!(Simulated::get("DBGWVR0_EL1"), Some(0x4010_0000));
+    assert_eq!(
+        Simulated::get("DBGWCR0_EL1"),
+        Some(0b11_0000 << 5 | 0b10 << 3 | 0b01 << 1 | 1)
+    );
+    debug
+        .set_watchpoint(3, 0x4010_1000, 0x1000, WatchAccess::Any)
+        .unwrap();
+    assert_eq!(
+        Simulated::get("DBGWCR3_EL1"),
+        Some(12 << 24 | 0xff << 5 | 0b11 << 3 | 0b01 << 1 | 1)
+    );
+    assert_eq!(
+        debug.set_watchpoint(1, 0x4010_0006, 4, WatchAccess::Load),
+        Err(DebugError::Length {
+            address: 0x4010_0006,
+            len: 4
+        })
+    );
+
+    // SAFETY: all zeroes is a valid frame.
+    let mut frame: ExceptionFrame = unsafe { core::mem::zeroed() };
+    frame.elr = 0x4008_0000;
+    frame.spsr = 0b0101;
+    let breakpoint = ExceptionSyndromeEl1::new().with_ec(ExceptionClass::BreakpointSameEl);
+    let mut events = Vec::new();
+    assert!(debug.handle(&mut frame, breakpoint, 0, |event| {
+        events.push(event);
+        Resume::Continue
+    }));
+    // Stepping over the breakpoint with the breakpoints disabled.
+    assert_eq!(Simulated::get("DBGBCR1_EL1"), Some(0b1111 << 5 | 0b01 << 1));
+    assert!(SavedProgramStateEl1::from(frame.spsr as u64).ss());
+    assert_eq!(Simulated::get("MDSCR_EL1"), Some(1 << 15 | 1 << 13 | 1));
+
+    frame.elr = 0x4008_0004;
+    frame.spsr = 0b0101;
+    let step = ExceptionSyndromeEl1::new().with_ec(ExceptionClass::StepSameEl);
+    assert!(debug.handle(&mut frame, step, 0, |event| {
+        events.push(event);
+        Resume::Continue
+    }));
+    assert_eq!(
+        Simulated::get("DBGBCR1_EL1"),
+        Some(0b1111 << 5 | 0b01 << 1 | 1)
+    );
+    assert_eq!(Simulated::get("MDSCR_EL1"), Some(1 << 15 | 1 << 13));
+    assert!(!SavedProgramStateEl1::from(frame.spsr as u64).ss());
+
+    let watchpoint = ExceptionSyndromeEl1::new()
+        .with_ec(ExceptionClass::WatchpointSameEl)
+        .with_iss(1 << 6 | 0b10_0010);
+    assert!(debug.handle(&mut frame, watchpoint, 0x4010_0005, |event| {
+        events.push(event);
+        Resume::Step
+    }));
+    assert_eq!(Simulated::get("DBGWCR0_EL1").unwrap() & 1, 0);
+    assert!(debug.handle(&mut frame, step, 0, |event| {
+        events.push(event);
+        Resume::Continue
+    }));
+    assert_eq!(Simulated::get("DBGWCR0_EL1").unwrap() & 1, 1);
+
+    let brk = ExceptionSyndromeEl1::new()
+        .with_ec(ExceptionClass::Brk64bit)
+        .with_iss(0x42);
+    assert!(debug.handle(&mut frame, brk, 0, |event| {
+        events.push(event);
+        Resume::Continue
+    }));
+    assert_eq!({ frame.elr }, 0x4008_0008);
+    // The syndrome is stale for an IRQ.
+    frame.source = ExceptionSource::Irq;
+    assert!(!debug.handle(&mut frame, brk, 0, |_| Resume::Continue));
+    assert_eq!({ frame.elr }, 0x4008_0008);
+    frame.source = ExceptionSource::Synchronous;
+    let svc = ExceptionSyndromeEl1::new().with_ec(ExceptionClass::Svc64bit);
+    assert!(!debug.handle(&mut frame, svc, 0, |_| Resume::Continue));
+
+    assert_eq!(
+        events,
+        [
+            DebugEvent::Breakpoint { pc: 0x4008_0000 },
+            DebugEvent::Watchpoint {
+                pc: 0x4008_0004,
+                address: 0x4010_0005,
+                write: true
+            },
+            DebugEvent::Step { pc: 0x4008_0004 },
+            DebugEvent::Brk {
+                pc: 0x4008_0004,
+                comment: 0x42
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_simulated_pointer_auth() {
+    Simulated::reset();
+    let mut seed = 0;
+    let keys = Keys::generate(|| {
+        seed += 1;
+        seed
+    });
+    assert_eq!(
+        (keys.ia.lo, keys.ia.hi, keys.ga.lo, keys.ga.hi),
+        (1, 2, 9, 10)
+    );
+    keys.install();
+    assert_eq!(Simulated::get("APIAKeyLo_EL1"), Some(1));
+    assert_eq!(Simulated::get("APDBKeyHi_EL1"), Some(8));
+    assert_eq!(Simulated::get("APGAKeyHi_EL1"), Some(10));
+
+    Simulated::set("SCTLR_EL1", 1);
+    pauth::enable(Protection {
+        data_keys: false,
+        ..Protection::ALL
+    });
+    let sctlr_el1 = SystemControlEl1::read();
+    assert_eq!(
+        (sctlr_el1.m(), sctlr_el1.en_ia(), sctlr_el1.en_ib()),
+        (1, 1, 1)
+    );
+    assert_eq!((sctlr_el1.en_da(), sctlr_el1.en_db()), (0, 0));
+    assert_eq!((sctlr_el1.bt0(), sctlr_el1.bt1()), (0, 1));
+}
+
+#[test]
+fn test_simulated_rng() {
+    Simulated::reset();
+    let mut rng = Rng::new();
+    assert_eq!(rng.source(), Source::Counter);
+    // SplitMix64 seeded with 0.
+    assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+    assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+    Simulated::set("ID_AA64ISAR0_EL1", 1 << 60);
+    Simulated::set("CNTVCT_EL0", 0);
+    let mut rng = Rng::new();
+    assert_eq!(rng.source(), Source::Rndr);
+    // RNDR keeps failing, the fallback answers.
+    assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+
+    Simulated::set("RNDR", 0x0807_0605_0403_0201);
+    Simulated::set("RNDRRS", 7);
+    assert_eq!(rng.next_u64(), 0x0807_0605_0403_0201);
+    assert_eq!(rng.next_u64_reseeded(), 7);
+    assert_eq!(rng.below(0x100), 0x01);
+
+    let mut bytes = [0; 12];
+    rng.fill_bytes(&mut bytes);
+    assert_eq!(bytes, [1, 2, 3, 4, 5, 6, 7, 8, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_simulated_sve_enable() {
+    Simulated::reset();
+    let features = CpuFeatures::detect();
+    assert!(!sve::enable_sve(&features));
+    assert!(!sve::enable_sme(&features));
+    assert_eq!(Simulated::get("ZCR_EL1"), None);
+
+    Simulated::set("ID_AA64PFR0_EL1", 1 << 32);
+    Simulated::set("ID_AA64PFR1_EL1", 2 << 24);
+    Simulated::set("ID_AA64SMFR0_EL1", 1 << 63);
+    let features = CpuFeatures::detect();
+    assert!(sve::enable_sve(&features));
+    assert!(sve::enable_sme(&features));
+    let cpacr = ArchFeatureAccessControlEl1::read();
+    assert_eq!(cpacr.fpen(), CpacrTrap::NoTrap);
+    assert_eq!(cpacr.zen(), CpacrTrap::NoTrap);
+    assert_eq!(cpacr.smen(), CpacrTrap::NoTrap);
+    assert_eq!(Simulated::get("ZCR_EL1"), Some(0xf));
+    // FA64, and ZT0 with SME2.
+    assert_eq!(Simulated::get("SMCR_EL1"), Some(3 << 30 | 0xf));
+}
+
+#[test]
+fn test_sve_vector_lengths() {
+    // The powers of two up to 512 bits: 128, 256, 256, 512, 512, ...
+    let lengths = VectorLengths::probe(|len| {
+        let quads = (len as usize + 1).min(4);
+        16 << (usize::BITS - 1 - quads.leading_zeros())
+    });
+    assert_eq!(lengths.iter().collect::<Vec<_>>(), [16, 32, 64]);
+    assert_eq!(lengths.max(), Some(64));
+    assert_eq!(lengths.to_string(), "128, 256, 512");
+    assert!(VectorLengths::default().is_empty());
+    assert_eq!(VectorLengths::probe(|_| 256).to_string(), "2048");
+}
+
+#[test]
+fn test_simulated_timer() {
+    Simulated::reset();
+    Simulated::set("CNTFRQ_EL0", 62_500_000);
+    let start = Instant::from_ticks(1000);
+    let later = start + Duration::from_micros(10);
+    assert_eq!(later.ticks(), 1625);
+    assert_eq!(later - start, Duration::from_micros(10));
+    assert_eq!(start - later, Duration::ZERO);
+    // Rounded up to a whole tick.
+    assert_eq!((start + Duration::from_nanos(1)).ticks(), 1001);
+    assert_eq!(start.checked_sub(Duration::from_secs(1)), None);
+
+    let timer = Timer::new(TimerKind::Virtual);
+    Simulated::set("CNTVCT_EL0", 1000);
+    timer.set_deadline(later);
+    assert_eq!(Simulated::get("CNTV_CVAL_EL0"), Some(1625));
+    assert_eq!(Simulated::get("CNTV_CTL_EL0"), Some(0b001));
+    assert!(!timer.handle_interrupt());
+    Simulated::set("CNTV_CTL_EL0", 0b101);
+    assert!(timer.handle_interrupt());
+    assert_eq!(timer.expirations(), 1);
+    assert_eq!(Simulated::get("CNTV_CTL_EL0"), Some(0b010));
+
+    // 625 ticks a period, the interrupt comes late by two more.
+    timer.start_periodic(Duration::from_micros(10));
+    assert_eq!(Simulated::get("CNTV_CVAL_EL0"), Some(1625));
+    Simulated::set("CNTVCT_EL0", 1625 + 2 * 625 + 100);
+    Simulated::set("CNTV_CTL_EL0", 0b101);
+    assert!(timer.handle_interrupt());
+    assert_eq!(timer.expirations(), 4);
+    assert_eq!(Simulated::get("CNTV_CVAL_EL0"), Some(1625 + 3 * 625));
+    timer.stop();
+    assert_eq!(Simulated::get("CNTV_CTL_EL0"), Some(0b010));
+
+    // Nothing to wait for, the counter is past the deadline.
+    timer.wait_until(Instant::from_ticks(100));
+    assert_eq!(timer.expirations(), 4);
+
+    let timer = Timer::new(TimerKind::Physical);
+    assert_eq!(timer.kind().ppi(), 30);
+    Simulated::set("CNTPCT_EL0", 5);
+    timer.start_periodic(Duration::from_secs(1));
+    assert_eq!(Simulated::get("CNTP_CVAL_EL0"), Some(62_500_005));
+    assert_eq!(Simulated::get("CNTP_CTL_EL0"), Some(0b001));
+}
+
+#[test]
+fn test_simulated_gic_init_icc() {
+    Simulated::reset();
+    let mut gicd = vec![0u32; GICR_FRAME_SIZE / 4];
+    let mut gicr = vec![0u32; 2 * GICR_FRAME_SIZE / 4];
+    gicd[GICD_PIDR2_OFFSET / 4] = 3 << 4;
+    gicr[GICR_PIDR2_OFFSET / 4] = 3 << 4;
+
+    let mut gic = Gic::new(gicd.as_mut_ptr() as usize, gicr.as_mut_ptr() as usize, 1);
+    assert_eq!(gic.version(), GicVersion::GicV3);
+    gic.init_icc();
+    assert_eq!(Simulated::get("ICC_SRE_EL1"), Some(1));
+    assert_eq!(Simulated::get("ICC_CTLR_EL1"), Some(0));
+    assert_eq!(Simulated::get("ICC_PMR_EL1"), Some(0xff));
+    assert_eq!(Simulated::get("ICC_IGRPEN1_EL1"), Some(1));
+
+    assert!(gic.generate_sgi(3));
+    assert!(!gic.generate_sgi(16));
+    assert_eq!(Simulated::get("ICC_SGI1R_EL1"), Some(1 << 40 | 3 << 24));
+}
+
+#[test]
+fn test_gic_spis() {
+    let mut gicd = vec![0u32; GICR_FRAME_SIZE / 4];
+    let mut gicr = vec![0u32; 2 * GICR_FRAME_SIZE / 4];
+    gicd[GICD_PIDR2_OFFSET / 4] = 3 << 4;
+    gicr[GICR_PIDR2_OFFSET / 4] = 3 << 4;
+    gicd[GICD_TYPER_OFFSET / 4] = 8;
+    gicd[GICD_IGRPMODR_OFFSET / 4 + 1] = !0;
+
+    let mut gic = Gic::new(gicd.as_mut_ptr() as usize, gicr.as_mut_ptr() as usize, 1);
+    assert_eq!(gic.max_spi_id(), 257);
+    assert!(!gic.enable_spi(31, true));
+    assert!(!gic.enable_spi(257, true));
+    assert!(!gic.set_priority(16, 0));
+
+    // The UART of QEMU virt, SPI 1.
+    assert!(gic.enable_spi(33, true));
+    assert!(gic.pend_spi(40, true));
+    assert!(gic.set_priority(33, 0x80));
+    assert!(gic.set_trigger(33, Trigger::Edge));
+    assert!(gic.set_trigger(34, Trigger::Edge));
+    assert!(gic.set_trigger(34, Trigger::Level));
+    assert!(gic.set_group(33, InterruptGroup::NonSecureGroup1));
+    let affinity = Affinity::from(
+        MultiprocessorAffinityEl1::new()
+            .with_aff0(1)
+            .with_aff1(2)
+            .with_aff3(3),
+    );
+    assert_eq!(affinity.to_string(), "3.0.2.1");
+    assert!(gic.route_spi(33, SpiRoute::Pe(affinity)));
+    assert!(gic.route_spi(34, SpiRoute::Any));
+    assert!(!gic.route_spi(257, SpiRoute::Any));
+
+    assert_eq!(gicd[GICD_ISENABLER_OFFSET / 4 + 1], 1 << 1);
+    assert_eq!(gicd[GICD_ISPENDR_OFFSET / 4 + 1], 1 << 8);
+    assert_eq!(gicd[GICD_IPRIORITYR_OFFSET / 4 + 8], 0x80 << 8);
+    assert_eq!(gicd[GICD_ICFGR_OFFSET / 4 + 2], 0b10 << 2);
+    assert_eq!(gicd[GICD_IGROUPR_OFFSET / 4 + 1], 1 << 1);
+    assert_eq!(gicd[GICD_IGRPMODR_OFFSET / 4 + 1], !(1 << 1));
+    // GICD_IROUTER<33> and GICD_IROUTER<34>
+    assert_eq!(gicd[(GICD_IROUTER_OFFSET + 8) / 4], 0x0201);
+    assert_eq!(gicd[(GICD_IROUTER_OFFSET + 8) / 4 + 1], 3);
+    assert_eq!(gicd[(GICD_IROUTER_OFFSET + 16) / 4], 1 << 31);
+    assert_eq!(gic.spi_route(33), Some(SpiRoute::Pe(affinity)));
+    assert_eq!(gic.spi_route(34), Some(SpiRoute::Any));
+
+    gic.init_gicd();
+    assert_eq!(gic.spi_route(33), Some(SpiRoute::Pe(Default::default())));
+    assert_eq!(gicd[(GICD_IROUTER_OFFSET + 16) / 4], 0);
+
+    // Only the bit of the interrupt is written to the set-enable register.
+    assert!(gic.enable_ppi(23, true, 0));
+    assert!(gic.enable_ppi(27, true, 0));
+    assert_eq!(gicr[GICR_ISENABLER0_OFFSET / 4], 1 << 27);
+
+    // From an interrupt handler, through the clear-enable registers.
+    assert!(gic.disable_interrupt(23, 0));
+    assert_eq!(gicr[GICR_ICENABLER0_OFFSET / 4], 1 << 23);
+    assert!(gic.disable_interrupt(40, 0));
+    assert_eq!(gicd[GICD_ICENABLER_OFFSET / 4 + 1], 1 << 8);
+    assert!(!gic.disable_interrupt(257, 0));
+    assert_eq!(gic.cpu_of(Affinity::default()), Some(0));
+    let affinity = Affinity {
+        aff0: 1,
+        ..Default::default()
+    };
+    assert_eq!(gic.cpu_of(affinity), None);
+}
+
+#[test]
+fn test_simulated_irq_dispatch() {
+    static HANDLED: AtomicU64 = AtomicU64::new(0);
+
+    // Acknowledging the INTID 27 leaves the INTID 33 pending, then nothing.
+    fn on_timer(intid: u32) {
+        HANDLED.fetch_add(u64::from(intid), Ordering::Relaxed);
+        Simulated::set("ICC_IAR1_EL1", 33);
+    }
+    fn on_uart(intid: u32) {
+        HANDLED.fetch_add(u64::from(intid) << 16, Ordering::Relaxed);
+        Simulated::set("ICC_IAR1_EL1", 1023);
+    }
+
+    Simulated::reset();
+    let dispatcher = Dispatcher::new();
+    assert_eq!(
+        dispatcher.register(1020, on_uart),
+        Err(IrqError::Intid(1020))
+    );
+    assert_eq!(dispatcher.register(27, on_timer), Ok(()));
+    assert_eq!(dispatcher.register(27, on_uart), Err(IrqError::Busy(27)));
+    assert_eq!(dispatcher.register(33, on_uart), Ok(()));
+
+    assert_eq!(dispatcher.dispatch(ExceptionSource::Synchronous), None);
+    Simulated::set("ICC_IAR1_EL1", 1023);
+    assert_eq!(dispatcher.dispatch(ExceptionSource::Irq), Some(0));
+    assert_eq!(dispatcher.spurious(), 1);
+    assert_eq!(Simulated::get("ICC_EOIR1_EL1"), None);
+
+    Simulated::set("ICC_IAR1_EL1", 27);
+    assert_eq!(dispatcher.dispatch(ExceptionSource::Irq), Some(2));
+    assert_eq!(HANDLED.load(Ordering::Relaxed), 33 << 16 | 27);
+    assert_eq!(Simulated::get("ICC_EOIR1_EL1"), Some(33));
+    assert_eq!(Simulated::get("ICC_DIR_EL1"), None);
+    assert_eq!(dispatcher.spurious(), 1);
+
+    // The deactivation split from the priority drop.
+    Simulated::set("ICC_CTLR_EL1", 0b10);
+    Simulated::set("ICC_IAR1_EL1", 33);
+    assert_eq!(dispatcher.dispatch(ExceptionSource::Irq), Some(1));
+    assert_eq!(Simulated::get("ICC_EOIR1_EL1"), Some(33));
+    assert_eq!(Simulated::get("ICC_DIR_EL1"), Some(33));
+    assert_eq!(dispatcher.unhandled(), 0);
+
+    assert!(dispatcher.unregister(33).unwrap().is_some());
+    assert!(dispatcher.unregister(33).unwrap().is_none());
+
+    // Without a handler: disabled in the GIC, then ended. The simulated
+    // CPU interface acknowledges it again all the same, ending the loop.
+    let mut gicd = vec![0u32; GICR_FRAME_SIZE / 4];
+    let mut gicr = vec![0u32; 2 * GICR_FRAME_SIZE / 4];
+    gicd[GICD_PIDR2_OFFSET / 4] = 3 << 4;
+    gicd[GICD_TYPER_OFFSET / 4] = 8;
+    gicr[GICR_PIDR2_OFFSET / 4] = 3 << 4;
+    gicr[GICR_TYPER_OFFSET / 4] = 1 << 4;
+    let gic = Gic::new(gicd.as_mut_ptr() as usize, gicr.as_mut_ptr() as usize, 1);
+    assert!(dispatcher.attach(&gic));
+    assert!(!dispatcher.attach(&gic));
+
+    Simulated::set("ICC_IAR1_EL1", 40);
+    assert_eq!(dispatcher.dispatch(ExceptionSource::Irq), Some(2));
+    assert_eq!(dispatcher.unhandled(), 1);
+    assert_eq!(gicd[GICD_ICENABLER_OFFSET / 4 + 1], 1 << 8);
+    assert_eq!(Simulated::get("ICC_EOIR1_EL1"), Some(40));
+    assert_eq!(Simulated::get("ICC_DIR_EL1"), Some(40));
+
+    Simulated::set("ICC_IAR1_EL1", 20);
+    assert_eq!(dispatcher.dispatch(ExceptionSource::Irq), Some(2));
+    assert_eq!(dispatcher.unhandled(), 2);
+    assert_eq!(gicr[GICR_ICENABLER0_OFFSET / 4], 1 << 20);
+    assert_eq!(Simulated::get("ICC_EOIR1_EL1"), Some(20));
+    assert_eq!(Simulated::get("ICC_DIR_EL1"), Some(20));
+}
+
+#[test]
+fn test_simulated_sgi_mailbox() {
+    let mut gicd = vec![0u32; GICR_FRAME_SIZE / 4];
+    let mut gicr = vec![0u32; 2 * GICR_FRAME_SIZE / 4];
+    gicd[GICD_PIDR2_OFFSET / 4] = 3 << 4;
+    gicr[GICR_PIDR2_OFFSET / 4] = 3 << 4;
+    // GICR_TYPER.Last, affinity 0.2.1.3
+    gicr[GICR_TYPER_OFFSET / 4] = 1 << 4;
+    gicr[GICR_TYPER_OFFSET / 4 + 1] = 0x00_02_01_03;
+
+    Simulated::reset();
+    let gic = Gic::new(gicd.as_mut_ptr() as usize, gicr.as_mut_ptr() as usize, 1);
+    assert_eq!(gic.affinity(0).to_string(), "0.2.1.3");
+
+    assert!(gic.generate_sgi(3));
+    assert_eq!(Simulated::get("ICC_SGI1R_EL1"), Some(1 << 40 | 3 << 24));
+    assert!(!gic.send_sgi(16, &[gic.affinity(0)], SgiGroup::Group1));
+
+    // Aff0 0 and 5 of cluster 0.0.1 in one write, then 0.0.2.
+    let targets = [
+        Affinity {
+            aff0: 0,
+            aff1: 1,
+            aff2: 0,
+            aff3: 0,
+        },
+        Affinity {
+            aff0: 5,
+            aff1: 1,
+            aff2: 0,
+            aff3: 0,
+        },
+    ];
+    assert!(gic.send_sgi(2, &targets, SgiGroup::Group0));
+    assert_eq!(
+        Simulated::get("ICC_SGI0R_EL1"),
+        Some(2 << 24 | 1 << 16 | 0b10_0001)
+    );
+    let targets = [Affinity {
+        aff0: 1,
+        aff1: 2,
+        aff2: 0,
+        aff3: 0,
+    }];
+    assert!(gic.send_sgi(2, &targets, SgiGroup::AlternateGroup1));
+    assert_eq!(
+        Simulated::get("ICC_ASGI1R_EL1"),
+        Some(2 << 24 | 2 << 16 | 0b10)
+    );
+
+    // Aff0 17 needs the range selector, Aff3 1 the A3V.
+    let targets = [Affinity {
+        aff0: 17,
+        aff1: 0,
+        aff2: 0,
+        aff3: 1,
+    }];
+    assert!(!gic.send_sgi(2, &targets, SgiGroup::Group1));
+    Simulated::set("ICC_CTLR_EL1", 1 << 18);
+    assert!(!gic.send_sgi(2, &targets, SgiGroup::Group1));
+    Simulated::set("ICC_CTLR_EL1", 1 << 18 | 1 << 15);
+    assert!(gic.send_sgi(2, &targets, SgiGroup::Group1));
+    assert_eq!(
+        Simulated::get("ICC_SGI1R_EL1"),
+        Some(1 << 48 | 1 << 44 | 2 << 24 | 0b10)
+    );
+
+    let mailbox = Mailbox::<2>::new(1);
+    assert!(!mailbox.handle(0));
+    assert_eq!(mailbox.post(&gic, 1, |x| x, 0), Err(IpiError::Cpu(1)));
+    assert_eq!(mailbox.post(&gic, 0, |x| x * 2, 21), Ok(()));
+    assert_eq!(
+        Simulated::get("ICC_SGI1R_EL1"),
+        Some(2 << 32 | 1 << 16 | 1 << 24 | 1 << 3)
+    );
+    assert_eq!(mailbox.post(&gic, 0, |x| x, 0), Err(IpiError::Busy(0)));
+
+    // The SGI handler of CPU 0.
+    assert!(mailbox.handle(0));
+    assert!(!mailbox.handle(0));
+    assert_eq!(mailbox.wait(0), Ok(42));
+    assert_eq!(mailbox.wait(0), Err(IpiError::NotPosted(0)));
+    assert_eq!(mailbox.post(&gic, 0, |x| x + 1, 1), Ok(()));
+
+    // Not an SGI: nothing sent, the slot stays empty.
+    let mailbox = Mailbox::<1>::new(16);
+    Simulated::reset();
+    assert_eq!(mailbox.post(&gic, 0, |x| x, 0), Err(IpiError::Sgi(0)));
+    assert_eq!(Simulated::get("ICC_SGI1R_EL1"), None);
+    assert!(!mailbox.handle(0));
+    assert_eq!(mailbox.wait(0), Err(IpiError::NotPosted(0)));
+}
+
+#[test]
+fn test_simulated_pmu() {
+    Simulated::reset();
+    assert!(Pmu::new().is_none());
+
+    Simulated::set("ID_AA64DFR0_EL1", 0b0100 << 8);
+    Simulated::set("PMCR_EL0", 2 << 11);
+    Simulated::set("PMCEID0_EL0", 1 << 0x11 | 1 << 0x05);
+    let pmu = Pmu::new().expect("PMUv3.1");
+    assert_eq!(pmu.counters(), 2);
+    assert_eq!(Simulated::get("PMCR_EL0"), Some(2 << 11 | 0b0100_0111));
+    assert!(pmu.supports(Event::CpuCycles));
+    assert!(pmu.supports(Event::L1dTlbRefill));
+    assert!(!pmu.supports(Event::InstRetired));
+    assert!(!pmu.supports(Event::DtlbWalk));
+
+    assert_eq!(
+        pmu.start(&[Event::CpuCycles, Event::L1dTlbRefill, Event::InstRetired]),
+        Err(PmuError::TooManyEvents {
+            events: 3,
+            counters: 2
+        })
+    );
+
+    Simulated::set("PMCCNTR_EL0", 100);
+    Simulated::set("PMEVCNTR1_EL0", 0xffff_fffe);
+    let (result, measurement) = pmu
+        .measure(&[Event::CpuCycles, Event::L1dTlbRefill], || {
+            Simulated::set("PMCCNTR_EL0", 350);
+            Simulated::set("PMEVCNTR1_EL0", 3);
+            42
+        })
+        .unwrap();
+    assert_eq!(result, 42);
+    assert_eq!(Simulated::get("PMCNTENSET_EL0"), Some(1 << 31 | 0b11));
+    assert_eq!(Simulated::get("PMEVTYPER1_EL0"), Some(0x05));
+    assert_eq!(measurement.cycles, 250);
+    assert_eq!(measurement.count(Event::CpuCycles), Some(0));
+    assert_eq!(measurement.count(Event::L1dTlbRefill), Some(5));
+    assert_eq!(measurement.count(Event::InstRetired), None);
+    assert_eq!(
+        measurement.to_string(),
+        "cycles: 250, CPU_CYCLES: 0, L1D_TLB_REFILL: 5"
+    );
+    assert_eq!(Simulated::get("PMCCFILTR_EL0"), Some(0));
+
+    pmu.overflow_after(1000);
+    assert_eq!(Simulated::get("PMCCNTR_EL0"), Some(1000u64.wrapping_neg()));
+    Simulated::set("PMOVSSET_EL0", 1 << 31 | 0b10);
+    let overflows = Pmu::take_overflows();
+    assert!(overflows.c());
+    assert_eq!(overflows.p(), 0b10);
+    assert_eq!(Simulated::get("PMOVSCLR_EL0"), Some(1 << 31 | 0b10));
+}
+
+#[test]
+fn test_simulated_mmu_enable() {
+    Simulated::reset();
+    let mut space = vec![0; 0x10000];
+    let page_tables =
+        PageTableSpace::new(0x4024_8000, &mut space).expect("Can initialize page tables");
+
+    let tcr2_el1 = mmu::enable(&page_tables, TranslationConfig::default());
+    assert!(tcr2_el1.is_none());
+    let ttbr0_el1 = TranslationBase0El1::read();
+    assert_eq!(u64::from(ttbr0_el1), 0x4024_8000);
+    assert!(!ttbr0_el1.cnp());
+    assert_eq!(TranslationControlEl1::read().t0sz(), 16);
+    assert_eq!(TranslationControlEl1::read().tbi0(), 0);
+    assert_eq!(Simulated::get("TCR2_EL1"), None);
+    let sctlr_el1 = SystemControlEl1::read();
+    assert_eq!(
+        (sctlr_el1.m(), sctlr_el1.a(), sctlr_el1.c(), sctlr_el1.i()),
+        (1, 1, 1, 1)
+    );
+
+    Simulated::reset();
+    let mut space = vec![0; 0x10000];
+    let page_tables =
+        PageTableSpace::new_d128(0x4024_8000, &mut space).expect("Can initialize page tables");
+    let config = TranslationConfig {
+        tbi: true,
+        pie: true,
+        poe: false,
+    };
+    let tcr2_el1 = mmu::enable(&page_tables, config).expect("TCR2_EL1 is set up");
+    assert_eq!((tcr2_el1.pie(), tcr2_el1.poe(), tcr2_el1.d128()), (1, 0, 1));
+    assert_eq!(Simulated::get("TCR2_EL1"), Some(tcr2_el1.into()));
+    assert_eq!(Simulated::get128("TTBR0_EL1").map(|v| v >> 64), Some(0));
+    assert_eq!(TranslationControlEl1::read().t0sz(), 20);
+    assert_eq!(TranslationControlEl1::read().tbi0(), 1);
+    assert!(Simulated::get("PIR_EL1").is_some());
+    assert_eq!(Simulated::get("POR_EL1"), None);
+}
+
+#[test]
+fn test_survey_record() {
+    let mut record = String::new();
+    let midr = MainIdEl1::from(0x410f_d083u64);
+    survey::write_record(&mut record, "boot", &midr).unwrap();
+    assert_eq!(
+        record,
+        concat!(
+            r#"{"stage":"boot","register":"MIDR_EL1","bits":"0x000000410fd083","fields":{"#,
+            r#""revision":"3","part_num":"d08","architecture":"f","variant":"0","implementer":"41"}}"#,
+            "\n"
+        )
+    );
+
+    // The tuple structs and the nested values.
+    let mut record = String::new();
+    let mair = MemoryAttributeIndirectionEl1::from(0xffbb_4400_u64);
+    survey::write_record(&mut record, "a \"quoted\" stage", &mair).unwrap();
+    assert_eq!(
+        record,
+        concat!(
+            r#"{"stage":"a \"quoted\" stage","register":"MAIR_EL1","bits":"0x000000ffbb4400","#,
+            r#""fields":{"value":"[0, 44, bb, ff, 0, 0, 0, 0]"}}"#,
+            "\n"
+        )
+    );
+
+    let mut record = String::new();
+    let isar0 = InstructionSetFeatures0El1::from(0x7u64 << 20);
+    survey::write_record(&mut record, "boot", &isar0).unwrap();
+    assert!(record.contains(r#""atomic":"Unknown(7)","#));
+    assert!(record.ends_with("\"}}\n"));
+}
+
+#[test]
+fn test_register_fields() {
+    fn compose<R: RegisterFields>(fields: &[(&str, u64)]) -> u64 {
+        fields.iter().fold(0, |bits, &(name, value)| {
+            R::field(name).unwrap().insert(bits, value).unwrap()
+        })
+    }
+
+    let tcr = TranslationControlEl1::new()
+        .with_t0sz(16)
+        .with_sh0(3)
+        .with_tg0(TranslationGranule0::_16KB)
+        .with_tg1(TranslationGranule1::_64KB)
+        .with_ips(IntermPhysAddrSize::_48_bits_256TB)
+        .with_tbi0(1)
+        .with_mtx1(1);
+    let tg0 = TranslationControlEl1::field("tg0").unwrap();
+    let ips = TranslationControlEl1::field("ips").unwrap();
+    assert_eq!(
+        compose::<TranslationControlEl1>(&[
+            ("t0sz", 16),
+            ("sh0", 3),
+            ("tg0", tg0.value("_16KB").unwrap()),
+            ("tg1", 0b11),
+            ("ips", ips.value("_48_bits_256TB").unwrap()),
+            ("tbi0", 1),
+            ("mtx1", 1),
+        ]),
+        tcr.into()
+    );
+    assert_eq!(tg0.value_name(tg0.get(tcr.into())), Some("_16KB"));
+    assert_eq!(tg0.insert(0, 4), None);
+    assert_eq!(TranslationControlEl1::field("_mbz0"), None);
+
+    let sctlr = SystemControlEl1::new()
+        .with_m(1)
+        .with_n_twe(1)
+        .with_tcf(2)
+        .with_twedel(0xf)
+        .with_tidcp(1);
+    assert_eq!(
+        compose::<SystemControlEl1>(&[
+            ("m", 1),
+            ("n_twe", 1),
+            ("tcf", 2),
+            ("twedel", 0xf),
+            ("tidcp", 1)
+        ]),
+        u64::from(sctlr)
+    );
+
+    let pa_range = MmFeatures0El1::field("pa_range").unwrap();
+    let mmfr0 = MmFeatures0El1::from(compose::<MmFeatures0El1>(&[
+        ("pa_range", pa_range.value("_52_bits_4PB").unwrap()),
+        ("t_gran4", 0xf),
+        ("ecv", 2),
+    ]));
+    assert!(matches!(mmfr0.pa_range(), MmfPaRange::_52_bits_4PB));
+    assert!(matches!(mmfr0.t_gran4(), MmfTGran4KB::No));
+    assert_eq!(mmfr0.ecv(), 2);
+
+    // The tables cover the registers without overlaps, and each field is
+    // where the accessor of the type reads it: with all the other bits
+    // set, a field placed or sized differently reads another value.
+    fn check_fields<R: RegisterFields>() {
+        let mut seen = 0u64;
+        for field in R::FIELDS {
+            assert_eq!(seen & field.mask(), 0, "{} {}", R::NAME, field.name);
+            seen |= field.mask();
+
+            let values = if field.values.is_empty() {
+                vec![0, 1, field.mask() >> field.lsb]
+            } else {
+                field.values.iter().map(|&(_, value)| value).collect()
+            };
+            for value in values {
+                assert_eq!(
+                    R::read_field(!field.mask() | value << field.lsb, field.name),
+                    Some(value),
+                    "{} {}",
+                    R::NAME,
+                    field.name
+                );
+            }
+        }
+    }
+    check_fields::<CurrentEl>();
+    check_fields::<MainIdEl1>();
+    check_fields::<SavedProgramStateEl1>();
+    check_fields::<ExceptionSyndromeEl1>();
+    check_fields::<MemoryAttributeIndirectionEl1>();
+    check_fields::<TranslationBase0El1>();
+    check_fields::<TranslationBase1El1>();
+    check_fields::<TranslationControlEl1>();
+    check_fields::<SystemControlEl1>();
+    check_fields::<MmFeatures0El1>();
+}
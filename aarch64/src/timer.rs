@@ -0,0 +1,313 @@
+//! The Generic Timer
+//!
+//! The system counter counts up at `CNTFRQ_EL0` ticks per second, the
+//! virtual count being the physical one less `CNTVOFF_EL2`. Each of the
+//! EL1 virtual and physical timers fires its PPI once its counter reaches
+//! the compare value, and keeps it asserted until the compare value moves
+//! past the counter or the timer is masked or disabled.
+//!
+//! `Timer` builds the one-shot and the periodic deadlines on that, and the
+//! delays: `busy_wait` polls the counter, `wait` sleeps in WFI until the
+//! deadline. WFI wakes on a pending interrupt also when `PSTATE.I` masks
+//! it, so `wait` works with the IRQs masked as long as the PPI is enabled
+//! in the GIC (`Timer::enable_interrupt`).
+
+use core::ops::Add;
+use core::ops::Sub;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+use crate::gic::Gic;
+use crate::regs::access::ReadableRegister;
+use crate::regs::access::WritableRegister;
+use crate::regs::CounterFrequencyEl0;
+use crate::regs::PhysicalCountEl0;
+use crate::regs::PhysicalTimerCompareEl0;
+use crate::regs::PhysicalTimerControlEl0;
+use crate::regs::VirtualCountEl0;
+use crate::regs::VirtualTimerCompareEl0;
+use crate::regs::VirtualTimerControlEl0;
+
+/// The INTID of the EL1 virtual timer PPI.
+pub const VIRTUAL_TIMER_PPI: u64 = 27;
+/// The INTID of the EL1 physical timer PPI.
+pub const PHYSICAL_TIMER_PPI: u64 = 30;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// The counter frequency in Hz
+pub fn frequency() -> u64 {
+    CounterFrequencyEl0::read().clockfreq()
+}
+
+/// A point in time, in the ticks of the counter of the timer it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    pub const fn from_ticks(ticks: u64) -> Self {
+        Self(ticks)
+    }
+
+    pub const fn ticks(&self) -> u64 {
+        self.0
+    }
+
+    /// Zero if `earlier` is later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        to_duration(self.0.saturating_sub(earlier.0))
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(to_ticks(duration)?).map(Self)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub(to_ticks(duration)?).map(Self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// The ticks in `duration` rounded up, so that the delays are never short.
+fn to_ticks(duration: Duration) -> Option<u64> {
+    let ticks = (duration.as_nanos() * u128::from(frequency())).div_ceil(NANOS_PER_SEC);
+    u64::try_from(ticks).ok()
+}
+
+fn to_duration(ticks: u64) -> Duration {
+    let frequency = frequency();
+    assert!(frequency != 0, "CNTFRQ_EL0 is not set");
+    let nanos = u128::from(ticks) * NANOS_PER_SEC / u128::from(frequency);
+    Duration::new(
+        (nanos / NANOS_PER_SEC) as u64,
+        (nanos % NANOS_PER_SEC) as u32,
+    )
+}
+
+#[cfg(all(target_arch = "aarch64", not(test)))]
+fn wait_for_interrupt() {
+    // SAFETY: only waits.
+    unsafe { core::arch::asm!("wfi", options(nomem, nostack, preserves_flags)) };
+}
+
+#[cfg(not(all(target_arch = "aarch64", not(test))))]
+fn wait_for_interrupt() {
+    core::hint::spin_loop();
+}
+
+/// Which of the EL1 timers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerKind {
+    /// `CNTV_*_EL0` and the virtual count
+    Virtual,
+    /// `CNTP_*_EL0` and the physical count
+    Physical,
+}
+
+impl TimerKind {
+    pub fn ppi(self) -> u64 {
+        match self {
+            TimerKind::Virtual => VIRTUAL_TIMER_PPI,
+            TimerKind::Physical => PHYSICAL_TIMER_PPI,
+        }
+    }
+}
+
+/// A timer of the current CPU, meant for a `static` shared with the IRQ
+/// handler calling `handle_interrupt`
+#[derive(Debug)]
+pub struct Timer {
+    kind: TimerKind,
+    /// The period of the periodic deadline in ticks, 0 for a one-shot one.
+    period: AtomicU64,
+    /// The deadlines passed.
+    expirations: AtomicU64,
+    /// The comparator is borrowed by `wait`, its firing is not a deadline.
+    waiting: AtomicBool,
+}
+
+impl Timer {
+    pub const fn new(kind: TimerKind) -> Self {
+        Self {
+            kind,
+            period: AtomicU64::new(0),
+            expirations: AtomicU64::new(0),
+            waiting: AtomicBool::new(false),
+        }
+    }
+
+    pub fn kind(&self) -> TimerKind {
+        self.kind
+    }
+
+    /// Enables the PPI of the timer for `cpu`.
+    #[must_use]
+    pub fn enable_interrupt(&self, gic: &mut Gic, cpu: usize) -> bool {
+        gic.enable_ppi(self.kind.ppi(), true, cpu)
+    }
+
+    pub fn now(&self) -> Instant {
+        Instant(match self.kind {
+            TimerKind::Virtual => VirtualCountEl0::read().virtualcount(),
+            TimerKind::Physical => PhysicalCountEl0::read().physicalcount(),
+        })
+    }
+
+    /// The control register, the physical one has the same layout.
+    fn control(&self) -> VirtualTimerControlEl0 {
+        match self.kind {
+            TimerKind::Virtual => VirtualTimerControlEl0::read(),
+            TimerKind::Physical => u64::from(PhysicalTimerControlEl0::read()).into(),
+        }
+    }
+
+    fn set_control(&self, enable: bool, masked: bool) {
+        let control = VirtualTimerControlEl0::new()
+            .with_enable(enable)
+            .with_imask(masked);
+        match self.kind {
+            TimerKind::Virtual => control.store(),
+            TimerKind::Physical => PhysicalTimerControlEl0::from(u64::from(control)).store(),
+        }
+    }
+
+    fn compare(&self) -> u64 {
+        match self.kind {
+            TimerKind::Virtual => VirtualTimerCompareEl0::read().comparevalue(),
+            TimerKind::Physical => PhysicalTimerCompareEl0::read().comparevalue(),
+        }
+    }
+
+    fn set_compare(&self, ticks: u64) {
+        match self.kind {
+            TimerKind::Virtual => VirtualTimerCompareEl0::new()
+                .with_comparevalue(ticks)
+                .store(),
+            TimerKind::Physical => PhysicalTimerCompareEl0::new()
+                .with_comparevalue(ticks)
+                .store(),
+        }
+    }
+
+    fn arm(&self, deadline: u64) {
+        self.set_compare(deadline);
+        self.set_control(true, false);
+    }
+
+    /// Fires once at `deadline`, replacing the deadline set before.
+    pub fn set_deadline(&self, deadline: Instant) {
+        self.period.store(0, Ordering::Relaxed);
+        self.arm(deadline.0);
+    }
+
+    /// Fires every `period` from now on, replacing the deadline set before.
+    pub fn start_periodic(&self, period: Duration) {
+        let ticks = to_ticks(period).expect("period too long").max(1);
+        self.period.store(ticks, Ordering::Relaxed);
+        self.arm(self.now().0 + ticks);
+    }
+
+    /// Cancels the deadline.
+    pub fn stop(&self) {
+        self.period.store(0, Ordering::Relaxed);
+        self.set_control(false, true);
+    }
+
+    /// The deadlines passed since the start.
+    pub fn expirations(&self) -> u64 {
+        self.expirations.load(Ordering::Relaxed)
+    }
+
+    /// Acknowledges the timer PPI: moves a periodic deadline to the next
+    /// period, skipping the missed ones, and disables a one-shot one.
+    /// `false` if the timer has not fired.
+    pub fn handle_interrupt(&self) -> bool {
+        let control = self.control();
+        if !control.enable() || control.imask() || !control.istatus() {
+            return false;
+        }
+        if self.waiting.load(Ordering::Relaxed) {
+            self.set_control(true, true);
+            return true;
+        }
+
+        let period = self.period.load(Ordering::Relaxed);
+        if period == 0 {
+            self.expirations.fetch_add(1, Ordering::Relaxed);
+            self.set_control(false, true);
+            return true;
+        }
+        let compare = self.compare();
+        let passed = (self.now().0.saturating_sub(compare) / period) + 1;
+        self.expirations.fetch_add(passed, Ordering::Relaxed);
+        self.set_compare(compare + passed * period);
+        true
+    }
+
+    /// Polls the counter until `deadline`.
+    pub fn busy_wait_until(&self, deadline: Instant) {
+        while self.now() < deadline {
+            core::hint::spin_loop();
+        }
+    }
+
+    pub fn busy_wait(&self, duration: Duration) {
+        self.busy_wait_until(self.now() + duration);
+    }
+
+    /// Sleeps in WFI until `deadline`. An idle timer is armed for the
+    /// deadline meanwhile, a running periodic one wakes the CPU on each
+    /// tick. With a one-shot deadline later than `deadline` pending, this
+    /// polls instead, as it does while a passed deadline waits for
+    /// `handle_interrupt` with the IRQs masked.
+    pub fn wait_until(&self, deadline: Instant) {
+        while self.now() < deadline {
+            let control = self.control();
+            if !control.enable() {
+                self.waiting.store(true, Ordering::Relaxed);
+                self.arm(deadline.0);
+            } else if !self.waiting.load(Ordering::Relaxed)
+                && self.period.load(Ordering::Relaxed) == 0
+                && self.compare() > deadline.0
+            {
+                core::hint::spin_loop();
+                continue;
+            }
+            wait_for_interrupt();
+        }
+        if self.waiting.swap(false, Ordering::Relaxed) {
+            self.set_control(false, true);
+        }
+    }
+
+    pub fn wait(&self, duration: Duration) {
+        self.wait_until(self.now() + duration);
+    }
+}
@@ -3,6 +3,8 @@
 TARGET_JSON="./lab/lab.json"
 TARGET="lab"
 CARGO_OPT="-Zbuild-std=core -Zbuild-std-features=compiler-builtins-mem"
+# Signed return addresses and landing pads, see `aarch64::pauth`.
+export RUSTFLAGS="${RUSTFLAGS} -Zbranch-protection=pac-ret,bti"
 
 cargo clippy --target ${TARGET_JSON} ${CARGO_OPT} -p aarch64-lab
 cargo build --release --target ${TARGET_JSON} ${CARGO_OPT} -p aarch64-lab
@@ -4,10 +4,24 @@
 const USE_SEMIHOSTING: bool = false;
 const SETUP_MMU: bool = true;
 const NUM_CPUS: usize = 1;
-
-// TODO: qemu virt-9.2 specific
-const GICD_BASE: u64 = 0x08000000;
-const GICR_BASE: u64 = 0x080a0000;
+const PROVOKE_TAG_CHECK_FAULT: bool = false;
+/// Emit the JSON lines of the register survey, see `aarch64::survey`
+const SURVEY: bool = true;
+/// Write the survey to a file on the host rather than to the console
+const SURVEY_TO_FILE: bool = false;
+const SURVEY_FILE: &core::ffi::CStr = c"survey.jsonl";
+
+/// The DTB node of the GIC, its `reg` has the GICD and then the GICRs.
+const GIC_COMPATIBLE: &[u8] = b"arm,gic-v3";
+const PL011_COMPATIBLE: &[u8] = b"arm,pl011";
+/// The DTB node of the PMU, its `interrupts` has the overflow PPI.
+const PMU_COMPATIBLE: &[u8] = b"arm,armv8-pmuv3";
+/// The cycles until the cycle counter overflows.
+const PMU_OVERFLOW_CYCLES: u64 = 1_000_000;
+/// The SPI 1 of the PL011.
+const UART_SPI: u64 = 33;
+/// The SGI of the cross-CPU calls.
+const CALL_SGI: u64 = 5;
 
 core::arch::global_asm!(include_str!("start.S"));
 
@@ -38,7 +52,8 @@ mod image_data {
         fn _base();
         fn _end();
         fn _image_size();
-        fn _payload_start();
+        fn _stack_top();
+        fn _stack_bot();
     }
 
     pub fn base() -> usize {
@@ -53,28 +68,71 @@ mod image_data {
         _image_size as usize
     }
 
-    pub fn payload_start() -> usize {
-        _payload_start as usize
+    pub fn stack_start() -> usize {
+        _stack_top as usize
+    }
+
+    pub fn stack_end() -> usize {
+        _stack_bot as usize
     }
 }
 
 mod reloc;
 
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+use aarch64::debug::Debug;
+use aarch64::debug::DebugEvent;
+use aarch64::debug::Resume;
+use aarch64::debug::WatchAccess;
+use aarch64::fdt::Device;
+use aarch64::fdt::Fdt;
+use aarch64::features::CpuFeatures;
+use aarch64::features::Feature;
 use aarch64::gic;
+use aarch64::gic::Affinity;
 use aarch64::gic::Gic;
-use aarch64::gic::GICR_FRAME_SIZE;
+use aarch64::gic::SpiRoute;
+use aarch64::ipi::Mailbox;
+use aarch64::irq::Dispatcher;
+use aarch64::memmap::MemoryMap;
+use aarch64::memmap::RegionKind;
+use aarch64::memmap::Reservation;
 use aarch64::mmu;
 use aarch64::mmu::PageTableSpace;
+use aarch64::mmu::PermissionModel;
+use aarch64::mmu::TranslationConfig;
+use aarch64::mte;
+use aarch64::pauth;
+use aarch64::pauth::Keys;
+use aarch64::pauth::Protection;
 use aarch64::pl011;
-use aarch64::pl011::PL011_BASE;
+use aarch64::pmu::Counter;
+use aarch64::pmu::Event;
+use aarch64::pmu::Pmu;
 use aarch64::register;
 use aarch64::regs::access::Aarch64Register;
+use aarch64::regs::access::ReadWriteRegister;
+use aarch64::regs::access::ReadableRegister;
+use aarch64::regs::access::WritableRegister;
 use aarch64::regs::*;
+use aarch64::rng::Rng;
 use aarch64::semihosting;
-
-fn print_registers(out: &mut dyn core::fmt::Write) {
+use aarch64::semihosting::OpenMode;
+use aarch64::survey;
+use aarch64::sve;
+use aarch64::timer;
+use aarch64::timer::Timer;
+use aarch64::timer::TimerKind;
+
+fn print_registers(out: &mut dyn core::fmt::Write, stage: &str) {
     let regs = [
         register!(MainIdEl1),
+        register!(RevisionIdEl1),
+        register!(MultiprocessorAffinityEl1),
         register!(ProcessorFeatures0El1),
         register!(ProcessorFeatures1El1),
         register!(MmFeatures0El1),
@@ -82,6 +140,11 @@ fn print_registers(out: &mut dyn core::fmt::Write) {
         register!(MmFeatures2El1),
         register!(MmFeatures3El1),
         register!(MmFeatures4El1),
+        register!(InstructionSetFeatures0El1),
+        register!(InstructionSetFeatures1El1),
+        register!(InstructionSetFeatures2El1),
+        register!(DebugFeatures0El1),
+        register!(PerfMonControlEl0),
         register!(CurrentEl),
         register!(SystemControlEl1),
         register!(VectorBaseEl1),
@@ -92,23 +155,128 @@ fn print_registers(out: &mut dyn core::fmt::Write) {
         register!(ExceptionLinkEl1),
         register!(ExceptionSyndromeEl1),
         register!(SavedProgramStateEl1),
+        register!(ArchFeatureAccessControlEl1),
+        register!(InterruptMask),
+        register!(ContextIdEl1),
+        register!(ThreadIdEl1),
+    ];
+    let el2_regs = [
+        register!(HypervisorConfigEl2),
+        register!(SystemControlEl2),
+        register!(VectorBaseEl2),
+        register!(TranslationControlEl2),
+        register!(TranslationBase0El2),
+        register!(MemoryAttributeIndirectionEl2),
+        register!(ArchFeatureTrapEl2),
+        register!(CounterHypControlEl2),
+        register!(VirtualizationProcessorIdEl2),
+        register!(VirtualizationMultiprocessorIdEl2),
+        register!(ExceptionLinkEl2),
+        register!(ExceptionSyndromeEl2),
+        register!(SavedProgramStateEl2),
+        register!(FaultAddressEl2),
+        register!(HypervisorIpaFaultAddressEl2),
     ];
 
-    for r in regs {
+    // The EL2 registers are undefined at EL1.
+    let at_el2 = matches!(CurrentEl::read().el(), El::EL2);
+
+    let mut survey_file = if SURVEY && SURVEY_TO_FILE {
+        semihosting::Semihosting.open(SURVEY_FILE, OpenMode::Append)
+    } else {
+        None
+    };
+
+    for r in regs
+        .into_iter()
+        .chain(el2_regs.into_iter().filter(|_| at_el2))
+    {
         r.load();
 
         let raw: u64 = r.bits();
         let name = r.name();
         writeln!(out, "{name}\t{raw:#016x?}: {r:x?}").ok();
+
+        if SURVEY {
+            let survey_out = match survey_file.as_mut() {
+                Some(file) => file as &mut dyn core::fmt::Write,
+                None => &mut *out,
+            };
+            survey::write_record(survey_out, stage, r).ok();
+        }
+    }
+
+    write!(out, "Features:").ok();
+    for feature in CpuFeatures::detect().iter() {
+        write!(out, " {feature:?}").ok();
     }
+    writeln!(out).ok();
 }
 
-fn setup_mmu(out: &mut dyn core::fmt::Write) {
-    let mut page_tables = PageTableSpace::new(
-        page_table_space::page_tables_phys_start(),
-        page_table_space::page_tables_area(),
-    )
-    .unwrap();
+fn has_memory_tagging() -> bool {
+    CpuFeatures::detect().has(Feature::Mte2)
+}
+
+/// Enables SVE and SME, and prints the vector lengths they support.
+fn report_vector_lengths(out: &mut dyn core::fmt::Write, features: &CpuFeatures) {
+    if sve::enable_sve(features) {
+        writeln!(
+            out,
+            "SVE vector lengths: {} bits",
+            sve::sve_vector_lengths()
+        )
+        .ok();
+    } else {
+        writeln!(out, "No SVE").ok();
+    }
+    if sve::enable_sme(features) {
+        writeln!(
+            out,
+            "SME streaming vector lengths: {} bits",
+            sve::sme_vector_lengths()
+        )
+        .ok();
+    } else {
+        writeln!(out, "No SME").ok();
+    }
+}
+
+/// The optional features of the stage 1 translation, all of them
+/// are controlled with TCR2_EL1.
+struct TranslationFeatures {
+    /// The Permission Indirection
+    pie: bool,
+    /// The Permission Overlay
+    poe: bool,
+    /// The 128-bit descriptors, these need `pie` too.
+    d128: bool,
+}
+
+fn translation_features() -> TranslationFeatures {
+    let features = CpuFeatures::detect();
+    let tcr2 = features.has(Feature::Tcr2);
+    let pie = tcr2 && features.has(Feature::S1Pie);
+    TranslationFeatures {
+        pie,
+        poe: tcr2 && features.has(Feature::S1Poe),
+        d128: pie && features.has(Feature::D128),
+    }
+}
+
+fn setup_mmu(out: &mut dyn core::fmt::Write, memory_map: &mut MemoryMap, pmu: Option<&Pmu>) {
+    let features = translation_features();
+    let mut page_tables = if features.d128 {
+        PageTableSpace::new_d128(
+            page_table_space::page_tables_phys_start(),
+            page_table_space::page_tables_area(),
+        )
+    } else {
+        PageTableSpace::new(
+            page_table_space::page_tables_phys_start(),
+            page_table_space::page_tables_area(),
+        )
+    }
+    .unwrap_or_else(|e| panic!("{e}"));
     writeln!(
         out,
         "Page tables are located at\t[{:#016x};{:#016x}]",
@@ -117,96 +285,43 @@ fn setup_mmu(out: &mut dyn core::fmt::Write) {
     )
     .ok();
 
-    let mut mair_el1 = MemoryAttributeIndirectionEl1::default();
-    mair_el1.store();
-
-    page_tables
-        .map_range(
-            image_data::base() as u64,
-            mmu::VirtualAddress::from(image_data::base() as u64),
-            image_data::size() as u64,
-            mair_el1
-                .get_index(MemoryAttributeEl1::Normal_WriteBack)
-                .expect("must be some WB memory available"),
-        )
-        .unwrap();
+    if features.pie {
+        // The PIIndex 0: read-write-execute at EL1, no access at EL0.
+        page_tables.set_permission_model(PermissionModel::Indirect(0));
+    }
 
-    let payload_size = 3 * 1024 * 1024;
-    page_tables
-        .map_range(
-            image_data::payload_start() as u64,
-            mmu::VirtualAddress::from(image_data::payload_start() as u64),
-            payload_size,
-            mair_el1
-                .get_index(MemoryAttributeEl1::Normal_WriteBack)
-                .expect("must be some WB memory available"),
-        )
-        .unwrap();
-
-    page_tables
-        .map_range(
-            GICD_BASE,
-            mmu::VirtualAddress::from(GICD_BASE),
-            gic::GICD_SIZE as u64,
-            mair_el1
-                .get_index(MemoryAttributeEl1::Device_nGnRnE)
-                .expect("must be some device attrs available"),
-        )
-        .unwrap();
-
-    page_tables
-        .map_range(
-            GICR_BASE,
-            mmu::VirtualAddress::from(GICR_BASE),
-            4 * GICR_FRAME_SIZE as u64,
-            mair_el1
-                .get_index(MemoryAttributeEl1::Device_nGnRnE)
-                .expect("must be some device attrs available"),
-        )
-        .unwrap();
+    let mte = has_memory_tagging();
+    let mut mair = MairBuilder::new();
+    let attrs = MemoryAttributes {
+        device: mair.add(MemoryAttributeEl1::DEVICE_NGNRNE).unwrap(),
+        normal: mair.add(MemoryAttributeEl1::NORMAL_WRITE_BACK).unwrap(),
+        tagged: mte.then(|| mair.add(MemoryAttributeEl1::NormalTagged).unwrap()),
+    };
+    mair.build().store();
 
-    writeln!(
-        out,
-        "running stride test at {:#x}",
-        image_data::payload_start()
-    )
-    .ok();
+    let payload_size = 3 * 1024 * 1024;
+    let payload_start = memory_map
+        .allocate(payload_size, PAGE_SIZE, Reservation::Payload)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let tagged_start = if mte {
+        let tagged_start = memory_map
+            .allocate(TAGGED_SIZE as u64, PAGE_SIZE, Reservation::Allocated)
+            .unwrap_or_else(|e| panic!("{e}"));
+        Some(tagged_start)
+    } else {
+        None
+    };
 
-    let dword_count = check_page_stride(
-        image_data::payload_start() as u64,
-        (image_data::payload_start() + payload_size as usize) as u64,
+    let guard_image = CpuFeatures::detect().has(Feature::Bti);
+    map_memory(
+        &mut page_tables,
+        memory_map,
+        &attrs,
+        tagged_start,
+        guard_image,
     );
-    writeln!(out, "dword count: {dword_count:#x}").ok();
-
-    page_tables
-        .map_pages(
-            PL011_BASE,
-            mmu::VirtualAddress::from(PL011_BASE),
-            1,
-            mmu::PageSize::Small,
-            mair_el1
-                .get_index(MemoryAttributeEl1::Device_nGnRnE)
-                .expect("must be some strongly ordered non-cacheable memory available"),
-        )
-        .unwrap();
-
-    TranslationBase0El1::new()
-        .with_asid(0)
-        .with_baddr(page_table_space::page_tables_phys_start() as u64)
-        .store();
-    TranslationBase1El1::new().store();
-    TranslationControlEl1::new()
-        .with_t0sz(16)
-        .with_irgn0(1)
-        .with_orgn0(1)
-        .with_sh0(3)
-        .with_tg0(TranslationGranule0::_4KB)
-        .with_epd1(1)
-        .with_tg1(TranslationGranule1::_4KB)
-        .with_ips(IntermPhysAddrSize::_48_bits_256TB)
-        // .with_ha(1) // Should checked against the MMU feature reg #1
-        // .with_hd(1) // Should checked against the MMU feature reg #1
-        .store();
+
+    run_page_stride(out, pmu, payload_start, payload_start + payload_size);
 
     writeln!(out, "Page tables use {:#x} bytes", page_tables.used_space()).ok();
     writeln!(
@@ -217,24 +332,205 @@ fn setup_mmu(out: &mut dyn core::fmt::Write) {
     .ok();
     writeln!(out, "Enabling MMU").ok();
 
-    let mut sctlr_el1 = SystemControlEl1::new();
-    sctlr_el1.load();
-    sctlr_el1.with_m(1).with_a(1).with_c(1).with_i(1).store();
+    let tcr2_el1 = mmu::enable(
+        &page_tables,
+        TranslationConfig {
+            tbi: mte,
+            pie: features.pie,
+            poe: features.poe,
+        },
+    );
 
     writeln!(out, "MMU enabled").ok();
+    if let Some(tcr2_el1) = tcr2_el1 {
+        writeln!(out, "TCR2_EL1\t{:#016x?}: {tcr2_el1:x?}", tcr2_el1.bits()).ok();
+    }
 
-    writeln!(
-        out,
-        "running stride test at {:#x}",
-        image_data::payload_start()
-    )
-    .ok();
+    if let Some(tagged_start) = tagged_start {
+        check_memory_tagging(out, tagged_start as *mut u64);
+    }
 
-    let dword_count = check_page_stride(
-        image_data::payload_start() as u64,
-        (image_data::payload_start() + payload_size as usize) as u64,
-    );
-    writeln!(out, "dword count: {dword_count:#x}").ok();
+    run_page_stride(out, pmu, payload_start, payload_start + payload_size);
+}
+
+/// The events counted along with the cycles around the stride test,
+/// those the PMU does not count are skipped.
+const STRIDE_EVENTS: [Event; 3] = [Event::InstRetired, Event::L1iTlbRefill, Event::L1dTlbRefill];
+
+fn run_page_stride(out: &mut dyn core::fmt::Write, pmu: Option<&Pmu>, start: u64, end: u64) {
+    writeln!(out, "running stride test at {start:#x}").ok();
+
+    let Some(pmu) = pmu else {
+        let dword_count = check_page_stride(start, end);
+        writeln!(out, "dword count: {dword_count:#x}").ok();
+        return;
+    };
+
+    let mut events = [Event::CpuCycles; STRIDE_EVENTS.len()];
+    let mut event_count = 0;
+    for event in STRIDE_EVENTS {
+        if pmu.supports(event) && event_count < pmu.counters() {
+            events[event_count] = event;
+            event_count += 1;
+        }
+    }
+    let (dword_count, measurement) = pmu
+        .measure(&events[..event_count], || check_page_stride(start, end))
+        .unwrap_or_else(|e| panic!("{e}"));
+    writeln!(out, "dword count: {dword_count:#x}, {measurement}").ok();
+}
+
+const PAGE_SIZE: u64 = mmu::PageSize::Small as u64;
+
+/// The MAIR slots the lab programs.
+struct MemoryAttributes {
+    device: MairIndex,
+    normal: MairIndex,
+    /// Only with FEAT_MTE2.
+    tagged: Option<MairIndex>,
+}
+
+/// Identity-maps the RAM as write-back and the MMIO windows as device
+/// memory, merging the adjacent regions with the same attributes. The
+/// image is mapped as Guarded Pages with `guard_image`.
+fn map_memory(
+    page_tables: &mut PageTableSpace,
+    memory_map: &MemoryMap,
+    attrs: &MemoryAttributes,
+    tagged_start: Option<u64>,
+    guard_image: bool,
+) {
+    let mut map = |base: u64, end: u64, memory_attribute_index, guarded| {
+        let base = base & !(PAGE_SIZE - 1);
+        let end = (end + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        page_tables.set_guarded(guarded);
+        page_tables
+            .map_range(
+                base,
+                mmu::VirtualAddress::from(base),
+                end - base,
+                memory_attribute_index,
+            )
+            .unwrap_or_else(|e| panic!("{e}"));
+    };
+
+    let mut pending: Option<(u64, u64, MairIndex, bool)> = None;
+    for r in memory_map.regions() {
+        let memory_attribute_index = if r.kind == RegionKind::Mmio {
+            attrs.device
+        } else if Some(r.base) == tagged_start {
+            attrs
+                .tagged
+                .expect("must be the tagged memory attribute programmed")
+        } else {
+            attrs.normal
+        };
+        // The generated code of the stride test has no landing pads.
+        let guarded = guard_image && r.kind == RegionKind::Reserved(Reservation::Image);
+        pending = match pending {
+            Some((base, end, index, g))
+                if end == r.base && index == memory_attribute_index && g == guarded =>
+            {
+                Some((base, r.end(), index, g))
+            }
+            _ => {
+                if let Some((base, end, index, g)) = pending {
+                    map(base, end, index, g);
+                }
+                Some((r.base, r.end(), memory_attribute_index, guarded))
+            }
+        };
+    }
+    if let Some((base, end, index, g)) = pending {
+        map(base, end, index, g);
+    }
+    page_tables.set_guarded(false);
+}
+
+fn find_device<'a>(fdt: &Fdt<'a>, compatible: &[u8]) -> Device<'a> {
+    fdt.find_compatible(compatible)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .unwrap_or_else(|| {
+            panic!(
+                "no {} in the DTB",
+                core::str::from_utf8(compatible).unwrap_or_default()
+            )
+        })
+}
+
+/// The RAM and the reservations from the DTB, plus what the image,
+/// its stack and page tables, and the devices take.
+fn build_memory_map(out: &mut dyn core::fmt::Write, fdt: &Fdt, dtb: usize) -> MemoryMap {
+    let mut memory_map = MemoryMap::from_fdt(fdt).unwrap_or_else(|e| panic!("{e}"));
+
+    let reservations = [
+        (
+            image_data::stack_start(),
+            image_data::stack_end(),
+            Reservation::Stack,
+        ),
+        (
+            page_table_space::page_tables_phys_start(),
+            page_table_space::page_tables_phys_end(),
+            Reservation::PageTables,
+        ),
+        (image_data::base(), image_data::end(), Reservation::Image),
+        (dtb, dtb + fdt.total_size(), Reservation::Dtb),
+    ];
+    for (start, end, reservation) in reservations {
+        let start = start as u64 & !(PAGE_SIZE - 1);
+        let end = (end as u64 + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        memory_map
+            .carve_out(start, end - start, reservation)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    for compatible in [GIC_COMPATIBLE, PL011_COMPATIBLE] {
+        for (base, size) in find_device(fdt, compatible).reg() {
+            memory_map
+                .add(base, size, RegionKind::Mmio)
+                .unwrap_or_else(|e| panic!("{e}"));
+        }
+    }
+
+    for r in memory_map.regions() {
+        writeln!(out, "[{:#016x};{:#016x}) {:?}", r.base, r.end(), r.kind).ok();
+    }
+
+    memory_map
+}
+
+const TAGGED_SIZE: usize = 64 * 1024;
+
+/// Enables synchronous tag checks and tags a chunk of `tagged`.
+fn check_memory_tagging(out: &mut dyn core::fmt::Write, tagged: *mut u64) {
+    // Never generate the tag 0 so tagged pointers differ
+    // from the untagged ones.
+    TagControlEl1::new().with_exclude(1).store();
+    RandomTagSeedEl1::new().with_seed(0x1234).store();
+
+    SystemControlEl1::modify(|r| r.with_ata(1).with_tcf(1));
+
+    let chunk_size = 4 * mte::TAG_GRANULE;
+    let chunk = mte::irg(tagged, 0);
+    // SAFETY: the chunk is in the tagged memory mapped above.
+    unsafe {
+        mte::tag_range(chunk, chunk_size);
+        chunk.write_volatile(0xdead_beef);
+        writeln!(
+            out,
+            "Tagged chunk at {chunk:#x?}, read back {:#x}, allocation tag {:#x}",
+            chunk.read_volatile(),
+            mte::tag_of(mte::ldg(chunk))
+        )
+        .ok();
+    }
+
+    if PROVOKE_TAG_CHECK_FAULT {
+        let wrong_tag = mte::with_tag(chunk, mte::tag_of(chunk) ^ 1);
+        // SAFETY: the memory is mapped, the tag check must fail.
+        unsafe { wrong_tag.write_volatile(0) };
+    }
 }
 
 /// This function "generates" a function that adds 1 to
@@ -268,8 +564,175 @@ fn check_page_stride(start: u64, end: u64) -> usize {
     dword_counter(1)
 }
 
+static DEBUG: Debug = Debug::new();
+
+/// Written by `debug_target` under a watchpoint.
+static WATCHED: AtomicU64 = AtomicU64::new(0);
+/// The instructions to step after the breakpoint.
+static STEPS_LEFT: AtomicUsize = AtomicUsize::new(0);
+
+#[inline(never)]
+fn debug_target(value: u64) -> u64 {
+    WATCHED.store(value, Ordering::Relaxed);
+    value + 1
+}
+
+fn on_debug_event(out: &mut dyn core::fmt::Write, event: DebugEvent) -> Resume {
+    writeln!(out, "Debug event {event:x?}").ok();
+    let steps_left = match event {
+        DebugEvent::Breakpoint { .. } => 3,
+        DebugEvent::Step { .. } => STEPS_LEFT.load(Ordering::Relaxed).saturating_sub(1),
+        _ => 0,
+    };
+    STEPS_LEFT.store(steps_left, Ordering::Relaxed);
+    if steps_left > 0 {
+        Resume::Step
+    } else {
+        Resume::Continue
+    }
+}
+
+/// Hits a breakpoint stepping a few instructions after it, a watchpoint
+/// and a BRK. The debug exceptions must be unmasked.
+fn check_debug(out: &mut dyn core::fmt::Write) {
+    DEBUG.enable();
+    writeln!(
+        out,
+        "{} breakpoints, {} watchpoints",
+        DEBUG.breakpoints(),
+        DEBUG.watchpoints()
+    )
+    .ok();
+
+    let target = debug_target as *const () as u64;
+    DEBUG
+        .set_breakpoint(0, target)
+        .unwrap_or_else(|e| panic!("{e}"));
+    DEBUG
+        .set_watchpoint(0, WATCHED.as_ptr() as u64, 8, WatchAccess::Store)
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    let value = debug_target(0x41);
+    // SAFETY: the debug exception handler skips the BRK.
+    unsafe { core::arch::asm!("brk #0x42") };
+    writeln!(out, "debug target returned {value:#x}").ok();
+
+    DEBUG.clear_breakpoint(0).unwrap_or_else(|e| panic!("{e}"));
+    DEBUG.clear_watchpoint(0).unwrap_or_else(|e| panic!("{e}"));
+}
+
+static TIMER: Timer = Timer::new(TimerKind::Virtual);
+
+/// The exception handler dispatches the IRQs and FIQs through this.
+static IRQS: Dispatcher = Dispatcher::new();
+/// Counted by `on_sgi`.
+static SGIS: AtomicUsize = AtomicUsize::new(0);
+
+fn on_sgi(_intid: u32) {
+    SGIS.fetch_add(1, Ordering::Relaxed);
+}
+
+fn on_timer(_intid: u32) {
+    TIMER.handle_interrupt();
+}
+
+/// Counted by `on_pmu_overflow`.
+static PMU_OVERFLOWS: AtomicUsize = AtomicUsize::new(0);
+
+fn on_pmu_overflow(_intid: u32) {
+    if Pmu::take_overflows().c() {
+        PMU_OVERFLOWS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Waits for the SGI sent before, then for a few timer ticks, all taken
+/// as IRQs returning to the WFI loops.
+fn check_irqs(out: &mut dyn core::fmt::Write) {
+    while SGIS.load(Ordering::Relaxed) == 0 {
+        // SAFETY: only waits.
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+    }
+
+    IRQS.register(TIMER.kind().ppi() as u32, on_timer)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let ticks = TIMER.expirations() + 5;
+    TIMER.start_periodic(Duration::from_millis(10));
+    while TIMER.expirations() < ticks {
+        // SAFETY: only waits.
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+    }
+    TIMER.stop();
+
+    writeln!(
+        out,
+        "IRQs: {} SGIs, {} timer ticks, {} PMU overflows, {} unhandled, {} spurious",
+        SGIS.load(Ordering::Relaxed),
+        TIMER.expirations(),
+        PMU_OVERFLOWS.load(Ordering::Relaxed),
+        IRQS.unhandled(),
+        IRQS.spurious()
+    )
+    .ok();
+}
+
+/// Runs the cross-CPU calls posted by the other CPUs.
+static MAILBOX: Mailbox<NUM_CPUS> = Mailbox::new(CALL_SGI);
+
+fn on_call(_intid: u32) {
+    // The lab runs on CPU 0 only.
+    MAILBOX.handle(0);
+}
+
+/// Calls a function on CPU 0 through the mailbox, the SGI targeting the
+/// affinity of its redistributor.
+fn check_call(out: &mut dyn core::fmt::Write, gic: &mut Gic) {
+    IRQS.register(CALL_SGI as u32, on_call)
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert!(gic.enable_sgi(CALL_SGI, true, 0));
+
+    let result = MAILBOX
+        .call(gic, 0, |argument| argument * 2, 21)
+        .unwrap_or_else(|e| panic!("{e}"));
+    writeln!(
+        out,
+        "Called on CPU 0 at {}: 21 * 2 = {result}",
+        gic.affinity(0)
+    )
+    .ok();
+}
+
+/// Times a busy and a WFI delay, and a few periodic ticks. Runs with the
+/// IRQs masked, WFI wakes on the pending timer PPI all the same.
+fn check_timer(out: &mut dyn core::fmt::Write, gic: &mut Gic) {
+    writeln!(out, "Counter at {} Hz", timer::frequency()).ok();
+    assert!(TIMER.enable_interrupt(gic, 0));
+    // SAFETY: not touching memory.
+    unsafe { core::arch::asm!("msr DAIFSet, #2", options(nomem, nostack)) };
+
+    let start = TIMER.now();
+    TIMER.busy_wait(Duration::from_millis(1));
+    writeln!(out, "Busy wait of 1ms took {:?}", TIMER.now() - start).ok();
+
+    let start = TIMER.now();
+    TIMER.wait(Duration::from_millis(10));
+    writeln!(out, "WFI wait of 10ms took {:?}", TIMER.now() - start).ok();
+
+    let start = TIMER.now();
+    TIMER.start_periodic(Duration::from_millis(5));
+    while TIMER.expirations() < 4 {
+        // SAFETY: only waits.
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+        TIMER.handle_interrupt();
+    }
+    TIMER.stop();
+    writeln!(out, "4 ticks of 5ms took {:?}", TIMER.now() - start).ok();
+
+    // SAFETY: not touching memory, the timer is stopped.
+    unsafe { core::arch::asm!("msr DAIFClr, #2", options(nomem, nostack)) };
+}
+
 #[no_mangle]
-fn start() {
+extern "C" fn start(dtb: usize) -> ! {
     let mut semi: semihosting::Semihosting = semihosting::Semihosting;
     let mut pl011: pl011::Pl011 = pl011::Pl011;
     let id = pl011.reset_and_init();
@@ -290,10 +753,44 @@ fn start() {
     )
     .ok();
 
-    print_registers(out);
+    writeln!(out, "DTB at {dtb:#x}").ok();
+    // SAFETY: the loader passes the DTB, and nothing writes over it.
+    let fdt = unsafe { Fdt::from_ptr(dtb as *const u8) }.unwrap_or_else(|e| panic!("{e}"));
+    let mut memory_map = build_memory_map(out, &fdt, dtb);
+
+    // Here rather than in a function as the return addresses signed before
+    // fail to authenticate, and `start` never returns.
+    let features = CpuFeatures::detect();
+    let pointer_auth = features.has(Feature::PAuth);
+    let mut rng = Rng::new();
+    writeln!(out, "Random numbers from {:?}", rng.source()).ok();
+    if pointer_auth {
+        Keys::generate(|| rng.next_u64_reseeded()).install();
+    }
+    pauth::enable(Protection {
+        instruction_keys: pointer_auth,
+        data_keys: pointer_auth,
+        strict_bti: features.has(Feature::Bti),
+    });
+    writeln!(
+        out,
+        "Pointer authentication {pointer_auth}, BTI {}",
+        features.has(Feature::Bti)
+    )
+    .ok();
+
+    let pmu = Pmu::new();
+    match &pmu {
+        Some(pmu) => writeln!(out, "PMU with {} event counters", pmu.counters()).ok(),
+        None => writeln!(out, "No PMU").ok(),
+    };
+
+    report_vector_lengths(out, &features);
+
+    print_registers(out, "boot");
     if SETUP_MMU {
-        setup_mmu(out);
-        print_registers(out);
+        setup_mmu(out, &mut memory_map, pmu.as_ref());
+        print_registers(out, "mmu");
     }
 
     // Try exception handler
@@ -311,10 +808,15 @@ fn start() {
         core::arch::asm!("msr DAIFClr, #0xf", options(nomem, nostack));
     }
 
-    let mut gic = Gic::new(GICD_BASE as usize, GICR_BASE as usize, NUM_CPUS);
+    let mut gic_reg = find_device(&fdt, GIC_COMPATIBLE).reg();
+    let (Some((gicd_base, _)), Some((gicr_base, _))) = (gic_reg.next(), gic_reg.next()) else {
+        panic!("no GICD and GICR in the DTB");
+    };
+    let mut gic = Gic::new(gicd_base as usize, gicr_base as usize, NUM_CPUS);
     gic.init_gicd();
     gic.wakeup_cpu_and_init_gicr(0);
     gic.init_icc();
+    assert!(IRQS.attach(&gic));
 
     writeln!(
         out,
@@ -324,12 +826,42 @@ fn start() {
     )
     .ok();
 
+    // `-trace "gicv3_dist*"` in run.sh shows the GICD_IROUTER writes.
+    assert!(gic.route_spi(UART_SPI, SpiRoute::Pe(Affinity::current())));
+    writeln!(
+        out,
+        "UART SPI {UART_SPI} routed to {:?}",
+        gic.spi_route(UART_SPI)
+    )
+    .ok();
+
+    if let Some(pmu) = &pmu {
+        let ppi = gic::dt_intid(find_device(&fdt, PMU_COMPATIBLE).interrupts())
+            .expect("the PMU interrupt is a PPI");
+        IRQS.register(ppi as u32, on_pmu_overflow)
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(gic.enable_ppi(ppi, true, 0));
+        pmu.start(&[]).unwrap_or_else(|e| panic!("{e}"));
+        pmu.overflow_after(PMU_OVERFLOW_CYCLES);
+        pmu.enable_overflow_interrupt(Counter::Cycles, true);
+        writeln!(
+            out,
+            "PMU overflow PPI {ppi}, after {PMU_OVERFLOW_CYCLES} cycles"
+        )
+        .ok();
+    }
+
+    check_timer(out, &mut gic);
+    check_debug(out);
+
     let irq_num = 4;
+    IRQS.register(irq_num as u32, on_sgi)
+        .unwrap_or_else(|e| panic!("{e}"));
     assert!(gic.enable_sgi(irq_num, true, 0));
     assert!(gic.pend_sgi(irq_num, true, 0));
     assert!(gic.generate_sgi(irq_num));
-
-    unsafe { core::arch::asm!("1: wfi; b 1b") };
+    check_irqs(out);
+    check_call(out, &mut gic);
 
     writeln!(
         out,
@@ -339,7 +871,7 @@ fn start() {
     if USE_SEMIHOSTING {
         semi.exit(0)
     } else {
-        unsafe { core::arch::asm!("1: wfe; b 1b") };
+        unsafe { core::arch::asm!("1: wfe; b 1b", options(noreturn)) }
     }
 }
 
@@ -354,16 +886,33 @@ unsafe extern "C" fn exception_handler(exception_frame: *mut ExceptionFrame) {
         &mut pl011 as &mut dyn core::fmt::Write
     };
 
+    let frame = unsafe { exception_frame.as_mut().expect("valid exception frame") };
+    // ESR_EL1 and FAR_EL1 are stale for the asynchronous exceptions.
+    if IRQS.dispatch(frame.source).is_some() {
+        return;
+    }
+    let syndrome = ExceptionSyndromeEl1::read();
+    let fault_address = FaultAddressEl1::read().bits();
+    if DEBUG.handle(frame, syndrome, fault_address, |event| {
+        on_debug_event(out, event)
+    }) {
+        return;
+    }
+
     writeln!(out, "!!!!!!!!!!!! EXCEPTION !!!!!!!!!!!!!!").ok();
 
-    let frame = unsafe { exception_frame.as_mut().expect("valid exception frame") };
     writeln!(out, "Exception frame {frame:x?}").ok();
 
     // Get the interesting registers
     let el = register!(CurrentEl);
     let elr = register!(ExceptionLinkEl1);
     let esr = register!(ExceptionSyndromeEl1);
-    let regs = [el, elr, esr];
+    let far = register!(FaultAddressEl1);
+    let afsr0 = register!(AuxFaultStatus0El1);
+    let afsr1 = register!(AuxFaultStatus1El1);
+    let daif = register!(InterruptMask);
+    let sp_el0 = register!(StackPointerEl0);
+    let regs = [el, elr, esr, far, afsr0, afsr1, daif, sp_el0];
     for r in regs {
         r.load();
 
@@ -371,6 +920,23 @@ unsafe extern "C" fn exception_handler(exception_frame: *mut ExceptionFrame) {
         let name = r.name();
         writeln!(out, "{name}\t{raw:#016x?}: {r:x?}").ok();
     }
+
+    let esr = ExceptionSyndromeEl1::from(esr.bits());
+    let far = far.bits();
+    let syndrome = esr.decode();
+    writeln!(out, "{syndrome:x?}").ok();
+    match syndrome {
+        Syndrome::DataAbort(iss) if !iss.fnv() => {
+            writeln!(out, "Fault address {far:#016x}").ok();
+        }
+        Syndrome::InstructionAbort(iss) if !iss.fnv() => {
+            writeln!(out, "Fault address {far:#016x}").ok();
+        }
+        _ => {}
+    }
+    if esr.is_tag_check_fault() {
+        writeln!(out, "Tag check fault").ok();
+    }
     writeln!(out, "!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!").ok();
 
     // Hang for now
@@ -385,11 +951,6 @@ unsafe extern "C" fn exception_handler(exception_frame: *mut ExceptionFrame) {
     } else {
         unsafe { core::arch::asm!("1: wfe; b 1b") };
     }
-
-    // let esr = ExceptionSyndromeEl1::from(esr.bits());
-    // if esr.ec() == ExceptionClass::Brk64bit {
-    //     frame.elr += 4;
-    // }
 }
 
 #[cfg(target_os = "none")]
@@ -1,5 +1,6 @@
 #!/bin/sh
 #-trace "*" \
+# -trace "gicv3_dist*" shows the distributor accesses, e.g. the SPI routing
 
 TARGET="lab"
 FLAVOR="debug"
@@ -7,6 +8,9 @@ KERNEL="target/${TARGET}/${FLAVOR}/aarch64-lab.Image"
 
 MACHINE="virt,gic-version=3,highmem=on,virtualization=off"
 CPU="cortex-a76" # max # host
+# Memory tagging needs CPU="max" and "mte=on" in MACHINE
+# The SVE and SME vector lengths are reported with CPU="max"
+# "virtualization=on" starts at EL2, the EL2 registers are printed then
 
 qemu-system-aarch64 -machine ${MACHINE} -machine dumpdtb=./dump.dtb
 dtc -I dtb -O dts -o ./dump.dts ./dump.dtb
@@ -0,0 +1,38 @@
+//! Prints the register types generated from the specification subset.
+//!
+//! ```text
+//! regs-gen REGISTERS_JSON LIST > ../aarch64/src/regs/generated.rs
+//! ```
+//!
+//! See `tools/aarchmrs/registers.list` for the format of the list.
+
+use std::process::ExitCode;
+
+use aarch64_tools::regs_gen;
+
+const USAGE: &str = "usage: regs-gen REGISTERS_JSON LIST";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [spec_path, list_path] = &args[..] else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let read = |path: &str| std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"));
+    let source = read(spec_path).and_then(|spec| {
+        let list = read(list_path)?;
+        let specs = regs_gen::parse_spec(&spec).map_err(|e| e.to_string())?;
+        let list = regs_gen::parse_list(&list).map_err(|e| format!("{list_path}: {e}"))?;
+        regs_gen::generate(&specs, &list).map_err(|e| e.to_string())
+    });
+    match source {
+        Ok(source) => {
+            print!("{source}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Prints the README comparison tables of the registers from the survey
+//! records of several environments.
+//!
+//! ```text
+//! survey-tables [--stage STAGE] "ENVIRONMENT=FILE"...
+//! ```
+//!
+//! A file is a survey file or the console log of the lab. Only the
+//! records of the stage are taken, `boot` by default.
+
+use std::process::ExitCode;
+
+use aarch64_tools::survey;
+use aarch64_tools::survey::Environment;
+
+const USAGE: &str = "usage: survey-tables [--stage STAGE] \"ENVIRONMENT=FILE\"...";
+
+fn main() -> ExitCode {
+    let mut stage = "boot".to_owned();
+    let mut envs = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--stage" {
+            let Some(value) = args.next() else {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            };
+            stage = value;
+            continue;
+        }
+        let Some((name, path)) = arg.rsplit_once('=') else {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        };
+        let records = match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| survey::parse_records(&text).map_err(|e| e.to_string()))
+        {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        envs.push(Environment {
+            name: name.to_owned(),
+            records,
+        });
+    }
+    if envs.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    for env in &mut envs {
+        env.records.retain(|r| r.stage == stage);
+    }
+    print!("{}", survey::tables(&envs));
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,103 @@
+//! Decodes a system register value, or composes it from the fields.
+//!
+//! ```text
+//! sysreg TCR_EL1 0x480803514
+//! sysreg TCR_EL1 t0sz=0x10 tg0=_4KB ips=_48_bits_256TB
+//! sysreg TCR_EL1 0x480803514 tbi0=1
+//! sysreg --fields TCR_EL1
+//! sysreg --list
+//! ```
+//!
+//! The values print as `{:#x?}` of the register type. Composing needs
+//! the field tables of `aarch64::regs::fields`, the fields start from
+//! zero or from the value given before them. The numbers are hex unless
+//! they start with `0b` or `0d`.
+
+use std::process::ExitCode;
+
+use aarch64_tools::sysreg;
+
+const USAGE: &str = "usage: sysreg REGISTER [VALUE] [FIELD=VALUE]...
+       sysreg --fields REGISTER
+       sysreg --list";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["--list"] => {
+            for register in sysreg::REGISTERS {
+                let composable = if register.fields.is_some() {
+                    " (fields)"
+                } else {
+                    ""
+                };
+                println!("{}{composable}", register.name);
+            }
+            ExitCode::SUCCESS
+        }
+        ["--fields", name] => {
+            let Some(register) = find(name) else {
+                return ExitCode::FAILURE;
+            };
+            let Some(fields) = register.fields else {
+                eprintln!("no field table for {}", register.name);
+                return ExitCode::FAILURE;
+            };
+            print!("{}", sysreg::layout(fields));
+            ExitCode::SUCCESS
+        }
+        [name, rest @ ..] if !name.starts_with('-') => {
+            let Some(register) = find(name) else {
+                return ExitCode::FAILURE;
+            };
+            let (base, assignments) = match rest.first().and_then(|v| sysreg::parse_number(v)) {
+                Some(base) => (base, &rest[1..]),
+                None => (0, rest),
+            };
+            if rest.is_empty() {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+
+            let bits = if assignments.is_empty() {
+                base
+            } else {
+                let Some(fields) = register.fields else {
+                    eprintln!("no field table for {}", register.name);
+                    return ExitCode::FAILURE;
+                };
+                match sysreg::compose(fields, base, assignments.iter().copied()) {
+                    Ok(bits) => bits,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            };
+
+            println!("{} = {bits:#x}", register.name);
+            // The panic message tells which field has an unexpected value.
+            match std::panic::catch_unwind(|| (register.decode)(bits)) {
+                Ok(decoded) => {
+                    println!("{decoded}");
+                    ExitCode::SUCCESS
+                }
+                Err(_) => ExitCode::FAILURE,
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn find(name: &str) -> Option<&'static sysreg::Register> {
+    let register = sysreg::find(name);
+    if register.is_none() {
+        eprintln!("unknown register {name}, see --list");
+    }
+    register
+}
@@ -0,0 +1,8 @@
+//! Host tools for the data the lab collects
+
+pub mod json;
+pub mod regs_gen;
+pub mod survey;
+pub mod sysreg;
+
+mod tests;
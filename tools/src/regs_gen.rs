@@ -0,0 +1,441 @@
+//! Generates the register types from a subset of the Arm machine-readable
+//! specification (AARCHMRS) `Registers.json`.
+//!
+//! A listing names the registers to generate and their types. For each
+//! register there is a bitfield struct, the enums of the fields with the
+//! listed values, the access impls and the field table. The bits the
+//! specification subset does not describe are kept as padding.
+//!
+//! The indexed registers, `<n>` in the name, get only the struct, as
+//! their accessors take the index.
+
+use std::fmt::Write;
+
+use crate::json;
+use crate::json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    /// The register or the file the error is in.
+    pub context: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.context, self.message)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+fn error(context: &str, message: impl Into<String>) -> SpecError {
+    SpecError {
+        context: context.to_owned(),
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+    /// The values are named by their meanings, empty for a number.
+    Field(Vec<(String, u64)>),
+    Res0,
+    Res1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: String,
+    pub lsb: u32,
+    pub width: u32,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterSpec {
+    pub name: String,
+    pub title: String,
+    pub readable: bool,
+    pub writable: bool,
+    /// Sorted by `lsb`, not overlapping.
+    pub fields: Vec<FieldSpec>,
+}
+
+/// Takes the 64-bit fieldset of the AArch64 registers.
+pub fn parse_spec(text: &str) -> Result<Vec<RegisterSpec>, SpecError> {
+    let value = json::parse(text).map_err(|e| error("Registers.json", e.to_string()))?;
+    value
+        .elements()
+        .iter()
+        .filter(|r| {
+            r.get("_type").and_then(Value::as_str) == Some("Register")
+                && r.get("state").and_then(Value::as_str) == Some("AArch64")
+        })
+        .map(parse_register)
+        .collect()
+}
+
+fn parse_register(register: &Value) -> Result<RegisterSpec, SpecError> {
+    let name = register
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| error("Registers.json", "a register without a name"))?;
+    let accessors: Vec<&str> = register
+        .get("accessors")
+        .map_or(&[][..], Value::elements)
+        .iter()
+        .filter_map(|a| a.get("name").and_then(Value::as_str))
+        .collect();
+    let fieldset = register
+        .get("fieldsets")
+        .map_or(&[][..], Value::elements)
+        .iter()
+        .find(|f| f.get("width").and_then(Value::as_i64) == Some(64))
+        .ok_or_else(|| error(name, "no 64-bit fieldset"))?;
+
+    let mut fields = Vec::new();
+    for field in fieldset.get("values").map_or(&[][..], Value::elements) {
+        let [range] = field.get("rangeset").map_or(&[][..], Value::elements) else {
+            return Err(error(name, "only the fields of one range are supported"));
+        };
+        let bit = |key: &str| {
+            range
+                .get(key)
+                .and_then(Value::as_i64)
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| error(name, format!("a range without \"{key}\"")))
+        };
+        let (lsb, width) = (bit("start")?, bit("width")?);
+        let (field_name, kind) = match field.get("_type").and_then(Value::as_str) {
+            Some("Fields.Field") => {
+                let field_name = field
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| error(name, "a field without a name"))?;
+                let values = parse_values(name, field_name, field.get("values"))?;
+                (field_name.to_owned(), FieldKind::Field(values))
+            }
+            Some("Fields.Reserved") => match field.get("value").and_then(Value::as_str) {
+                Some("RES0") => (String::new(), FieldKind::Res0),
+                Some("RES1") => (String::new(), FieldKind::Res1),
+                _ => return Err(error(name, "reserved bits other than RES0 and RES1")),
+            },
+            _ => return Err(error(name, "unsupported field type")),
+        };
+        fields.push(FieldSpec {
+            name: field_name,
+            lsb,
+            width,
+            kind,
+        });
+    }
+    fields.sort_by_key(|f| f.lsb);
+
+    let mut next = 0;
+    for field in &fields {
+        if field.width == 0 || field.lsb < next || field.lsb + field.width > 64 {
+            return Err(error(
+                name,
+                format!("the bits of \"{}\" overlap or are out of range", field.name),
+            ));
+        }
+        next = field.lsb + field.width;
+    }
+
+    Ok(RegisterSpec {
+        name: name.to_owned(),
+        title: register
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or(name)
+            .to_owned(),
+        readable: accessors.iter().any(|a| a.starts_with("MRS")),
+        writable: accessors.iter().any(|a| a.starts_with("MSR")),
+        fields,
+    })
+}
+
+/// The values are bit strings, `'0101'`, named by their meanings.
+fn parse_values(
+    register: &str,
+    field: &str,
+    values: Option<&Value>,
+) -> Result<Vec<(String, u64)>, SpecError> {
+    let Some(values) = values.and_then(|v| v.get("values")) else {
+        return Ok(Vec::new());
+    };
+    values
+        .elements()
+        .iter()
+        .map(|value| {
+            let bits = value
+                .get("value")
+                .and_then(Value::as_str)
+                .map(|v| v.trim_matches('\''))
+                .and_then(|v| u64::from_str_radix(v, 2).ok())
+                .ok_or_else(|| error(register, format!("a value of \"{field}\" is not bits")))?;
+            let meaning = value
+                .get("meaning")
+                .and_then(Value::as_str)
+                .ok_or_else(|| error(register, format!("a value of \"{field}\" has no meaning")))?;
+            Ok((meaning.to_owned(), bits))
+        })
+        .collect()
+}
+
+/// A register to generate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listed {
+    pub name: String,
+    pub type_name: String,
+    /// The enums of the fields are named with it, the fields are plain
+    /// numbers otherwise.
+    pub enum_prefix: Option<String>,
+    /// The `.arch_extension` the assembler needs for the register.
+    pub extension: Option<String>,
+    /// Only read, whatever the accessors: EL1 may write the register but
+    /// must leave it to the firmware.
+    pub read_only: bool,
+}
+
+/// A line for each register: the name, the type name, the optional enum
+/// prefix and the optional assembler extension after `+`. `#` starts
+/// a comment.
+pub fn parse_list(text: &str) -> Result<Vec<Listed>, SpecError> {
+    let mut listed = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        let read_only = words.last() == Some(&"!ro");
+        if read_only {
+            words.pop();
+        }
+        let (extension, words) = match words.split_last() {
+            Some((last, rest)) if last.starts_with('+') => (Some(&last[1..]), rest),
+            _ => (None, &words[..]),
+        };
+        match *words {
+            [] if extension.is_none() && !read_only => {}
+            [_, _, prefix] if prefix.starts_with(['+', '!']) => {
+                return Err(error(
+                    &format!("line {}", index + 1),
+                    "`+EXTENSION` and then `!ro` end the line",
+                ))
+            }
+            [name, type_name] | [name, type_name, _] => listed.push(Listed {
+                name: name.to_owned(),
+                type_name: type_name.to_owned(),
+                enum_prefix: words.get(2).map(|&p| p.to_owned()),
+                extension: extension.map(str::to_owned),
+                read_only,
+            }),
+            _ => {
+                return Err(error(
+                    &format!("line {}", index + 1),
+                    "expected NAME TYPE [ENUM_PREFIX] [+EXTENSION] [!ro]",
+                ))
+            }
+        }
+    }
+    Ok(listed)
+}
+
+/// `Not implemented, NV1` is `NotImplementedNv1`.
+fn camel_case(s: &str) -> String {
+    let mut out = String::new();
+    for word in s.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.push(first.to_ascii_uppercase());
+            out.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        out.insert(0, 'V');
+    }
+    out
+}
+
+/// The Rust source of the listed registers, in the order of the list.
+pub fn generate(specs: &[RegisterSpec], list: &[Listed]) -> Result<String, SpecError> {
+    let mut items = String::new();
+    let mut macros = Vec::new();
+    for listed in list {
+        let spec = specs
+            .iter()
+            .find(|s| s.name == listed.name)
+            .ok_or_else(|| error(&listed.name, "not in the specification"))?;
+        let access = match (spec.readable, spec.writable && !listed.read_only) {
+            _ if spec.name.contains("<n>") => None,
+            (true, true) => Some("impl_register_access"),
+            (true, false) => Some("impl_register_access_ro"),
+            (false, true) => Some("impl_register_access_wo"),
+            (false, false) => return Err(error(&spec.name, "no MRS or MSR accessor")),
+        };
+        if let Some(access) = access.filter(|a| !macros.contains(a)) {
+            macros.push(access);
+        }
+        generate_register(&mut items, spec, listed, access)?;
+    }
+
+    let mut out = String::from(
+        "// Generated by `tools/src/bin/regs-gen.rs` from `tools/aarchmrs/`, do not edit.\n\n\
+         use bitfield_struct::bitfield;\n\n",
+    );
+    macros.sort_unstable();
+    for access in macros {
+        writeln!(out, "use super::access::{access};").unwrap();
+    }
+    if items.contains("impl_register_fields!(") {
+        out.push_str("use super::fields::impl_register_fields;\n");
+    }
+    if items.contains("id_field!(") {
+        out.push_str("use super::id_field;\n");
+    }
+    out.push_str(&items);
+    Ok(out)
+}
+
+fn generate_register(
+    out: &mut String,
+    spec: &RegisterSpec,
+    listed: &Listed,
+    access: Option<&str>,
+) -> Result<(), SpecError> {
+    let type_name = &listed.type_name;
+    let mut body = String::new();
+    let mut table = String::new();
+    let mut res1 = 0u64;
+    let (mut mbz, mut mbo) = (0, 0);
+    let mut padding = |body: &mut String, width: u32, one: bool| {
+        let (prefix, index) = if one {
+            ("mbo", &mut mbo)
+        } else {
+            ("mbz", &mut mbz)
+        };
+        writeln!(body, "    #[bits({width})]\n    _{prefix}{index}: u64,").unwrap();
+        *index += 1;
+    };
+
+    let mut next = 0;
+    for field in &spec.fields {
+        if field.lsb > next {
+            padding(&mut body, field.lsb - next, false);
+        }
+        next = field.lsb + field.width;
+        let values = match &field.kind {
+            FieldKind::Res0 => {
+                padding(&mut body, field.width, false);
+                continue;
+            }
+            FieldKind::Res1 => {
+                res1 |= (u64::MAX >> (64 - field.width)) << field.lsb;
+                padding(&mut body, field.width, true);
+                continue;
+            }
+            FieldKind::Field(values) => values,
+        };
+
+        let ident = field.name.to_ascii_lowercase();
+        let enum_name = match (&listed.enum_prefix, values.is_empty()) {
+            (Some(prefix), false) => {
+                let enum_name = format!("{prefix}{}", camel_case(&field.name));
+                generate_enum(out, spec, field, &enum_name, values)?;
+                Some(enum_name)
+            }
+            _ => None,
+        };
+        match &enum_name {
+            Some(enum_name) => writeln!(
+                body,
+                "    #[bits({})]\n    pub {ident}: {enum_name},",
+                field.width
+            ),
+            None if field.width == 1 => writeln!(body, "    pub {ident}: bool,"),
+            None => writeln!(body, "    #[bits({})]\n    pub {ident}: u64,", field.width),
+        }
+        .unwrap();
+
+        write!(table, "    {ident}: {}", field.lsb).unwrap();
+        if field.width > 1 {
+            write!(table, "..={}", field.lsb + field.width - 1).unwrap();
+        }
+        if let Some(enum_name) = &enum_name {
+            write!(table, " as {enum_name}").unwrap();
+        }
+        table.push_str(",\n");
+    }
+    if next < 64 {
+        padding(&mut body, 64 - next, false);
+    }
+
+    write!(
+        out,
+        "\n/// {}\n#[bitfield(u64)]\npub struct {type_name} {{\n{body}}}\n",
+        spec.title
+    )
+    .unwrap();
+    if res1 != 0 {
+        write!(
+            out,
+            "\nimpl {type_name} {{\n    const RES1: u64 = {res1:#x};\n}}\n\n\
+             impl Default for {type_name} {{\n    fn default() -> Self {{\n        \
+             Self::from(Self::RES1)\n    }}\n}}\n"
+        )
+        .unwrap();
+    }
+    let Some(access) = access else {
+        return Ok(());
+    };
+    match &listed.extension {
+        Some(extension) => writeln!(
+            out,
+            "\n{access}!({type_name}, {}, \"{extension}\");",
+            spec.name
+        ),
+        None => writeln!(out, "\n{access}!({type_name}, {});", spec.name),
+    }
+    .unwrap();
+    if !table.is_empty() {
+        write!(out, "\nimpl_register_fields!({type_name} {{\n{table}}});\n").unwrap();
+    }
+    Ok(())
+}
+
+fn generate_enum(
+    out: &mut String,
+    spec: &RegisterSpec,
+    field: &FieldSpec,
+    enum_name: &str,
+    values: &[(String, u64)],
+) -> Result<(), SpecError> {
+    let mut variants: Vec<String> = Vec::new();
+    for (meaning, _) in values {
+        let variant = camel_case(meaning);
+        if variant == "Unknown" || variants.contains(&variant) {
+            return Err(error(
+                &spec.name,
+                format!("the values of \"{}\" repeat \"{variant}\"", field.name),
+            ));
+        }
+        variants.push(variant);
+    }
+
+    write!(
+        out,
+        "\nid_field!(\n    /// {}.{}\n    {enum_name} {{\n",
+        spec.name, field.name
+    )
+    .unwrap();
+    for (variant, (_, value)) in variants.iter().zip(values) {
+        writeln!(
+            out,
+            "        {variant} = {value:#0width$b},",
+            width = field.width as usize + 2
+        )
+        .unwrap();
+    }
+    out.push_str("    }\n);\n");
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Merges the register survey records of several environments into
+//! the comparison tables of the README.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::json;
+
+/// A register as an environment has it, see `aarch64::survey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub stage: String,
+    pub register: String,
+    pub bits: String,
+    /// The decoded fields in the order of the register layout.
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    /// Starts from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+/// Picks the records out of a console log or a survey file, the lines
+/// not starting with `{` are skipped.
+pub fn parse_records(text: &str) -> Result<Vec<Record>, RecordError> {
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if !line.starts_with('{') {
+            continue;
+        }
+        let error = |message: String| RecordError {
+            line: index + 1,
+            message,
+        };
+        let value = json::parse(line).map_err(|e| error(e.to_string()))?;
+        let string = |key: &str| {
+            value
+                .get(key)
+                .and_then(json::Value::as_str)
+                .map(str::to_owned)
+                .ok_or_else(|| error(format!("no \"{key}\"")))
+        };
+        let fields = value
+            .get("fields")
+            .ok_or_else(|| error("no \"fields\"".to_owned()))?
+            .members()
+            .iter()
+            .map(|(name, value)| match value.as_str() {
+                Some(s) => Ok((name.clone(), s.to_owned())),
+                None => Err(error(format!("the field \"{name}\" is not a string"))),
+            })
+            .collect::<Result<_, _>>()?;
+        records.push(Record {
+            stage: string("stage")?,
+            register: string("register")?,
+            bits: string("bits")?,
+            fields,
+        });
+    }
+    Ok(records)
+}
+
+/// The records of one environment.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub name: String,
+    pub records: Vec<Record>,
+}
+
+/// A section for each register, sorted by the register name. The
+/// environments are listed sorted by their name, and only those having
+/// the register. The first record of a register in an environment is
+/// taken, the fields are `bits` and then the fields of the first
+/// environment having the register.
+pub fn tables(envs: &[Environment]) -> String {
+    let mut envs: Vec<&Environment> = envs.iter().collect();
+    envs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut registers: BTreeMap<&str, Vec<(&str, &Record)>> = BTreeMap::new();
+    for env in &envs {
+        for record in &env.records {
+            let samples = registers.entry(&record.register).or_default();
+            if !samples.iter().any(|(name, _)| *name == env.name) {
+                samples.push((&env.name, record));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (index, (register, samples)) in registers.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        writeln!(out, "## {index}. Register `{register}`\n").unwrap();
+        for (index, (env, _)) in samples.iter().enumerate() {
+            writeln!(out, "{index}. {env}").unwrap();
+        }
+        out.push('\n');
+
+        out.push_str("| Field\\Env |");
+        for index in 0..samples.len() {
+            write!(out, " `{index}` |").unwrap();
+        }
+        out.push('\n');
+        out.push_str(&"|---".repeat(samples.len() + 1));
+        out.push_str("|\n");
+
+        let bits = samples.iter().map(|(_, r)| r.bits.as_str());
+        write_row(&mut out, "bits", bits);
+        for (field, _) in &samples[0].1.fields {
+            let values = samples.iter().map(|(_, r)| {
+                r.fields
+                    .iter()
+                    .find(|(name, _)| name == field)
+                    .map_or("", |(_, value)| value.as_str())
+            });
+            write_row(&mut out, field, values);
+        }
+    }
+    out
+}
+
+fn write_row<'a>(out: &mut String, field: &str, values: impl Iterator<Item = &'a str>) {
+    write!(out, "| `{field}` |").unwrap();
+    for value in values {
+        write!(out, " {value} |").unwrap();
+    }
+    out.push('\n');
+}
@@ -0,0 +1,230 @@
+//! Decodes the system register values, and composes them from the fields.
+
+use aarch64::gic::*;
+use aarch64::regs::access::SystemRegister;
+use aarch64::regs::fields::Field;
+use aarch64::regs::fields::RegisterFields;
+use aarch64::regs::*;
+
+/// A register the tool knows by name
+pub struct Register {
+    pub name: &'static str,
+    /// The `{:#x?}` of the value, panics if a field has a value the type
+    /// does not model.
+    pub decode: fn(u64) -> String,
+    /// Only the registers with the field tables can be composed.
+    pub fields: Option<&'static [Field]>,
+}
+
+fn decode<R: SystemRegister + core::fmt::Debug>(bits: u64) -> String {
+    format!("{:#x?}", R::from(bits))
+}
+
+macro_rules! registers {
+    ($($register_type:ident $(with $fields:ident)?),+ $(,)?) => {
+        &[$(Register {
+            name: <$register_type as SystemRegister>::NAME,
+            decode: decode::<$register_type>,
+            fields: registers!(@fields $register_type $($fields)?),
+        }),+]
+    };
+    (@fields $register_type:ident fields) => {
+        Some(<$register_type as RegisterFields>::FIELDS)
+    };
+    (@fields $register_type:ident) => {
+        None
+    };
+}
+
+pub const REGISTERS: &[Register] = registers![
+    MainIdEl1 with fields,
+    RevisionIdEl1,
+    MultiprocessorAffinityEl1,
+    ProcessorFeatures0El1,
+    ProcessorFeatures1El1,
+    MmFeatures0El1 with fields,
+    MmFeatures1El1,
+    MmFeatures2El1,
+    MmFeatures3El1,
+    MmFeatures4El1 with fields,
+    InstructionSetFeatures0El1,
+    InstructionSetFeatures1El1,
+    InstructionSetFeatures2El1,
+    DebugFeatures0El1,
+    PerfMonControlEl0 with fields,
+    PerfMonCountEnableSetEl0 with fields,
+    PerfMonOverflowSetEl0 with fields,
+    PerfMonInterruptEnableSetEl1 with fields,
+    PerfMonUserEnableEl0 with fields,
+    PerfMonCycleCountEl0 with fields,
+    SveFeatures0El1,
+    SmeFeatures0El1,
+    CounterFrequencyEl0 with fields,
+    VirtualCountEl0 with fields,
+    PhysicalCountEl0 with fields,
+    VirtualTimerControlEl0 with fields,
+    VirtualTimerCompareEl0 with fields,
+    PhysicalTimerControlEl0 with fields,
+    PhysicalTimerCompareEl0 with fields,
+    CounterKernelControlEl1 with fields,
+    SveControlEl1 with fields,
+    SmeControlEl1 with fields,
+    StreamingVectorControl with fields,
+    CurrentEl with fields,
+    SystemControlEl1 with fields,
+    VectorBaseEl1,
+    ExceptionLinkEl1,
+    ExceptionSyndromeEl1 with fields,
+    SavedProgramStateEl1 with fields,
+    FaultAddressEl1,
+    AuxFaultStatus0El1,
+    AuxFaultStatus1El1,
+    InterruptMask,
+    ConditionFlags,
+    ArchFeatureAccessControlEl1,
+    StackPointerEl0,
+    ThreadIdEl0,
+    ThreadIdEl1,
+    ContextIdEl1,
+    TranslationControlEl1 with fields,
+    TranslationBase0El1 with fields,
+    TranslationBase1El1 with fields,
+    MemoryAttributeIndirectionEl1 with fields,
+    TranslationControl2El1,
+    PermissionIndirectionEl1,
+    PermissionIndirectionE0El1,
+    PermissionOverlayEl1,
+    TagControlEl1,
+    RandomTagSeedEl1,
+    TagFaultStatusEl1,
+    OsLockAccessEl1,
+    MonitorDebugSystemControlEl1 with fields,
+    HypervisorConfigEl2,
+    SystemControlEl2,
+    VectorBaseEl2,
+    ExceptionSyndromeEl2,
+    ExceptionLinkEl2,
+    SavedProgramStateEl2,
+    FaultAddressEl2,
+    HypervisorIpaFaultAddressEl2,
+    TranslationControlEl2,
+    TranslationBase0El2,
+    MemoryAttributeIndirectionEl2,
+    ArchFeatureTrapEl2,
+    CounterHypControlEl2,
+    VirtualizationProcessorIdEl2,
+    VirtualizationMultiprocessorIdEl2,
+    IccSre,
+    IccCtlr,
+    IccPmr,
+    IccIgrpen1,
+];
+
+/// Case-insensitive, as QEMU prints some names in the lower case.
+pub fn find(name: &str) -> Option<&'static Register> {
+    REGISTERS.iter().find(|r| r.name.eq_ignore_ascii_case(name))
+}
+
+/// A hex number with or without `0x`, as QEMU and the lab print them, a
+/// binary one with `0b`, or a decimal one with `0d`. The digits can be
+/// grouped with `_`.
+pub fn parse_number(s: &str) -> Option<u64> {
+    let s = s.replace('_', "");
+    if let Some(bin) = s.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2).ok()
+    } else if let Some(dec) = s.strip_prefix("0d") {
+        dec.parse().ok()
+    } else {
+        let hex = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(&s);
+        u64::from_str_radix(hex, 16).ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComposeError {
+    /// Not `field=value`.
+    Syntax(String),
+    UnknownField(String),
+    /// Neither a number nor a name of the field values.
+    InvalidValue {
+        field: String,
+        value: String,
+    },
+    /// Does not fit into the field.
+    TooLarge {
+        field: String,
+        value: u64,
+    },
+}
+
+impl std::fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComposeError::Syntax(s) => write!(f, "expected field=value, got \"{s}\""),
+            ComposeError::UnknownField(field) => write!(f, "no field \"{field}\""),
+            ComposeError::InvalidValue { field, value } => {
+                write!(f, "invalid value \"{value}\" of \"{field}\"")
+            }
+            ComposeError::TooLarge { field, value } => {
+                write!(f, "{value:#x} does not fit into \"{field}\"")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ComposeError {}
+
+/// Sets the fields of `base` from the `field=value` pairs, the values
+/// are numbers or the names of the field values (`tg0=_4KB`).
+pub fn compose<'a>(
+    fields: &[Field],
+    base: u64,
+    assignments: impl IntoIterator<Item = &'a str>,
+) -> Result<u64, ComposeError> {
+    let mut bits = base;
+    for assignment in assignments {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| ComposeError::Syntax(assignment.to_owned()))?;
+        let field = fields
+            .iter()
+            .find(|f| f.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| ComposeError::UnknownField(name.to_owned()))?;
+        let number = field
+            .value(value)
+            .or_else(|| parse_number(value))
+            .ok_or_else(|| ComposeError::InvalidValue {
+                field: field.name.to_owned(),
+                value: value.to_owned(),
+            })?;
+        bits = field
+            .insert(bits, number)
+            .ok_or_else(|| ComposeError::TooLarge {
+                field: field.name.to_owned(),
+                value: number,
+            })?;
+    }
+    Ok(bits)
+}
+
+/// A line for each field: the bits, the name, and the names of
+/// the values if any.
+pub fn layout(fields: &[Field]) -> String {
+    let mut out = String::new();
+    for field in fields {
+        let range = if field.width == 1 {
+            format!("[{}]", field.lsb)
+        } else {
+            format!("[{}:{}]", field.lsb + field.width - 1, field.lsb)
+        };
+        out.push_str(&format!("{range:>7} {}", field.name));
+        for (name, value) in field.values {
+            out.push_str(&format!(" {name}={value:#x}"));
+        }
+        out.push('\n');
+    }
+    out
+}
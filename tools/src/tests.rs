@@ -0,0 +1,227 @@
+#![cfg(test)]
+
+use crate::json;
+use crate::json::Value;
+use crate::regs_gen;
+use crate::regs_gen::FieldKind;
+use crate::survey;
+use crate::survey::Environment;
+use crate::sysreg;
+use crate::sysreg::ComposeError;
+
+#[test]
+fn test_json_parse() {
+    let value = json::parse(r#" {"a":"x\"yA", "b": {}, "c":{"d":"e"}} "#).unwrap();
+    assert_eq!(value.get("a"), Some(&Value::String("x\"yA".to_owned())));
+    assert_eq!(value.get("b"), Some(&Value::Object(vec![])));
+    assert_eq!(
+        value
+            .get("c")
+            .and_then(|c| c.get("d"))
+            .and_then(Value::as_str),
+        Some("e")
+    );
+    assert_eq!(value.get("z"), None);
+
+    let value = json::parse(r#"[1, -20, true, null, [], {"a": [false]}]"#).unwrap();
+    assert_eq!(
+        value.elements()[..4],
+        [
+            Value::Number(1),
+            Value::Number(-20),
+            Value::Bool(true),
+            Value::Null
+        ]
+    );
+    assert_eq!(
+        value.elements()[5].get("a"),
+        Some(&Value::Array(vec![Value::Bool(false)]))
+    );
+
+    assert_eq!(json::parse(r#"{"a":1.5}"#).unwrap_err().offset, 6);
+    assert!(json::parse("[1,]").is_err());
+    assert!(json::parse("nul").is_err());
+    assert!(json::parse(r#"{"a":"b"} x"#).is_err());
+    assert!(json::parse(r#"{"a":"b"#).is_err());
+}
+
+#[test]
+fn test_survey_tables() {
+    let log = concat!(
+        "MIDR_EL1\t0x000000410fd083: MainIdEl1 { .. }\n",
+        r#"{"stage":"boot","register":"MIDR_EL1","bits":"0x000000410fd083","fields":{"revision":"3","part_num":"d08"}}"#,
+        "\n",
+        r#"{"stage":"boot","register":"MAIR_EL1","bits":"0x00000000000000","fields":{"value":"[0, 0, 0, 0, 0, 0, 0, 0]"}}"#,
+        "\n",
+        r#"{"stage":"mmu","register":"MAIR_EL1","bits":"0x000000ffbb4400","fields":{"value":"[0, 44, bb, ff, 0, 0, 0, 0]"}}"#,
+        "\n",
+    );
+    let records = survey::parse_records(log).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].register, "MIDR_EL1");
+    assert_eq!(
+        records[0].fields,
+        [
+            ("revision".to_owned(), "3".to_owned()),
+            ("part_num".to_owned(), "d08".to_owned())
+        ]
+    );
+
+    let other = survey::parse_records(
+        r#"{"stage":"boot","register":"MIDR_EL1","bits":"0x000000000f0510","fields":{"revision":"0","part_num":"51"}}"#,
+    )
+    .unwrap();
+    let envs = [
+        Environment {
+            name: "Raspberry Pi 4, cpu host".to_owned(),
+            records: records.into_iter().filter(|r| r.stage == "boot").collect(),
+        },
+        Environment {
+            name: "M1, cpu max".to_owned(),
+            records: other,
+        },
+    ];
+    assert_eq!(
+        survey::tables(&envs),
+        "## 0. Register `MAIR_EL1`
+
+0. Raspberry Pi 4, cpu host
+
+| Field\\Env | `0` |
+|---|---|
+| `bits` | 0x00000000000000 |
+| `value` | [0, 0, 0, 0, 0, 0, 0, 0] |
+
+## 1. Register `MIDR_EL1`
+
+0. M1, cpu max
+1. Raspberry Pi 4, cpu host
+
+| Field\\Env | `0` | `1` |
+|---|---|---|
+| `bits` | 0x000000000f0510 | 0x000000410fd083 |
+| `revision` | 0 | 3 |
+| `part_num` | 51 | d08 |
+"
+    );
+
+    let error = survey::parse_records("\n{\"stage\":\"boot\"}").unwrap_err();
+    assert_eq!(error.line, 2);
+}
+
+#[test]
+fn test_sysreg_compose() {
+    assert_eq!(sysreg::parse_number("0x4_8080_3514"), Some(0x4_8080_3514));
+    assert_eq!(sysreg::parse_number("0b101"), Some(5));
+    assert_eq!(sysreg::parse_number("0d16"), Some(16));
+    // Pasted from QEMU `info registers`, without the prefix.
+    assert_eq!(sysreg::parse_number("16"), Some(0x16));
+    assert_eq!(sysreg::parse_number("480803514"), Some(0x4_8080_3514));
+    assert_eq!(sysreg::parse_number("_4KB"), None);
+
+    let tcr = sysreg::find("tcr_el1").unwrap();
+    assert_eq!(tcr.name, "TCR_EL1");
+    let fields = tcr.fields.unwrap();
+    let bits = sysreg::compose(
+        fields,
+        0,
+        [
+            "t0sz=0d20", "irgn0=1", "orgn0=1", "sh0=3", "tg0=_4KB", "epd1=1",
+        ]
+        .into_iter()
+        .chain(["tg1=_4KB", "ips=_44_bits_16TB"]),
+    )
+    .unwrap();
+    assert_eq!(bits, 0x4_8080_3514);
+    assert!((tcr.decode)(bits).contains("ips: _44_bits_16TB,"));
+
+    assert_eq!(
+        sysreg::compose(fields, bits, ["tbi0=1", "T0SZ=0x10"]),
+        Ok(0x24_8080_3510)
+    );
+    assert_eq!(
+        sysreg::compose(fields, 0, ["tg0"]),
+        Err(ComposeError::Syntax("tg0".to_owned()))
+    );
+    assert_eq!(
+        sysreg::compose(fields, 0, ["tg2=1"]),
+        Err(ComposeError::UnknownField("tg2".to_owned()))
+    );
+    assert_eq!(
+        sysreg::compose(fields, 0, ["tg0=_8KB"]),
+        Err(ComposeError::InvalidValue {
+            field: "tg0".to_owned(),
+            value: "_8KB".to_owned()
+        })
+    );
+    assert_eq!(
+        sysreg::compose(fields, 0, ["t0sz=0d64"]),
+        Err(ComposeError::TooLarge {
+            field: "t0sz".to_owned(),
+            value: 64
+        })
+    );
+
+    assert!(sysreg::layout(fields).contains("[15:14] tg0 _4KB=0x0 _64KB=0x1 _16KB=0x2\n"));
+    assert!(sysreg::find("ICC_PMR_EL1").unwrap().fields.is_none());
+    assert!(sysreg::find("TTBR2_EL1").is_none());
+}
+
+#[test]
+fn test_regs_gen() {
+    // The committed types must be what the generator makes of the
+    // vendored specification.
+    let specs = regs_gen::parse_spec(include_str!("../aarchmrs/Registers.json")).unwrap();
+    let list = regs_gen::parse_list(include_str!("../aarchmrs/registers.list")).unwrap();
+    assert_eq!(
+        regs_gen::generate(&specs, &list).unwrap(),
+        include_str!("../../aarch64/src/regs/generated.rs")
+    );
+
+    let spec = r#"[{
+        "_type": "Register", "name": "TEST_EL1", "state": "AArch64",
+        "accessors": [{"name": "MSRregister TEST_EL1"}],
+        "fieldsets": [{"width": 64, "values": [
+            {"_type": "Fields.Reserved", "value": "RES1", "rangeset": [{"start": 4, "width": 2}]},
+            {"_type": "Fields.Field", "name": "EN", "rangeset": [{"start": 0, "width": 1}]},
+            {"_type": "Fields.Field", "name": "MODE", "rangeset": [{"start": 8, "width": 2}],
+             "values": {"values": [
+                {"value": "'00'", "meaning": "Off"},
+                {"value": "'11'", "meaning": "4KB pages"}
+             ]}}
+        ]}]
+    }]"#;
+    let specs = regs_gen::parse_spec(spec).unwrap();
+    assert_eq!(specs[0].fields[1].kind, FieldKind::Res1);
+    assert!(!specs[0].readable);
+    let list = regs_gen::parse_list("TEST_EL1 Test Tst  # comment\n\n").unwrap();
+    let source = regs_gen::generate(&specs, &list).unwrap();
+    for item in [
+        "use super::access::impl_register_access_wo;\n",
+        "    TstMode {\n        Off = 0b00,\n        V4kbPages = 0b11,\n",
+        "    pub en: bool,\n    #[bits(3)]\n    _mbz0: u64,\n    #[bits(2)]\n    _mbo0: u64,\n",
+        "    const RES1: u64 = 0x30;\n",
+        "    en: 0,\n    mode: 8..=9 as TstMode,\n",
+    ] {
+        assert!(source.contains(item), "{item:?} not in\n{source}");
+    }
+
+    let overlapping = spec.replace(r#""start": 8"#, r#""start": 5"#);
+    assert_eq!(
+        regs_gen::parse_spec(&overlapping).unwrap_err().message,
+        "the bits of \"MODE\" overlap or are out of range"
+    );
+    let list = regs_gen::parse_list("TEST_EL1 Test +sve").unwrap();
+    assert_eq!(list[0].extension.as_deref(), Some("sve"));
+    assert_eq!(list[0].enum_prefix, None);
+    let source = regs_gen::generate(&specs, &list).unwrap();
+    assert!(source.contains("impl_register_access_wo!(Test, TEST_EL1, \"sve\");"));
+    let list = regs_gen::parse_list("TEST_EL1 Test +sve !ro").unwrap();
+    assert!(list[0].read_only);
+    assert_eq!(list[0].extension.as_deref(), Some("sve"));
+    assert!(regs_gen::parse_list("TEST_EL1 Test !ro +sve").is_err());
+    assert!(regs_gen::parse_list("TEST_EL1").is_err());
+    assert!(regs_gen::parse_list("+sve").is_err());
+    let list = regs_gen::parse_list("TEST_EL2 Test").unwrap();
+    assert!(regs_gen::generate(&specs, &list).is_err());
+}